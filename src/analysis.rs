@@ -0,0 +1,53 @@
+use crate::board::Board;
+use crate::eval::Eval;
+use crate::piece::{Color, Piece};
+
+const PIECES: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+/// Value of the cheapest piece among `attackers`
+fn least_valuable(board: &Board, attackers: u64, color: Color) -> i32 {
+    PIECES
+        .iter()
+        .find(|&&piece| board.get_bb(piece, color) & attackers != 0)
+        .map_or(i32::MAX, |&piece| Eval::match_piece_value(piece))
+}
+
+/// Squares of the pieces of `color` the opponent attacks that are either undefended or
+/// attacked by a cheaper piece. The king is never included, checks are not captures
+pub fn hanging_pieces(board: &Board, color: Color) -> u64 {
+    let occupied = board.get_all_occupied();
+    let mut hanging = 0;
+    let mut pieces = board.get_all_pieces(color) & !board.get_bb(Piece::King, color);
+    while pieces != 0 {
+        let square = pieces.trailing_zeros() as u8;
+        pieces &= pieces - 1;
+
+        let attackers = board.attackers_to(square, occupied);
+        let attacked_by = attackers & board.get_all_pieces(!color);
+        if attacked_by == 0 {
+            continue;
+        }
+        let Some((piece, _)) = board.get_piece_at(square) else {
+            continue;
+        };
+        let defended = attackers & board.get_all_pieces(color) != 0;
+        if !defended || least_valuable(board, attacked_by, !color) < Eval::match_piece_value(piece)
+        {
+            hanging |= 1u64 << square;
+        }
+    }
+    hanging
+}
+
+/// Squares of the enemy pieces `color` threatens to win: those it attacks that are
+/// undefended or attacked by a cheaper piece
+pub fn threats(board: &Board, color: Color) -> u64 {
+    hanging_pieces(board, !color)
+}
@@ -0,0 +1,262 @@
+use crate::piece::Color;
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (2, 1),
+    (1, 2),
+    (-1, 2),
+    (-2, 1),
+    (-2, -1),
+    (-1, -2),
+    (1, -2),
+    (2, -1),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// Offset a square by (rank, file) steps, or `None` off the board
+const fn offset_square(square: u8, dr: i8, df: i8) -> Option<u8> {
+    let rank = (square / 8) as i8 + dr;
+    let file = (square % 8) as i8 + df;
+    if rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+        Some((rank * 8 + file) as u8)
+    } else {
+        None
+    }
+}
+
+/// Squares one step away from each square, for every offset on the board
+const fn step_table(offsets: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < offsets.len() {
+            if let Some(target) = offset_square(square as u8, offsets[i].0, offsets[i].1) {
+                table[square] |= 1u64 << target;
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Ray directions, the four that walk toward higher squares first, so the first piece
+/// met on a ray is its lowest set bit for those and its highest for the others
+const RAY_DIRECTIONS: [(i8, i8); 8] = [
+    (1, 0),
+    (0, 1),
+    (1, 1),
+    (1, -1),
+    (-1, 0),
+    (0, -1),
+    (-1, -1),
+    (-1, 1),
+];
+const ROOK_RAYS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_RAYS: [usize; 4] = [2, 3, 6, 7];
+
+/// The squares from each square to the edge of the board in each of [`RAY_DIRECTIONS`]
+const fn ray_table() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let (dr, df) = RAY_DIRECTIONS[direction];
+        let mut square = 0;
+        while square < 64 {
+            let mut current = square as u8;
+            while let Some(target) = offset_square(current, dr, df) {
+                table[direction][square] |= 1u64 << target;
+                current = target;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+static RAYS: [[u64; 64]; 8] = ray_table();
+static KNIGHT_ATTACKS: [u64; 64] = step_table(&KNIGHT_OFFSETS);
+static KING_ATTACKS: [u64; 64] = step_table(&KING_OFFSETS);
+static PAWN_ATTACKS: [[u64; 64]; 2] = [
+    step_table(&[(1, 1), (1, -1)]),
+    step_table(&[(-1, 1), (-1, -1)]),
+];
+
+/// Slider attacks along the given rays, each ending on the first occupied square: the
+/// ray beyond that square is looked up and cut away
+fn ray_attacks(square: u8, occupied: u64, directions: &[usize]) -> u64 {
+    let mut attacks = 0;
+    for &direction in directions {
+        let ray = RAYS[direction][square as usize];
+        let blockers = ray & occupied;
+        attacks |= if blockers == 0 {
+            ray
+        } else {
+            let first = if direction < 4 {
+                blockers.trailing_zeros()
+            } else {
+                63 - blockers.leading_zeros()
+            };
+            ray ^ RAYS[direction][first as usize]
+        };
+    }
+    attacks
+}
+
+/// Squares attacked by a pawn of the given color
+pub fn pawn_attacks(square: u8, color: Color) -> u64 {
+    PAWN_ATTACKS[(color != Color::White) as usize][square as usize]
+}
+
+pub fn knight_attacks(square: u8) -> u64 {
+    KNIGHT_ATTACKS[square as usize]
+}
+
+pub fn king_attacks(square: u8) -> u64 {
+    KING_ATTACKS[square as usize]
+}
+
+/// Rook attacks from a square, blocked by the pieces in `occupied`
+pub fn rook_attacks(square: u8, occupied: u64) -> u64 {
+    #[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+    {
+        pext::tables().rook.attacks(square, occupied)
+    }
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+    {
+        ray_attacks(square, occupied, &ROOK_RAYS)
+    }
+}
+
+/// Bishop attacks from a square, blocked by the pieces in `occupied`
+pub fn bishop_attacks(square: u8, occupied: u64) -> u64 {
+    #[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+    {
+        pext::tables().bishop.attacks(square, occupied)
+    }
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+    {
+        ray_attacks(square, occupied, &BISHOP_RAYS)
+    }
+}
+
+pub fn queen_attacks(square: u8, occupied: u64) -> u64 {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+/// Squares a rook attacks behind the first of `blockers` on each of its rays, as if
+/// they were lifted off the board. Squares up to the blockers are not included
+pub fn xray_rook_attacks(square: u8, occupied: u64, blockers: u64) -> u64 {
+    let attacks = rook_attacks(square, occupied);
+    attacks ^ rook_attacks(square, occupied ^ (blockers & attacks))
+}
+
+/// Squares a bishop attacks behind the first of `blockers` on each of its rays, as
+/// [`xray_rook_attacks`]
+pub fn xray_bishop_attacks(square: u8, occupied: u64, blockers: u64) -> u64 {
+    let attacks = bishop_attacks(square, occupied);
+    attacks ^ bishop_attacks(square, occupied ^ (blockers & attacks))
+}
+
+/// Name of the slider attack implementation compiled in
+pub fn backend() -> &'static str {
+    if cfg!(all(
+        feature = "pext",
+        target_arch = "x86_64",
+        target_feature = "bmi2"
+    )) {
+        "pext"
+    } else {
+        "rays"
+    }
+}
+
+/// Lookup tables indexed by extracting the relevant occupancy bits with BMI2 `pext`.
+/// Enabled by the `pext` feature when compiling for a CPU with BMI2
+/// (e.g. `RUSTFLAGS="-C target-cpu=native"`), otherwise each ray is looked up in
+/// [`RAYS`] and cut off behind its first blocker
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+mod pext {
+    use super::{BISHOP_RAYS, RAYS, ROOK_RAYS, ray_attacks};
+    use std::arch::x86_64::{_pdep_u64, _pext_u64};
+    use std::sync::OnceLock;
+
+    pub(super) struct SliderTable {
+        masks: [u64; 64],
+        offsets: [usize; 64],
+        attacks: Vec<u64>,
+    }
+
+    impl SliderTable {
+        fn new(directions: &[usize]) -> Self {
+            let mut masks = [0; 64];
+            let mut offsets = [0; 64];
+            let mut attacks = Vec::new();
+
+            for square in 0..64u8 {
+                let mask = relevant_occupancy(square, directions);
+                masks[square as usize] = mask;
+                offsets[square as usize] = attacks.len();
+                for index in 0..1u64 << mask.count_ones() {
+                    // SAFETY: this module is only compiled when BMI2 is enabled for the target
+                    let occupied = unsafe { _pdep_u64(index, mask) };
+                    attacks.push(ray_attacks(square, occupied, directions));
+                }
+            }
+
+            Self {
+                masks,
+                offsets,
+                attacks,
+            }
+        }
+
+        pub(super) fn attacks(&self, square: u8, occupied: u64) -> u64 {
+            let square = square as usize;
+            // SAFETY: this module is only compiled when BMI2 is enabled for the target
+            let index = unsafe { _pext_u64(occupied, self.masks[square]) } as usize;
+            self.attacks[self.offsets[square] + index]
+        }
+    }
+
+    /// Squares whose occupancy can block a slider, excluding the last square of each ray
+    fn relevant_occupancy(square: u8, directions: &[usize]) -> u64 {
+        let mut mask = 0;
+        for &direction in directions {
+            let ray = RAYS[direction][square as usize];
+            if ray == 0 {
+                continue;
+            }
+            let edge = if direction < 4 {
+                63 - ray.leading_zeros()
+            } else {
+                ray.trailing_zeros()
+            };
+            mask |= ray & !(1u64 << edge);
+        }
+        mask
+    }
+
+    pub(super) struct Tables {
+        pub(super) rook: SliderTable,
+        pub(super) bishop: SliderTable,
+    }
+
+    pub(super) fn tables() -> &'static Tables {
+        static TABLES: OnceLock<Tables> = OnceLock::new();
+        TABLES.get_or_init(|| Tables {
+            rook: SliderTable::new(&ROOK_RAYS),
+            bishop: SliderTable::new(&BISHOP_RAYS),
+        })
+    }
+}
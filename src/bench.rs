@@ -0,0 +1,62 @@
+use crate::board::Board;
+use crate::search::{Search, SearchOptions, SearchStats};
+use std::time::{Duration, Instant};
+
+/// Positions searched by [`run`]: openings, tactical middlegames and endgames. Changing
+/// this list changes the bench signature
+pub const BENCH_POSITIONS: &[&str] = &[
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+    "r1bq1rk1/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R1BQ1RK1 w - - 0 8",
+    "2r3k1/pp3ppp/4p3/3pP3/3P4/P4N2/1P3PPP/2R3K1 b - - 0 24",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/8/4k3/8/2P5/4K3/8/8 w - - 0 1",
+    "8/5k2/8/8/3BB3/8/8/4K3 w - - 0 1",
+];
+
+/// Depth searched by `oxm8 bench` when none is given
+pub const DEFAULT_DEPTH: u8 = 6;
+
+/// Totals over all bench positions. `nodes` only depends on the engine, never on the
+/// machine, so it serves as a signature of the search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchReport {
+    pub positions: usize,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Search statistics summed over the positions, zero without the `stats` feature
+    pub stats: SearchStats,
+}
+
+impl BenchReport {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.elapsed.as_millis().max(1)) as u64
+    }
+}
+
+/// Search every bench position to `depth` on a single thread with a fresh
+/// transposition table, calling `on_position` with each FEN and its node count
+pub fn run(depth: u8, mut on_position: impl FnMut(&str, u64)) -> BenchReport {
+    let start = Instant::now();
+    let mut nodes = 0;
+    let mut stats = SearchStats::default();
+    for fen in BENCH_POSITIONS {
+        let board = Board::try_from_fen(fen).expect("bench positions are valid FEN");
+        let mut search = Search::new(SearchOptions::default());
+        let result = search.search(&board, depth);
+        on_position(fen, result.nodes);
+        nodes += result.nodes;
+        stats += search.stats();
+    }
+    BenchReport {
+        positions: BENCH_POSITIONS.len(),
+        nodes,
+        elapsed: start.elapsed(),
+        stats,
+    }
+}
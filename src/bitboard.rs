@@ -0,0 +1,127 @@
+use crate::piece::Color;
+
+pub const FILE_A: u64 = 0x0101_0101_0101_0101;
+pub const FILE_H: u64 = 0x8080_8080_8080_8080;
+pub const RANK_1: u64 = 0x0000_0000_0000_00FF;
+pub const RANK_8: u64 = 0xFF00_0000_0000_0000;
+pub const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+pub const DARK_SQUARES: u64 = !LIGHT_SQUARES;
+
+const MAIN_DIAGONAL: u64 = 0x8040_2010_0804_0201;
+const MAIN_ANTI_DIAGONAL: u64 = 0x0102_0408_1020_4080;
+
+pub const fn file_mask(file: u8) -> u64 {
+    FILE_A << file
+}
+
+pub const fn rank_mask(rank: u8) -> u64 {
+    RANK_1 << (rank * 8)
+}
+
+/// The files either side of `file`, without `file` itself
+pub const fn adjacent_files(file: u8) -> u64 {
+    east(file_mask(file)) | west(file_mask(file))
+}
+
+/// The a1-h8 direction diagonal through `square`
+pub const fn diagonal_mask(square: u8) -> u64 {
+    let offset = (square / 8) as i8 - (square % 8) as i8;
+    if offset >= 0 {
+        MAIN_DIAGONAL << (offset * 8)
+    } else {
+        MAIN_DIAGONAL >> (-offset * 8)
+    }
+}
+
+/// The a8-h1 direction diagonal through `square`
+pub const fn anti_diagonal_mask(square: u8) -> u64 {
+    let offset = (square / 8 + square % 8) as i8 - 7;
+    if offset >= 0 {
+        MAIN_ANTI_DIAGONAL << (offset * 8)
+    } else {
+        MAIN_ANTI_DIAGONAL >> (-offset * 8)
+    }
+}
+
+pub const fn north(bb: u64) -> u64 {
+    bb << 8
+}
+
+pub const fn south(bb: u64) -> u64 {
+    bb >> 8
+}
+
+/// One file towards h, dropping what would wrap around to the a-file. The other
+/// sideways shifts work the same way
+pub const fn east(bb: u64) -> u64 {
+    (bb << 1) & !FILE_A
+}
+
+pub const fn west(bb: u64) -> u64 {
+    (bb >> 1) & !FILE_H
+}
+
+pub const fn north_east(bb: u64) -> u64 {
+    (bb << 9) & !FILE_A
+}
+
+pub const fn north_west(bb: u64) -> u64 {
+    (bb << 7) & !FILE_H
+}
+
+pub const fn south_east(bb: u64) -> u64 {
+    (bb >> 7) & !FILE_A
+}
+
+pub const fn south_west(bb: u64) -> u64 {
+    (bb >> 9) & !FILE_H
+}
+
+/// One rank towards the opponent of `color`
+pub const fn forward(bb: u64, color: Color) -> u64 {
+    match color {
+        Color::White => north(bb),
+        Color::Black => south(bb),
+    }
+}
+
+/// Every square on or above a square of `bb`
+pub const fn north_fill(mut bb: u64) -> u64 {
+    bb |= bb << 8;
+    bb |= bb << 16;
+    bb | bb << 32
+}
+
+/// Every square on or below a square of `bb`
+pub const fn south_fill(mut bb: u64) -> u64 {
+    bb |= bb >> 8;
+    bb |= bb >> 16;
+    bb | bb >> 32
+}
+
+/// The whole files of the squares of `bb`
+pub const fn file_fill(bb: u64) -> u64 {
+    north_fill(bb) | south_fill(bb)
+}
+
+/// Squares attacked by the pawns of `color` on `pawns`
+pub const fn pawn_attacks(pawns: u64, color: Color) -> u64 {
+    match color {
+        Color::White => north_east(pawns) | north_west(pawns),
+        Color::Black => south_east(pawns) | south_west(pawns),
+    }
+}
+
+/// Squares ahead of the pawns of `color` on their own files
+pub const fn front_span(pawns: u64, color: Color) -> u64 {
+    match color {
+        Color::White => north(north_fill(pawns)),
+        Color::Black => south(south_fill(pawns)),
+    }
+}
+
+/// Squares the pawns of `color` could attack as they advance
+pub const fn pawn_attack_span(pawns: u64, color: Color) -> u64 {
+    let span = front_span(pawns, color);
+    east(span) | west(span)
+}
@@ -1,7 +1,14 @@
+use crate::attacks;
+use crate::bitboard::LIGHT_SQUARES;
 use crate::fen::*;
-use crate::piece::{Color, Piece, piece_to_sp_char};
-
-#[derive(Clone, Copy)]
+use crate::piece::{Color, Piece, piece_to_char};
+use crate::square::Square;
+use crate::util;
+use crate::zobrist;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy)]
 pub struct Board {
     pub white_pawns: u64,
     pub white_knights: u64,
@@ -21,12 +28,130 @@ pub struct Board {
     pub halfmove_clock: u16,
     pub fullmove_number: u16,
     pub en_passant: Option<u8>, // index 0..63
-    pub castling_rights: u8,    // 4 bits: KQkq
+    pub castling_rights: u8, // WHITE_KINGSIDE | WHITE_QUEENSIDE | BLACK_KINGSIDE | BLACK_QUEENSIDE
+    /// Home square of the rook behind each castling right, indexed like the right bits.
+    /// Only differs from the corners in Chess960 positions read from X-FEN or Shredder-FEN
+    pub castling_rooks: [u8; 4],
+    /// Checks delivered by each side, only counted under three-check rules
+    pub white_checks: u8,
+    pub black_checks: u8,
+
+    /// Zobrist key, kept up to date by `set_piece`, `remove_piece` and `make_move`
+    key: u64,
+    /// The piece on each square, mirroring the bitboards and kept in sync the same way
+    mailbox: [Option<(Piece, Color)>; 64],
+    /// Enemy pieces giving check to the side to move, updated with the key
+    checkers: u64,
+}
+
+// Castling right flags stored in `Board::castling_rights`
+pub const WHITE_KINGSIDE: u8 = 0b0001;
+pub const WHITE_QUEENSIDE: u8 = 0b0010;
+pub const BLACK_KINGSIDE: u8 = 0b0100;
+pub const BLACK_QUEENSIDE: u8 = 0b1000;
+
+/// Rook home squares for the standard starting position: h1, a1, h8, a8
+pub const STANDARD_CASTLING_ROOKS: [u8; 4] = [7, 0, 63, 56];
+
+/// Everything [`Board::make_move`] overwrites, so [`Board::unmake_move`] can restore
+/// the previous position without keeping a copy of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoState {
+    pub mv: crate::moves::Move,
+    /// Captured piece and the square it stood on (differs from the target for en passant)
+    pub captured: Option<(Piece, u8)>,
+    pub castling_rights: u8,
+    pub en_passant: Option<u8>,
+    pub halfmove_clock: u16,
+    pub fullmove_number: u16,
+    pub key: u64,
+    pub checkers: u64,
+}
+
+/// A reason a position could not arise in a legal game, reported by [`Board::validate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionError {
+    /// A side without exactly one king
+    KingCount {
+        color: Color,
+        found: u32,
+    },
+    PawnOnBackRank {
+        square: u8,
+    },
+    /// The side that just moved left its king in check
+    OpponentInCheck,
+    /// A castling right whose king or rook is not on its home square
+    InconsistentCastling {
+        right: u8,
+    },
+    /// An en passant square not behind a pawn that just advanced two squares
+    InvalidEnPassant {
+        square: u8,
+    },
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PositionError::KingCount { color, found } => {
+                write!(f, "{:?} has {} kings instead of one", color, found)
+            }
+            PositionError::PawnOnBackRank { square } => {
+                write!(f, "pawn on {}", crate::util::u8_to_pos(square))
+            }
+            PositionError::OpponentInCheck => f.write_str("the side not to move is in check"),
+            PositionError::InconsistentCastling { right } => write!(
+                f,
+                "castling right {:#06b} without its king and rook in place",
+                right
+            ),
+            PositionError::InvalidEnPassant { square } => write!(
+                f,
+                "en passant square {} has no pawn that just moved two squares",
+                crate::util::u8_to_pos(square)
+            ),
+        }
+    }
+}
+
+impl core::error::Error for PositionError {}
+
+/// ASCII diagram from White's side, uppercase for White and `.` for empty squares
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rank in (0..8u8).rev() {
+            write!(f, "{} ", rank + 1)?;
+            for file in 0..8 {
+                let symbol = match self.get_piece_at(rank * 8 + file) {
+                    Some((piece, color)) => piece_to_char(piece, color),
+                    None => '.',
+                };
+                write!(f, " {}", symbol)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "   a b c d e f g h")
+    }
+}
+
+impl TryFrom<&str> for Board {
+    type Error = FenError;
+
+    fn try_from(fen: &str) -> Result<Self, FenError> {
+        parse_fen(fen)
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Board {
     pub fn new() -> Self {
-        Self {
+        let mut board = Self {
             white_pawns: 0,
             white_knights: 0,
             white_bishops: 0,
@@ -45,8 +170,16 @@ impl Board {
             halfmove_clock: 0,
             fullmove_number: 1,
             en_passant: None,
-            castling_rights: 0b1111,
-        }
+            castling_rights: WHITE_KINGSIDE | WHITE_QUEENSIDE | BLACK_KINGSIDE | BLACK_QUEENSIDE,
+            castling_rooks: STANDARD_CASTLING_ROOKS,
+            white_checks: 0,
+            black_checks: 0,
+            key: 0,
+            mailbox: [None; 64],
+            checkers: 0,
+        };
+        board.refresh_hash();
+        board
     }
 
     pub fn get_bb(&self, piece: Piece, color: Color) -> u64 {
@@ -83,52 +216,264 @@ impl Board {
         }
     }
 
-    pub fn set_piece(&mut self, piece: Piece, color: Color, square: u8) {
-        let bitboard = self.get_bb_mut(piece, color);
-        *bitboard |= 1 << square;
+    pub fn set_piece(&mut self, piece: Piece, color: Color, square: impl Into<Square>) {
+        self.place(piece, color, square.into().index());
+        self.checkers = self.find_checkers();
+    }
+
+    pub fn remove_piece(&mut self, piece: Piece, color: Color, square: impl Into<Square>) {
+        self.lift(piece, color, square.into().index());
+        self.checkers = self.find_checkers();
     }
 
-    pub fn remove_piece(&mut self, piece: Piece, color: Color, square: u8) {
+    fn place(&mut self, piece: Piece, color: Color, square: u8) {
         let bitboard = self.get_bb_mut(piece, color);
-        *bitboard &= !(1 << square);
+        if *bitboard & (1 << square) == 0 {
+            *bitboard |= 1 << square;
+            self.key ^= zobrist::piece_key(piece, color, square);
+        }
+        self.mailbox[square as usize] = Some((piece, color));
     }
 
-    pub fn get_piece_at(&self, square: u8) -> Option<(Piece, Color)> {
-        if self.white_pawns & (1 << square) != 0 { return Some((Piece::Pawn, Color::White)); }
-        if self.white_knights & (1 << square) != 0 { return Some((Piece::Knight, Color::White)); }
-        if self.white_bishops & (1 << square) != 0 { return Some((Piece::Bishop, Color::White)); }
-        if self.white_rooks & (1 << square) != 0 { return Some((Piece::Rook, Color::White)); }
-        if self.white_queens & (1 << square) != 0 { return Some((Piece::Queen, Color::White)); }
-        if self.white_king & (1 << square) != 0 { return Some((Piece::King, Color::White)); }
-        if self.black_pawns & (1 << square) != 0 { return Some((Piece::Pawn, Color::Black)); }
-        if self.black_knights & (1 << square) != 0 { return Some((Piece::Knight, Color::Black)); }
-        if self.black_bishops & (1 << square) != 0 { return Some((Piece::Bishop, Color::Black)); }
-        if self.black_rooks & (1 << square) != 0 { return Some((Piece::Rook, Color::Black)); }
-        if self.black_queens & (1 << square) != 0 { return Some((Piece::Queen, Color::Black)); }
-        if self.black_king & (1 << square) != 0 { return Some((Piece::King, Color::Black)); }
-        None
+    fn lift(&mut self, piece: Piece, color: Color, square: u8) {
+        let bitboard = self.get_bb_mut(piece, color);
+        if *bitboard & (1 << square) != 0 {
+            *bitboard &= !(1 << square);
+            self.key ^= zobrist::piece_key(piece, color, square);
+        }
+        if self.mailbox[square as usize] == Some((piece, color)) {
+            self.mailbox[square as usize] = None;
+        }
     }
 
-    pub fn display(&self) {
-        let mut board = String::new();
-        for rank in (0..8).rev() {
-            for file in 0..8 {
-                let square = rank * 8 + file;
-                match self.get_piece_at(square) {
-                    Some((piece, color)) => board.push(piece_to_sp_char(piece, color)),
-                    None => board.push('·'),
-                }
-                board.push(' ');
+    /// Castling rights that survive a move from or to `square`. Leaving the king's
+    /// square or a castling rook's home square, or capturing on one, gives up the rights
+    /// that depend on it. Asked before the move, while the king is still at home
+    pub(crate) fn castling_rights_kept(&self, square: u8) -> u8 {
+        let mut kept = !0;
+        if self.castling_rights == 0 {
+            return kept;
+        }
+        for (index, &rook) in self.castling_rooks.iter().enumerate() {
+            let color = if index < 2 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            if rook == square || self.get_bb(Piece::King, color) & (1 << square) != 0 {
+                kept &= !(1 << index);
             }
-            board.push('\n');
         }
-        println!("{}", board);
+        kept
+    }
+
+    /// Rook origin and destination for a `color` king castling onto `king_to`: the
+    /// rook comes from its home square in `castling_rooks` and lands beside the king,
+    /// on the f-file or the d-file
+    pub(crate) fn castling_rook_squares(&self, color: Color, king_to: u8) -> (u8, u8) {
+        let kingside = king_to % 8 == 6;
+        let index = match (color, kingside) {
+            (Color::White, true) => 0,
+            (Color::White, false) => 1,
+            (Color::Black, true) => 2,
+            (Color::Black, false) => 3,
+        };
+        let rook_to = if kingside { king_to - 1 } else { king_to + 1 };
+        (self.castling_rooks[index], rook_to)
     }
 
+    /// The piece on a square, read from the mailbox rather than the bitboards
+    pub fn get_piece_at(&self, square: impl Into<Square>) -> Option<(Piece, Color)> {
+        self.mailbox[square.into().index() as usize]
+    }
+
+    /// Print the board to stdout in the richest style the terminal supports
+    #[cfg(feature = "std")]
+    pub fn display(&self) {
+        use crate::render::{BoardStyle, RenderOptions, ascii};
+
+        println!("{}", ascii(self, &RenderOptions::new(BoardStyle::detect())));
+    }
+
+    /// Build a board from a FEN string. Panics on invalid input, see [`Board::try_from_fen`]
     pub fn from_fen(fen: &str) -> Self {
         parse_fen(fen).expect("Invalid FEN string")
     }
 
+    pub fn try_from_fen(fen: &str) -> Result<Self, FenError> {
+        parse_fen(fen)
+    }
+
+    /// Check that the position could occur in a game: one king per side, no pawns on
+    /// the first or last rank, the side that just moved not in check, castling rights
+    /// backed by a king and rook at home, and a plausible en passant square
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for color in [Color::White, Color::Black] {
+            let found = self.get_bb(Piece::King, color).count_ones();
+            if found != 1 {
+                return Err(PositionError::KingCount { color, found });
+            }
+        }
+
+        const BACK_RANKS: u64 = 0xFF00_0000_0000_00FF;
+        let pawns_on_back_rank = (self.white_pawns | self.black_pawns) & BACK_RANKS;
+        if pawns_on_back_rank != 0 {
+            return Err(PositionError::PawnOnBackRank {
+                square: pawns_on_back_rank.trailing_zeros() as u8,
+            });
+        }
+
+        let (us, them) = if self.to_move {
+            (Color::White, Color::Black)
+        } else {
+            (Color::Black, Color::White)
+        };
+        if crate::moves::Move::is_in_check(self, them) {
+            return Err(PositionError::OpponentInCheck);
+        }
+
+        for (index, (right, color)) in [
+            (WHITE_KINGSIDE, Color::White),
+            (WHITE_QUEENSIDE, Color::White),
+            (BLACK_KINGSIDE, Color::Black),
+            (BLACK_QUEENSIDE, Color::Black),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if self.castling_rights & right == 0 {
+                continue;
+            }
+            let back_rank = if color == Color::White { 0 } else { 7 };
+            let king = self.get_bb(Piece::King, color).trailing_zeros() as u8;
+            let rook = self.castling_rooks[index];
+            let kingside = right & (WHITE_KINGSIDE | BLACK_KINGSIDE) != 0;
+            let in_place = king / 8 == back_rank
+                && rook / 8 == back_rank
+                && self.get_piece_at(rook) == Some((Piece::Rook, color))
+                && (rook % 8 > king % 8) == kingside;
+            if !in_place {
+                return Err(PositionError::InconsistentCastling { right });
+            }
+        }
+
+        if let Some(square) = self.en_passant {
+            // The pawn that moved stands one square further from its own side
+            let (expected_rank, pawn_square, origin) = match us {
+                Color::White => (5, square.wrapping_sub(8), square.wrapping_add(8)),
+                Color::Black => (2, square.wrapping_add(8), square.wrapping_sub(8)),
+            };
+            let plausible = square / 8 == expected_rank
+                && self.get_piece_at(square).is_none()
+                && self.get_piece_at(origin).is_none()
+                && self.get_piece_at(pawn_square) == Some((Piece::Pawn, them));
+            if !plausible {
+                return Err(PositionError::InvalidEnPassant { square });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Neither side can possibly mate: bare kings, a single minor piece, or only
+    /// bishops that all stand on squares of the same color
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy_or_pawns = self.white_pawns
+            | self.black_pawns
+            | self.white_rooks
+            | self.black_rooks
+            | self.white_queens
+            | self.black_queens;
+        if heavy_or_pawns != 0 {
+            return false;
+        }
+
+        let knights = self.white_knights | self.black_knights;
+        let bishops = self.white_bishops | self.black_bishops;
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+
+        knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    }
+
+    /// The same position with the colours swapped and the board flipped top to bottom,
+    /// so White's pieces stand where Black's stood and the other side is to move
+    pub fn mirror(&self) -> Self {
+        let flip = u64::swap_bytes;
+        let rooks = self.castling_rooks;
+        let mut board = Self {
+            white_pawns: flip(self.black_pawns),
+            white_knights: flip(self.black_knights),
+            white_bishops: flip(self.black_bishops),
+            white_rooks: flip(self.black_rooks),
+            white_queens: flip(self.black_queens),
+            white_king: flip(self.black_king),
+            black_pawns: flip(self.white_pawns),
+            black_knights: flip(self.white_knights),
+            black_bishops: flip(self.white_bishops),
+            black_rooks: flip(self.white_rooks),
+            black_queens: flip(self.white_queens),
+            black_king: flip(self.white_king),
+            to_move: !self.to_move,
+            en_passant: self.en_passant.map(|square| square ^ 56),
+            castling_rights: (self.castling_rights & 0b0011) << 2
+                | (self.castling_rights >> 2) & 0b0011,
+            castling_rooks: [rooks[2] ^ 56, rooks[3] ^ 56, rooks[0] ^ 56, rooks[1] ^ 56],
+            white_checks: self.black_checks,
+            black_checks: self.white_checks,
+            ..*self
+        };
+        board.mailbox = core::array::from_fn(|square| {
+            self.mailbox[square ^ 56].map(|(piece, color)| (piece, !color))
+        });
+        board.refresh_hash();
+        board
+    }
+
+    /// Zobrist hash of the position, maintained incrementally
+    pub fn hash(&self) -> u64 {
+        self.key
+    }
+
+    /// Recompute the hash and the cached checkers from scratch. Needed after assigning
+    /// `to_move`, `castling_rights` or `en_passant` directly
+    pub fn refresh_hash(&mut self) {
+        self.key = zobrist::hash(self);
+        self.checkers = self.find_checkers();
+    }
+
+    /// Enemy pieces giving check to the side to move, cached as moves are made
+    pub fn checkers(&self) -> u64 {
+        self.checkers
+    }
+
+    fn find_checkers(&self) -> u64 {
+        let color = if self.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let king = self.get_bb(Piece::King, color);
+        if king == 0 {
+            return 0;
+        }
+        self.attackers_to(king.trailing_zeros() as u8, self.get_all_occupied())
+            & self.get_all_pieces(!color)
+    }
+
+    /// Hash contribution of the side to move, castling rights and en passant square
+    fn state_key(&self) -> u64 {
+        let mut key = zobrist::castling_key(self.castling_rights);
+        if let Some(square) = self.en_passant {
+            key ^= zobrist::en_passant_key(square % 8);
+        }
+        if !self.to_move {
+            key ^= zobrist::side_key();
+        }
+        key
+    }
+
     pub fn get_all_pieces(&self, color: Color) -> u64 {
         match color {
             Color::White => {
@@ -154,6 +499,62 @@ impl Board {
         self.get_all_pieces(Color::White) | self.get_all_pieces(Color::Black)
     }
 
+    /// Pieces of both colors attacking `square`, with sliders blocked by `occupied`. Mask
+    /// with [`Board::get_all_pieces`] for one side, or pass a smaller occupancy to see
+    /// through pieces that have been captured off the square
+    pub fn attackers_to(&self, square: impl Into<Square>, occupied: u64) -> u64 {
+        let square = square.into().index();
+        let queens = self.white_queens | self.black_queens;
+        let diagonal = self.white_bishops | self.black_bishops | queens;
+        let orthogonal = self.white_rooks | self.black_rooks | queens;
+        // A pawn attacks the square from where an enemy pawn on it would attack
+        attacks::pawn_attacks(square, Color::Black) & self.white_pawns
+            | attacks::pawn_attacks(square, Color::White) & self.black_pawns
+            | attacks::knight_attacks(square) & (self.white_knights | self.black_knights)
+            | attacks::king_attacks(square) & (self.white_king | self.black_king)
+            | attacks::bishop_attacks(square, occupied) & diagonal
+            | attacks::rook_attacks(square, occupied) & orthogonal
+    }
+
+    /// Pieces among `shields` standing alone between the king of `king_color` and a
+    /// slider of `slider_color` that would attack it through them
+    fn king_blockers(&self, king_color: Color, slider_color: Color, shields: u64) -> u64 {
+        let king = self.get_bb(Piece::King, king_color);
+        if king == 0 {
+            return 0;
+        }
+        let king_square = king.trailing_zeros() as u8;
+        let queens = self.get_bb(Piece::Queen, slider_color);
+        let rooks = self.get_bb(Piece::Rook, slider_color) | queens;
+        let bishops = self.get_bb(Piece::Bishop, slider_color) | queens;
+        let occupied = self.get_all_occupied();
+
+        // Sliders seen from the king through one shield, and the shield in between
+        let mut blockers = 0;
+        for (snipers, xray_attacks) in [
+            (rooks, attacks::xray_rook_attacks as fn(u8, u64, u64) -> u64),
+            (bishops, attacks::xray_bishop_attacks),
+        ] {
+            let mut snipers = snipers & xray_attacks(king_square, occupied, shields);
+            while snipers != 0 {
+                let sniper = snipers.trailing_zeros() as u8;
+                snipers &= snipers - 1;
+                blockers |= util::between(king_square, sniper) & occupied;
+            }
+        }
+        blockers & shields
+    }
+
+    /// Pieces of `color` pinned to their king by an enemy rook, bishop or queen
+    pub fn pinned_pieces(&self, color: Color) -> u64 {
+        self.king_blockers(color, !color, self.get_all_pieces(color))
+    }
+
+    /// Pieces of `color` whose move would uncover a check from one of its own sliders
+    pub fn discovered_check_candidates(&self, color: Color) -> u64 {
+        self.king_blockers(!color, color, self.get_all_pieces(color))
+    }
+
     pub fn get_piece_squares(&self, color: Color, piece: Piece) -> Vec<u8> {
         let mut squares = Vec::new();
         let bitboard = self.get_bb(piece, color);
@@ -168,36 +569,88 @@ impl Board {
         squares
     }
 
-    pub fn make_move(&mut self, mv: &crate::moves::Moves) {
+    /// Whether the side to move may play `mv` here. Only the moving piece's own moves are
+    /// generated, and the move is played out on a copy to make sure the king is safe
+    pub fn is_legal(&self, mv: &crate::moves::Move) -> bool {
+        use crate::moves::Move;
+
+        let color = if self.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let piece_moves = match self.get_piece_at(mv.from) {
+            Some((piece, c)) if c == color => match piece {
+                Piece::Pawn => Move::pawn_moves(self, mv.from, color),
+                Piece::Knight => Move::knight_moves(self, mv.from, color),
+                Piece::Bishop => Move::bishop_moves(self, mv.from, color),
+                Piece::Rook => Move::rook_moves(self, mv.from, color),
+                Piece::Queen => Move::queen_moves(self, mv.from, color),
+                Piece::King => Move::king_moves(self, mv.from, color),
+            },
+            _ => return false,
+        };
+        piece_moves.contains(mv) && Move::is_legal_move(self, mv, color)
+    }
+
+    /// Play a move, returning the state needed to take it back with [`Board::unmake_move`]
+    /// Besides the pieces, the side to move is flipped and the fullmove number advances
+    /// after Black's move, so the board always describes a complete position. The halfmove
+    /// clock resets on pawn moves and captures, and the en passant square only survives
+    /// the double pawn push that created it
+    pub fn make_move(&mut self, mv: &crate::moves::Move) -> UndoState {
         use crate::moves::MoveType;
 
         // Get the piece being moved
         let (piece, color) = self.get_piece_at(mv.from).expect("No piece at from square");
 
+        let captured = match mv.move_type {
+            MoveType::Capture | MoveType::PromotionCapture { .. } => self
+                .get_piece_at(mv.to)
+                .map(|(captured_piece, _)| (captured_piece, mv.to)),
+            MoveType::EnPassant if color == Color::White => Some((Piece::Pawn, mv.to - 8)),
+            MoveType::EnPassant => Some((Piece::Pawn, mv.to + 8)),
+            _ => None,
+        };
+        let undo = UndoState {
+            mv: *mv,
+            captured,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            key: self.key,
+            checkers: self.checkers,
+        };
+        // Piece keys are handled by set_piece and remove_piece, the rest is swapped at the end
+        self.key ^= self.state_key();
+        // Moving the king or a rook, or capturing a rook at home, loses castling rights
+        let castling_kept = self.castling_rights_kept(mv.from) & self.castling_rights_kept(mv.to);
+
         match mv.move_type {
             MoveType::Normal => {
                 // Remove piece from source square
-                self.remove_piece(piece, color, mv.from);
+                self.lift(piece, color, mv.from);
                 // Add piece to destination square
-                self.set_piece(piece, color, mv.to);
+                self.place(piece, color, mv.to);
             }
 
             MoveType::Capture => {
                 // Remove captured piece
                 if let Some((captured_piece, captured_color)) = self.get_piece_at(mv.to) {
-                    self.remove_piece(captured_piece, captured_color, mv.to);
+                    self.lift(captured_piece, captured_color, mv.to);
                 }
                 // Remove piece from source square
-                self.remove_piece(piece, color, mv.from);
+                self.lift(piece, color, mv.from);
                 // Add piece to destination square
-                self.set_piece(piece, color, mv.to);
+                self.place(piece, color, mv.to);
             }
 
             MoveType::Double => {
                 // Remove piece from source square
-                self.remove_piece(piece, color, mv.from);
+                self.lift(piece, color, mv.from);
                 // Add piece to destination square
-                self.set_piece(piece, color, mv.to);
+                self.place(piece, color, mv.to);
                 // Set en passant square (the square the pawn passed over)
                 let en_passant_square = if color == Color::White {
                     mv.from + 8
@@ -209,30 +662,26 @@ impl Board {
 
             MoveType::EnPassant => {
                 // Remove piece from source square
-                self.remove_piece(piece, color, mv.from);
+                self.lift(piece, color, mv.from);
                 // Add piece to destination square
-                self.set_piece(piece, color, mv.to);
+                self.place(piece, color, mv.to);
                 // Remove the captured pawn (not on the destination square)
                 let captured_pawn_square = if color == Color::White {
                     mv.to - 8
                 } else {
                     mv.to + 8
                 };
-                let enemy_color = if color == Color::White {
-                    Color::Black
-                } else {
-                    Color::White
-                };
-                self.remove_piece(Piece::Pawn, enemy_color, captured_pawn_square);
+                let enemy_color = color.opposite();
+                self.lift(Piece::Pawn, enemy_color, captured_pawn_square);
             }
 
             MoveType::Promotion {
                 piece: promoted_piece,
             } => {
                 // Remove pawn from source square
-                self.remove_piece(Piece::Pawn, color, mv.from);
+                self.lift(Piece::Pawn, color, mv.from);
                 // Add promoted piece to destination square
-                self.set_piece(promoted_piece, color, mv.to);
+                self.place(promoted_piece, color, mv.to);
             }
 
             MoveType::PromotionCapture {
@@ -240,36 +689,22 @@ impl Board {
             } => {
                 // Remove captured piece
                 if let Some((captured_piece, captured_color)) = self.get_piece_at(mv.to) {
-                    self.remove_piece(captured_piece, captured_color, mv.to);
+                    self.lift(captured_piece, captured_color, mv.to);
                 }
                 // Remove pawn from source square
-                self.remove_piece(Piece::Pawn, color, mv.from);
+                self.lift(Piece::Pawn, color, mv.from);
                 // Add promoted piece to destination square
-                self.set_piece(promoted_piece, color, mv.to);
+                self.place(promoted_piece, color, mv.to);
             }
 
             MoveType::Castle => {
-                // Move the king
-                self.remove_piece(piece, color, mv.from);
-                self.set_piece(piece, color, mv.to);
-
-                // Move the rook
-                let (rook_from, rook_to) = match (color, mv.to) {
-                    (Color::White, 6) => (7, 5),    // White kingside: rook from h1 to f1
-                    (Color::White, 2) => (0, 3),    // White queenside: rook from a1 to d1
-                    (Color::Black, 62) => (63, 61), // Black kingside: rook from h8 to f8
-                    (Color::Black, 58) => (56, 59), // Black queenside: rook from a8 to d8
-                    _ => panic!("Invalid castling move"),
-                };
-
-                self.remove_piece(Piece::Rook, color, rook_from);
-                self.set_piece(Piece::Rook, color, rook_to);
-
-                // Remove all castling rights for this color
-                match color {
-                    Color::White => self.castling_rights &= 0b1100, // Remove KQ, keep kq
-                    Color::Black => self.castling_rights &= 0b0011, // Remove kq, keep KQ
-                }
+                // Both pieces leave before either lands, as in Chess960 the king may
+                // land on the rook's square or the rook on the king's
+                let (rook_from, rook_to) = self.castling_rook_squares(color, mv.to);
+                self.lift(piece, color, mv.from);
+                self.lift(Piece::Rook, color, rook_from);
+                self.place(piece, color, mv.to);
+                self.place(Piece::Rook, color, rook_to);
             }
         }
 
@@ -278,28 +713,7 @@ impl Board {
             self.en_passant = None;
         }
 
-        // Update castling rights based on piece moves
-        match (piece, color, mv.from) {
-            // King moves - remove all castling rights for this color
-            (Piece::King, Color::White, 4) => self.castling_rights &= 0b1100, // Remove KQ
-            (Piece::King, Color::Black, 60) => self.castling_rights &= 0b0011, // Remove kq
-
-            // Rook moves - remove specific castling rights
-            (Piece::Rook, Color::White, 0) => self.castling_rights &= 0b1101, // Remove Q (queenside)
-            (Piece::Rook, Color::White, 7) => self.castling_rights &= 0b1110, // Remove K (kingside)
-            (Piece::Rook, Color::Black, 56) => self.castling_rights &= 0b0111, // Remove q (queenside)
-            (Piece::Rook, Color::Black, 63) => self.castling_rights &= 0b1011, // Remove k (kingside)
-            _ => {}
-        }
-
-        // If a rook is captured, remove castling rights
-        match mv.to {
-            0 => self.castling_rights &= 0b1101, // White queenside rook captured
-            7 => self.castling_rights &= 0b1110, // White kingside rook captured
-            56 => self.castling_rights &= 0b0111, // Black queenside rook captured
-            63 => self.castling_rights &= 0b1011, // Black kingside rook captured
-            _ => {}
-        }
+        self.castling_rights &= castling_kept;
 
         // Update turn
         self.to_move = !self.to_move;
@@ -320,5 +734,52 @@ impl Board {
         } else {
             self.halfmove_clock += 1;
         }
+
+        self.key ^= self.state_key();
+        self.checkers = self.find_checkers();
+        undo
+    }
+
+    /// Take back the move recorded in `undo`, which must be the last move played
+    pub fn unmake_move(&mut self, undo: &UndoState) {
+        use crate::moves::MoveType;
+
+        self.to_move = !self.to_move;
+        let (color, enemy_color) = if self.to_move {
+            (Color::White, Color::Black)
+        } else {
+            (Color::Black, Color::White)
+        };
+        let mv = undo.mv;
+
+        match mv.move_type {
+            MoveType::Promotion { piece } | MoveType::PromotionCapture { piece } => {
+                self.lift(piece, color, mv.to);
+                self.place(Piece::Pawn, color, mv.from);
+            }
+            MoveType::Castle => {
+                let (rook_from, rook_to) = self.castling_rook_squares(color, mv.to);
+                self.lift(Piece::King, color, mv.to);
+                self.lift(Piece::Rook, color, rook_to);
+                self.place(Piece::King, color, mv.from);
+                self.place(Piece::Rook, color, rook_from);
+            }
+            _ => {
+                let (piece, _) = self.get_piece_at(mv.to).expect("No piece at to square");
+                self.lift(piece, color, mv.to);
+                self.place(piece, color, mv.from);
+            }
+        }
+
+        if let Some((piece, square)) = undo.captured {
+            self.place(piece, enemy_color, square);
+        }
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.key = undo.key;
+        self.checkers = undo.checkers;
     }
 }
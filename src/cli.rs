@@ -0,0 +1,676 @@
+use crate::database::GameDatabase;
+use crate::explorer::{self, ExplorerSource};
+use crate::fen::{FenError, START_FEN};
+use crate::game::{Game, GameResult};
+use crate::moves::MoveType;
+use crate::notation::MoveParseError;
+use crate::piece::Color;
+use crate::render::{BoardStyle, Highlights, RenderOptions, THEMES, Theme, ascii};
+use crate::search::SearchLimits;
+use crate::util::pos_to_u8;
+use std::io::{self, BufRead, Write};
+use std::ops::{Deref, DerefMut};
+use std::time::Instant;
+
+/// A [`Game`] played on the command line: draws the board, reads moves and commands
+/// and keeps the display settings. Everything else is the game's, reached through
+/// `Deref`
+pub struct ChessGame {
+    game: Game,
+    /// Draw the board from Black's side
+    flipped: bool,
+    /// `None` picks a style for the terminal when [`ChessGame::run`] starts
+    style: Option<BoardStyle>,
+    /// Square whose legal destinations are marked, with the hash of the position they
+    /// were asked for in so any move clears them
+    shown: Option<(u8, u64)>,
+    /// Games for the `explore` command
+    database: Option<GameDatabase>,
+}
+
+impl From<Game> for ChessGame {
+    fn from(game: Game) -> Self {
+        Self {
+            game,
+            flipped: false,
+            style: None,
+            shown: None,
+            database: None,
+        }
+    }
+}
+
+impl Deref for ChessGame {
+    type Target = Game;
+
+    fn deref(&self) -> &Game {
+        &self.game
+    }
+}
+
+impl DerefMut for ChessGame {
+    fn deref_mut(&mut self) -> &mut Game {
+        &mut self.game
+    }
+}
+
+impl ChessGame {
+    pub fn new() -> Self {
+        Game::new().into()
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Game::from_fen(fen).map(Self::from)
+    }
+
+    /// The game without its terminal
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+
+    /// See [`Game::with_rules`]
+    pub fn with_rules(mut self, rules: impl crate::variant::Rules + 'static) -> Self {
+        self.game = self.game.with_rules(rules);
+        self
+    }
+
+    /// See [`Game::with_info`]
+    pub fn with_info(mut self, info: crate::game::GameInfo) -> Self {
+        self.game = self.game.with_info(info);
+        self
+    }
+
+    /// See [`Game::with_observer`]
+    pub fn with_observer(mut self, observer: impl crate::game::GameObserver + 'static) -> Self {
+        self.game = self.game.with_observer(observer);
+        self
+    }
+
+    /// See [`Game::with_engine`]. The board is drawn from the player's side
+    pub fn with_engine(mut self, color: Color, limits: SearchLimits) -> Self {
+        self.game = self.game.with_engine(color, limits);
+        self.flipped = color == Color::White;
+        self
+    }
+
+    /// See [`Game::with_book`]
+    pub fn with_book(mut self, book: crate::book::OpeningBook) -> Self {
+        self.game = self.game.with_book(book);
+        self
+    }
+
+    /// See [`Game::with_engine_options`]
+    pub fn with_engine_options(mut self, options: crate::options::EngineOptions) -> Self {
+        self.game = self.game.with_engine_options(options);
+        self
+    }
+
+    /// Games the `explore` command looks the current position up in
+    pub fn with_database(mut self, database: GameDatabase) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// Whether the board is drawn from Black's side
+    pub fn is_flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// Turn the board around for the next time it is drawn
+    pub fn flip_board(&mut self) {
+        self.flipped = !self.flipped;
+    }
+
+    /// Draw the board in `style` instead of detecting what the terminal supports
+    pub fn with_style(mut self, style: BoardStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    fn display_board(&self, out: &mut dyn Write) -> io::Result<()> {
+        let style = self.style.unwrap_or_default();
+        let highlights = Highlights {
+            last_move: self
+                .game
+                .history()
+                .last()
+                .map_or(0, |record| (1 << record.mv.from) | (1 << record.mv.to)),
+            targets: self
+                .shown_square()
+                .map_or(0, |square| self.game.targets_of(square)),
+        };
+        let perspective = if self.flipped {
+            Color::Black
+        } else {
+            Color::White
+        };
+        let options = RenderOptions::new(style)
+            .with_perspective(perspective)
+            .with_highlights(highlights);
+        writeln!(out, "{}", ascii(self.game.get_board(), &options))
+    }
+
+    /// `show e2` marks where the piece on e2 can move, `show` alone clears the marks
+    fn show_targets(&mut self, square: &str, out: &mut dyn Write) -> io::Result<()> {
+        if square.is_empty() {
+            self.shown = None;
+            return Ok(());
+        }
+        let Some(square) = pos_to_u8(square) else {
+            writeln!(out, "❌ '{}' is not a square.", square)?;
+            return Ok(());
+        };
+        let name = crate::util::u8_to_pos(square);
+        match self.game.get_board().get_piece_at(square) {
+            None => writeln!(out, "❌ There is no piece on {}.", name)?,
+            Some((_, color)) if color != self.game.get_current_player() => {
+                writeln!(out, "❌ The piece on {} is not yours.", name)?
+            }
+            Some(_) if self.game.targets_of(square) == 0 => {
+                writeln!(out, "The piece on {} has no legal moves.", name)?
+            }
+            Some(_) => self.shown = Some((square, self.game.get_board().hash())),
+        }
+        Ok(())
+    }
+
+    /// `theme` lists the board styles, `theme <name>` switches to one
+    fn set_theme(&mut self, name: &str, out: &mut dyn Write) -> io::Result<()> {
+        let style = match name {
+            "" => {
+                let themes: Vec<&str> = THEMES.iter().map(|theme| theme.name).collect();
+                writeln!(out, "Themes: {}, unicode, ascii", themes.join(", "))?;
+                return Ok(());
+            }
+            "unicode" => BoardStyle::UNICODE,
+            "ascii" => BoardStyle::ASCII,
+            _ => match Theme::by_name(name) {
+                Some(theme) => BoardStyle::themed(theme),
+                None => {
+                    writeln!(out, "❌ Unknown theme '{}'. Type 'theme' for a list.", name)?;
+                    return Ok(());
+                }
+            },
+        };
+        self.style = Some(style);
+        Ok(())
+    }
+
+    fn display_game_status(&self, out: &mut dyn Write) -> io::Result<()> {
+        let current_color_name = self.game.get_current_player();
+
+        writeln!(out, "Current player: {}", current_color_name)?;
+
+        match self
+            .game
+            .rules()
+            .outcome(self.game.get_board(), self.game.get_current_player())
+        {
+            Some(GameResult::Draw(_)) => writeln!(out, "🤝 STALEMATE! The game is a draw.")?,
+            Some(result) => {
+                let winner = match result.winner() {
+                    Some(Color::White) => "White",
+                    _ => "Black",
+                };
+                writeln!(
+                    out,
+                    "🏁 {}! {} wins!",
+                    result.reason().to_string().to_uppercase(),
+                    winner
+                )?;
+            }
+            None if self
+                .game
+                .rules()
+                .is_in_check(self.game.get_board(), self.game.get_current_player()) =>
+            {
+                writeln!(out, "⚠️  {} is in CHECK!", current_color_name)?;
+            }
+            None => {}
+        }
+
+        let legal_moves = self
+            .game
+            .rules()
+            .legal_moves(self.game.get_board(), self.game.get_current_player());
+        writeln!(out, "Legal moves available: {}", legal_moves.len())?;
+        Ok(())
+    }
+
+    fn show_legal_moves(&self, out: &mut dyn Write) -> io::Result<()> {
+        let legal_moves = self
+            .game
+            .rules()
+            .legal_moves(self.game.get_board(), self.game.get_current_player());
+
+        if legal_moves.is_empty() {
+            writeln!(out, "No legal moves available!")?;
+            return Ok(());
+        }
+
+        writeln!(out, "\nLegal moves (showing first 20):")?;
+        for (i, mv) in legal_moves.iter().take(20).enumerate() {
+            let piece_name = if let Some((piece, _)) = self.game.get_board().get_piece_at(mv.from) {
+                format!("{:?}", piece)
+            } else {
+                "?".to_string()
+            };
+
+            let move_desc = match mv.move_type {
+                MoveType::Normal => "",
+                MoveType::Capture => " (capture)",
+                MoveType::Castle => " (castle)",
+                MoveType::EnPassant => " (en passant)",
+                MoveType::Double => " (double pawn)",
+                MoveType::Promotion { piece } => &format!(" (promote to {:?})", piece),
+                MoveType::PromotionCapture { piece } => {
+                    &format!(" (capture + promote to {:?})", piece)
+                }
+            };
+
+            write!(
+                out,
+                "{:2}. {} ({}){}",
+                i + 1,
+                mv.to_san(self.game.get_board()),
+                piece_name,
+                move_desc
+            )?;
+            if (i + 1) % 2 == 0 {
+                writeln!(out)?;
+            } else {
+                write!(out, "    ")?;
+            }
+        }
+
+        if legal_moves.len() % 2 == 1 {
+            writeln!(out)?;
+        }
+
+        if legal_moves.len() > 20 {
+            writeln!(out, "... and {} more moves", legal_moves.len() - 20)?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    fn show_help(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\n=== CHESS GAME HELP ===")?;
+        writeln!(out, "Move formats:")?;
+        writeln!(out, "  • Standard algebraic: e4, Nf3, exd5, a8=Q")?;
+        writeln!(out, "  • Coordinate notation: e2e4, g1f3, a7a8=Q, a7a8q")?;
+        writeln!(out, "  • Long algebraic: e2-e4, Ng1-f3")?;
+        writeln!(out, "  • Space separated: e2 e4")?;
+        writeln!(out, "  • Castling: O-O (kingside), O-O-O (queenside)")?;
+        writeln!(out, "\nCommands:")?;
+        writeln!(out, "  • 'moves' - Show all legal moves")?;
+        writeln!(out, "  • 'help' - Show this help")?;
+        writeln!(out, "  • 'quit' - Exit game")?;
+        writeln!(out, "  • 'history' - Show move history")?;
+        writeln!(out, "  • 'fen' - Show current position in FEN notation")?;
+        writeln!(out, "  • 'pgn' - Show the game so far in PGN notation")?;
+        writeln!(out, "  • 'status' - Show detailed game status")?;
+        writeln!(out, "  • 'resign' - Resign the game")?;
+        writeln!(
+            out,
+            "  • 'takeback' - Take back your last move (and the engine's reply)"
+        )?;
+        writeln!(
+            out,
+            "  • 'show e2' - Mark the squares the piece on e2 can move to"
+        )?;
+        writeln!(
+            out,
+            "  • 'goto 5' - Go to the position after 5 plies, keeping later moves"
+        )?;
+        if self.database.is_some() || self.game.book().is_some() {
+            writeln!(
+                out,
+                "  • 'explore' - Show the moves played here and their results"
+            )?;
+        }
+        writeln!(out, "  • 'flip' - Turn the board around")?;
+        writeln!(
+            out,
+            "  • 'setboard <fen>' - Start over from a position ('position startpos' for the usual one)"
+        )?;
+        writeln!(out, "  • 'save <file>' - Save the game as PGN")?;
+        writeln!(out, "  • 'load <file>' - Continue a game saved as PGN")?;
+        writeln!(
+            out,
+            "  • 'theme <name>' - Change the board colours ('theme' lists them)"
+        )?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// The moves of the current position in the database, or else in the engine's book,
+    /// with their game counts and results as White's wins, draws and Black's wins
+    fn show_explorer(&self, out: &mut dyn Write) -> io::Result<()> {
+        let board = self.game.get_board();
+        let (source, header): (&dyn ExplorerSource, String) =
+            match (&self.database, self.game.book()) {
+                (Some(database), _) => (
+                    database,
+                    format!(
+                        "{} games reached this position:",
+                        database.find_position(board).len()
+                    ),
+                ),
+                (None, Some(book)) => (book, "Book moves with their weights:".to_string()),
+                (None, None) => {
+                    writeln!(out, "❌ No game database or opening book loaded.")?;
+                    return Ok(());
+                }
+            };
+        let rows = explorer::stats(source, board);
+        if rows.is_empty() {
+            writeln!(out, "No known moves in this position.")?;
+            return Ok(());
+        }
+
+        let percent = |count: u32, total: u32| count as f64 * 100.0 / total as f64;
+        writeln!(out, "\n{}", header)?;
+        for (mv, games, white_wins, draws, black_wins) in rows {
+            let san = mv.to_san(board);
+            if white_wins + draws + black_wins == 0 {
+                writeln!(out, "  {:<8} {:>6}", san, games)?;
+                continue;
+            }
+            writeln!(
+                out,
+                "  {:<8} {:>6} games  {:>5.1}% / {:>5.1}% / {:>5.1}%",
+                san,
+                games,
+                percent(white_wins, games),
+                percent(draws, games),
+                percent(black_wins, games)
+            )?;
+        }
+        writeln!(out)
+    }
+
+    fn show_history(&self, out: &mut dyn Write) -> io::Result<()> {
+        if self.game.history().is_empty() {
+            writeln!(out, "No moves played yet.")?;
+            return Ok(());
+        }
+
+        writeln!(out, "\nMove History:")?;
+        for (i, record) in self.game.history().iter().enumerate() {
+            if i % 2 == 0 {
+                write!(out, "{}. {}", i / 2 + 1, record.san)?;
+            } else {
+                writeln!(out, " {}", record.san)?;
+            }
+        }
+        if self.game.history().len() % 2 == 1 {
+            writeln!(out)?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    fn show_detailed_status(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\n=== GAME STATUS ===")?;
+
+        let current_color_name = self.game.get_current_player();
+
+        writeln!(out, "Current player: {}", current_color_name)?;
+        writeln!(out, "Moves played: {}", self.game.history().len())?;
+        let fen = crate::fen::to_fen(self.game.get_board());
+        writeln!(
+            out,
+            "Castling rights: {}",
+            fen.split(' ').nth(2).unwrap_or("-")
+        )?;
+
+        if let Some(ep) = self.game.get_board().en_passant {
+            writeln!(out, "En passant square: {}", crate::util::u8_to_pos(ep))?;
+        }
+
+        writeln!(
+            out,
+            "Halfmove clock: {}",
+            self.game.get_board().halfmove_clock
+        )?;
+        writeln!(
+            out,
+            "Fullmove number: {}",
+            self.game.get_board().fullmove_number
+        )?;
+
+        // Check game state
+        if self
+            .game
+            .rules()
+            .is_in_check(self.game.get_board(), Color::White)
+        {
+            writeln!(out, "White is in check!")?;
+        }
+        if self
+            .game
+            .rules()
+            .is_in_check(self.game.get_board(), Color::Black)
+        {
+            writeln!(out, "Black is in check!")?;
+        }
+
+        let legal_moves = self
+            .game
+            .rules()
+            .legal_moves(self.game.get_board(), self.game.get_current_player());
+        writeln!(
+            out,
+            "Legal moves for {}: {}",
+            current_color_name,
+            legal_moves.len()
+        )?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// Play in the terminal on stdin and stdout
+    pub fn run(&mut self) {
+        self.style.get_or_insert_with(BoardStyle::detect);
+        self.run_with(io::stdin().lock(), io::stdout())
+            .expect("terminal I/O failed");
+    }
+
+    /// Play interactively, reading commands and moves from `input` and writing the board
+    /// and messages to `output`, until the game ends, the user quits or input runs out
+    pub fn run_with(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        let out: &mut dyn Write = &mut output;
+        writeln!(out, "🏰 Welcome to OxM8 Chess! 🏰")?;
+        writeln!(
+            out,
+            "Type 'help' for commands or enter moves like 'e2e4' or 'g1f3'"
+        )?;
+
+        loop {
+            self.display_board(out)?;
+            self.display_game_status(out)?;
+
+            if let Some(result) = self.game.result() {
+                let outcome = match result.winner() {
+                    Some(Color::White) => "White wins",
+                    Some(Color::Black) => "Black wins",
+                    None => "Draw",
+                };
+                writeln!(out, "Game Over! {} by {}.", outcome, result.reason())?;
+                break;
+            }
+
+            if self.game.engine_color() == Some(self.game.get_current_player()) {
+                writeln!(out, "🤖 Engine is thinking...")?;
+                let start = Instant::now();
+                if self.game.play_engine_move().is_some() {
+                    self.game.set_move_time(start.elapsed());
+                    writeln!(out, "🤖 Engine played: {}", self.last_san())?;
+                }
+                continue;
+            }
+
+            write!(out, "Enter move: ")?;
+            out.flush()?;
+            let start = Instant::now();
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                break;
+            }
+
+            // Commands are matched in any case, moves keep theirs: "Bb5" is a bishop
+            // move and "bb5" a pawn capture. File names keep their case too
+            let text = line.trim();
+            let command = text.to_lowercase();
+            let argument = line
+                .trim()
+                .split_once(' ')
+                .map_or("", |(_, rest)| rest.trim());
+
+            match command.as_str() {
+                "quit" | "exit" | "q" => {
+                    writeln!(out, "Thanks for playing! 👋")?;
+                    break;
+                }
+                "help" | "h" => {
+                    self.show_help(out)?;
+                    continue;
+                }
+                "moves" | "m" => {
+                    self.show_legal_moves(out)?;
+                    continue;
+                }
+                "history" => {
+                    self.show_history(out)?;
+                    continue;
+                }
+                "explore" => {
+                    self.show_explorer(out)?;
+                    continue;
+                }
+                "fen" => {
+                    writeln!(
+                        out,
+                        "Current position: {}",
+                        crate::fen::to_fen(self.game.get_board())
+                    )?;
+                    continue;
+                }
+                "status" => {
+                    self.show_detailed_status(out)?;
+                    continue;
+                }
+                "pgn" => {
+                    writeln!(out, "{}", self.game.to_pgn())?;
+                    continue;
+                }
+                "resign" => {
+                    let color = self.game.get_current_player();
+                    self.game.resign(color);
+                    continue;
+                }
+                setboard
+                    if setboard.starts_with("setboard ") || setboard.starts_with("position ") =>
+                {
+                    let fen = match argument {
+                        "startpos" => START_FEN,
+                        fen => fen,
+                    };
+                    match self.game.set_position(fen) {
+                        Ok(()) => writeln!(out, "♟️ New position set.")?,
+                        Err(e) => writeln!(out, "❌ Invalid position: {}", e)?,
+                    }
+                    continue;
+                }
+                save if save.starts_with("save ") => {
+                    match self.game.save(argument) {
+                        Ok(()) => writeln!(out, "💾 Game saved to {}", argument)?,
+                        Err(e) => writeln!(out, "❌ Could not save to {}: {}", argument, e)?,
+                    }
+                    continue;
+                }
+                load if load.starts_with("load ") => {
+                    match self.game.load(argument) {
+                        Ok(()) => writeln!(out, "📂 Game loaded from {}", argument)?,
+                        Err(e) => writeln!(out, "❌ Could not load {}: {}", argument, e)?,
+                    }
+                    continue;
+                }
+                show if show == "show" || show.starts_with("show ") => {
+                    self.show_targets(show["show".len()..].trim(), out)?;
+                    continue;
+                }
+                theme if theme == "theme" || theme.starts_with("theme ") => {
+                    self.set_theme(theme["theme".len()..].trim(), out)?;
+                    continue;
+                }
+                goto if goto.starts_with("goto ") => {
+                    let ply = argument.parse().ok();
+                    if !ply.is_some_and(|ply| self.game.goto(ply)) {
+                        writeln!(out, "❌ The line has no ply '{}'.", argument)?;
+                    }
+                    continue;
+                }
+                "flip" => {
+                    self.flip_board();
+                    continue;
+                }
+                "takeback" => {
+                    if !self.game.takeback() {
+                        writeln!(out, "❌ No move to take back.")?;
+                    }
+                    continue;
+                }
+                "" => continue,
+                _ => {}
+            }
+
+            // Coordinates typed in capitals, like "E2E4", are read as lowercase
+            let parsed = match self.game.parse_move(text) {
+                Err(MoveParseError::Unrecognised) => self.game.parse_move(&command),
+                parsed => parsed,
+            };
+            match parsed {
+                Ok(mv) => match self.game.play(mv) {
+                    Ok(_) => {
+                        self.game.set_move_time(start.elapsed());
+                        writeln!(out, "✅ Move played: {}", self.last_san())?;
+                    }
+                    Err(_) => writeln!(out, "❌ Illegal move! Try again.")?,
+                },
+                Err(MoveParseError::Unrecognised) => {
+                    writeln!(out, "❌ Invalid move format! Type 'help' for examples.")?;
+                }
+                Err(MoveParseError::Illegal) => writeln!(
+                    out,
+                    "❌ Illegal move! '{}' is not a legal move in this position.",
+                    text
+                )?,
+                Err(MoveParseError::Ambiguous) => writeln!(
+                    out,
+                    "❌ Ambiguous move! Several pieces can play '{}', say which one.",
+                    text
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    fn shown_square(&self) -> Option<u8> {
+        self.shown
+            .filter(|&(_, hash)| hash == self.game.get_board().hash())
+            .map(|(square, _)| square)
+    }
+
+    fn last_san(&self) -> &str {
+        self.game.history().last().map_or("", |record| &record.san)
+    }
+}
+
+impl Default for ChessGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,162 @@
+use crate::board::Board;
+use crate::book::polyglot_key;
+use crate::moves::Move;
+use crate::pgn::{Game, PgnReader};
+use std::collections::HashMap;
+use std::io::BufRead;
+
+/// How the games reaching a position ended
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResultCounts {
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+    /// Games without a result, `*` or missing
+    pub unfinished: u32,
+}
+
+impl ResultCounts {
+    fn add(&mut self, result: &str) {
+        match result {
+            "1-0" => self.white_wins += 1,
+            "0-1" => self.black_wins += 1,
+            "1/2-1/2" => self.draws += 1,
+            _ => self.unfinished += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.white_wins + self.draws + self.black_wins + self.unfinished
+    }
+
+    /// White's share of the points of the finished games, from 0.0 to 1.0, or `None`
+    /// when none finished
+    pub fn white_score(&self) -> Option<f64> {
+        let finished = self.white_wins + self.draws + self.black_wins;
+        (finished > 0).then(|| (self.white_wins as f64 + self.draws as f64 / 2.0) / finished as f64)
+    }
+}
+
+/// A move played in a position of the database and how its games ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveStats {
+    pub mv: Move,
+    pub results: ResultCounts,
+}
+
+/// A collection of games indexed by the Polyglot key of every position of their
+/// mainlines, so positions are found however the games reached them. Unlike
+/// [`Board::hash`], the key only counts an en passant square a pawn can capture on,
+/// so move orders ending in a double pawn push still transpose
+#[derive(Debug, Clone, Default)]
+pub struct GameDatabase {
+    games: Vec<Game>,
+    /// Each position's occurrences as (game index, plies played before it)
+    positions: HashMap<u64, Vec<(usize, usize)>>,
+}
+
+impl GameDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_games(games: impl IntoIterator<Item = Game>) -> Self {
+        let mut database = Self::new();
+        for game in games {
+            database.add(game);
+        }
+        database
+    }
+
+    /// Read and index every game of a PGN collection
+    pub fn from_pgn(reader: impl BufRead) -> Result<Self, String> {
+        let games = PgnReader::new(reader).collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_games(games))
+    }
+
+    /// Index `game` and return its index
+    pub fn add(&mut self, game: Game) -> usize {
+        let index = self.games.len();
+        let mut board = game.initial_board;
+        for ply in 0..=game.moves.len() {
+            self.positions
+                .entry(polyglot_key(&board))
+                .or_default()
+                .push((index, ply));
+            if let Some(mv) = game.moves.get(ply) {
+                board.make_move(mv);
+            }
+        }
+        self.games.push(game);
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn game(&self, index: usize) -> &Game {
+        &self.games[index]
+    }
+
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    fn occurrences(&self, board: &Board) -> &[(usize, usize)] {
+        self.positions
+            .get(&polyglot_key(board))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The indices of the games reaching `board`, in the order they were added. A game
+    /// passing through the position more than once is listed once
+    pub fn find_position(&self, board: &Board) -> Vec<usize> {
+        let mut games: Vec<usize> = self.occurrences(board).iter().map(|&(g, _)| g).collect();
+        games.dedup();
+        games
+    }
+
+    /// How the games reaching `board` ended
+    pub fn results(&self, board: &Board) -> ResultCounts {
+        let mut results = ResultCounts::default();
+        for index in self.find_position(board) {
+            results.add(&self.games[index].result);
+        }
+        results
+    }
+
+    /// The moves played in `board` with the results of their games, most played first.
+    /// A game counts once for each different move it played there
+    pub fn moves(&self, board: &Board) -> Vec<MoveStats> {
+        let mut stats: Vec<MoveStats> = Vec::new();
+        let mut counted: Vec<(usize, Move)> = Vec::new();
+        for &(index, ply) in self.occurrences(board) {
+            let game = &self.games[index];
+            let Some(&mv) = game.moves.get(ply) else {
+                continue;
+            };
+            if counted.contains(&(index, mv)) {
+                continue;
+            }
+            counted.push((index, mv));
+            let position = match stats.iter().position(|entry| entry.mv == mv) {
+                Some(position) => position,
+                None => {
+                    stats.push(MoveStats {
+                        mv,
+                        results: ResultCounts::default(),
+                    });
+                    stats.len() - 1
+                }
+            };
+            stats[position].results.add(&game.result);
+        }
+        stats.sort_by_key(|entry| std::cmp::Reverse(entry.results.games()));
+        stats
+    }
+}
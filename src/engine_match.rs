@@ -0,0 +1,400 @@
+use crate::fen::{START_FEN, to_fen};
+use crate::game::Game;
+use crate::moves::Move;
+use crate::notation::MoveParser;
+use crate::params::EvalParams;
+use crate::piece::Color;
+use crate::search::{Search, SearchLimits, SearchOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// One side of an engine match
+pub trait Player {
+    fn name(&self) -> String;
+
+    /// Called before every game
+    fn new_game(&mut self) {}
+
+    /// The move to play in the game's current position, `None` to resign
+    fn choose_move(&mut self, game: &Game) -> Option<Move>;
+}
+
+/// This engine, searching in-process within fixed limits
+pub struct EnginePlayer {
+    name: String,
+    limits: SearchLimits,
+    search: Search,
+}
+
+impl EnginePlayer {
+    pub fn new(name: &str, limits: SearchLimits, options: SearchOptions) -> Self {
+        Self {
+            name: name.to_string(),
+            limits,
+            search: Search::new(options),
+        }
+    }
+
+    /// Evaluate with `params` instead of the defaults, to play one parameter set
+    /// against another
+    pub fn with_eval_params(mut self, params: EvalParams) -> Self {
+        self.search.set_eval_params(params);
+        self
+    }
+}
+
+impl Player for EnginePlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Option<Move> {
+        self.search.set_game_history(game.position_hashes());
+        self.search.best_move(game.get_board(), self.limits).0
+    }
+}
+
+/// An external engine binary driven over the UCI protocol
+pub struct UciPlayer {
+    name: String,
+    limits: SearchLimits,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl UciPlayer {
+    /// Start the engine at `path` and complete the UCI handshake
+    pub fn spawn(path: &str, limits: SearchLimits) -> io::Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut player = Self {
+            name: path.to_string(),
+            limits,
+            child,
+            stdin,
+            stdout,
+        };
+
+        player.send("uci")?;
+        let id = player.read_until("uciok")?;
+        if let Some(name) = id.iter().find_map(|line| line.strip_prefix("id name ")) {
+            player.name = name.to_string();
+        }
+        player.send("isready")?;
+        player.read_until("readyok")?;
+        Ok(player)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    /// Read lines up to one starting with `token`, returning all of them
+    fn read_until(&mut self, token: &str) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("engine exited before '{}'", token),
+                ));
+            }
+            let line = line.trim().to_string();
+            let done = line.starts_with(token);
+            lines.push(line);
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+
+    fn go_command(&self) -> String {
+        let mut command = String::from("go");
+        if let Some(depth) = self.limits.depth {
+            command += &format!(" depth {}", depth);
+        }
+        if let Some(nodes) = self.limits.nodes {
+            command += &format!(" nodes {}", nodes);
+        }
+        if let Some(time) = self.limits.movetime {
+            command += &format!(" movetime {}", time.as_millis());
+        }
+        if let Some(mate) = self.limits.mate {
+            command += &format!(" mate {}", mate);
+        }
+        command
+    }
+
+    fn best_move(&mut self, game: &Game) -> io::Result<String> {
+        let moves: Vec<String> = game.moves().iter().map(Move::to_uci).collect();
+        let mut position = format!("position fen {}", to_fen(game.initial_board()));
+        if !moves.is_empty() {
+            position += &format!(" moves {}", moves.join(" "));
+        }
+        self.send(&position)?;
+        let go = self.go_command();
+        self.send(&go)?;
+        let lines = self.read_until("bestmove")?;
+        let last = lines.last().map(String::as_str).unwrap_or_default();
+        Ok(last
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+            .to_string())
+    }
+}
+
+impl Player for UciPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) {
+        let _ = self.send("ucinewgame");
+    }
+
+    /// An engine that crashes or answers with an illegal move forfeits the game
+    fn choose_move(&mut self, game: &Game) -> Option<Move> {
+        let uci = self.best_move(game).ok()?;
+        MoveParser::with_moves(game.get_board(), game.get_legal_moves())
+            .parse(&uci)
+            .ok()
+    }
+}
+
+impl Drop for UciPlayer {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.wait();
+    }
+}
+
+/// Expected score of the stronger side for an Elo difference
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Elo difference matching an expected score
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Game results from the first player's point of view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game, 0.5 for an even match or before any game is played
+    pub fn score(&self) -> f64 {
+        match self.games() {
+            0 => 0.5,
+            games => (self.wins as f64 + self.draws as f64 / 2.0) / games as f64,
+        }
+    }
+
+    /// Per-game variance of the score
+    fn variance(&self) -> f64 {
+        let games = self.games() as f64;
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+
+    /// Elo difference and the half-width of its 95% confidence interval. Infinite while
+    /// one player has scored every point
+    pub fn elo(&self) -> (f64, f64) {
+        let score = self.score();
+        if self.games() == 0 {
+            return (0.0, f64::INFINITY);
+        }
+        let margin = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let low = elo_from_score((score - margin).max(0.0));
+        let high = elo_from_score((score + margin).min(1.0));
+        (elo_from_score(score), (high - low) / 2.0)
+    }
+}
+
+/// What the sequential probability ratio test concludes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtDecision {
+    /// The first player is stronger by `elo0` rather than `elo1`
+    AcceptH0,
+    /// The first player is stronger by `elo1` rather than `elo0`
+    AcceptH1,
+    Continue,
+}
+
+/// Sequential probability ratio test between the hypotheses that the first player is
+/// `elo0` or `elo1` Elo stronger, with false positive rate `alpha` and false negative
+/// rate `beta`. Uses the normal approximation of the log-likelihood ratio
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Self {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    /// Log-likelihood ratios at which H0 and H1 are accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log-likelihood ratio of H1 over H0. 0 until both a win and a loss or a draw make
+    /// the variance meaningful
+    pub fn llr(&self, stats: &MatchStats) -> f64 {
+        let games = stats.games() as f64;
+        let variance = stats.variance();
+        if stats.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (s1 - s0) * (2.0 * stats.score() - s0 - s1) / (2.0 * variance / games)
+    }
+
+    pub fn decide(&self, stats: &MatchStats) -> SprtDecision {
+        let (lower, upper) = self.bounds();
+        let llr = self.llr(stats);
+        if llr <= lower {
+            SprtDecision::AcceptH0
+        } else if llr >= upper {
+            SprtDecision::AcceptH1
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+/// How an engine match is played
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    /// Starting positions as FEN, each played twice with colours reversed
+    pub openings: Vec<String>,
+    /// Stop after this many games even without an SPRT decision
+    pub max_games: u32,
+    /// Adjudicate a draw after this many plies
+    pub max_plies: usize,
+    /// Stop early once the test reaches a decision
+    pub sprt: Option<Sprt>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            openings: vec![START_FEN.to_string()],
+            max_games: 100,
+            max_plies: 400,
+            sprt: None,
+        }
+    }
+}
+
+/// Outcome of [`run_match`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchReport {
+    pub stats: MatchStats,
+    /// `None` when the match ran without an SPRT
+    pub decision: Option<SprtDecision>,
+}
+
+/// Play `first` against `second`, alternating colours every game and moving on to the
+/// next opening every two games. Results are counted for `first`
+pub fn run_match(
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    config: &MatchConfig,
+) -> Result<MatchReport, String> {
+    if config.openings.is_empty() {
+        return Err("the match needs at least one opening".to_string());
+    }
+    let mut report = MatchReport {
+        stats: MatchStats::default(),
+        decision: config.sprt.map(|_| SprtDecision::Continue),
+    };
+
+    for index in 0..config.max_games {
+        let fen = &config.openings[(index as usize / 2) % config.openings.len()];
+        let first_color = if index % 2 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let winner = play_game(first, second, fen, first_color, config.max_plies)?;
+        match winner {
+            Some(color) if color == first_color => report.stats.wins += 1,
+            Some(_) => report.stats.losses += 1,
+            None => report.stats.draws += 1,
+        }
+
+        if let Some(sprt) = config.sprt {
+            let decision = sprt.decide(&report.stats);
+            report.decision = Some(decision);
+            if decision != SprtDecision::Continue {
+                break;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Play one game and return the winning colour, `None` for a draw
+fn play_game(
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    fen: &str,
+    first_color: Color,
+    max_plies: usize,
+) -> Result<Option<Color>, String> {
+    let mut game = Game::from_fen(fen).map_err(|e| format!("{} '{}'", e, fen))?;
+    first.new_game();
+    second.new_game();
+
+    loop {
+        if let Some(result) = game.result() {
+            return Ok(result.winner());
+        }
+        if game.moves().len() >= max_plies {
+            return Ok(None);
+        }
+        let to_move = game.get_current_player();
+        let choice = if to_move == first_color {
+            first.choose_move(&game)
+        } else {
+            second.choose_move(&game)
+        };
+        let played = choice.is_some_and(|mv| game.play(mv).is_ok());
+        if !played {
+            return Ok(Some(to_move.opposite()));
+        }
+    }
+}
@@ -1,191 +1,874 @@
+use crate::attacks;
+use crate::bitboard;
 use crate::board::Board;
-use crate::moves::{MoveType, Moves};
+use crate::moves::Move;
+use crate::params::EvalParams;
 use crate::piece::{Color, Piece};
+#[cfg(feature = "stats")]
+use crate::search::SearchStats;
+use crate::search::{SearchContext, is_quiet, order_moves_by, piece_to};
+use crate::tablebase;
+use crate::tt::Bound;
 
-const PAWN_VALUE: i32 = 100;
-const KNIGHT_VALUE: i32 = 300;
-const BISHOP_VALUE: i32 = 300;
-const ROOK_VALUE: i32 = 500;
-const QUEEN_VALUE: i32 = 900;
-const KING_VALUE: i32 = 0;
+/// How far a quiet move may raise the static evaluation at depths 1 and 2, see
+/// the futility pruning in [`Eval::negamax`]
+const FUTILITY_MARGINS: [i32; 3] = [0, 200, 400];
+
+/// Continuation history below which a quiet move is skipped at depths 1 and 2, per
+/// ply of depth
+const HISTORY_PRUNING_MARGIN: i32 = 2_000;
+
+/// Game phase of the starting position, see [`Eval::game_phase`]
+const MAX_PHASE: i32 = 24;
+
+/// Score bound larger than any reachable evaluation
+pub const INFINITY: i32 = 1_000_000;
+
+/// Score for delivering checkmate at the root; a mate found at ply `n` scores `MATE_SCORE - n`
+pub const MATE_SCORE: i32 = 100_000;
+
+/// Scores beyond this magnitude encode a forced mate
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1_000;
+
+/// Whether a search score encodes a forced mate
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_THRESHOLD
+}
+
+/// Convert a mate score into full moves to mate: positive when the side to move
+/// mates, negative when it gets mated
+pub fn mate_in(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+    let plies = MATE_SCORE - score.abs();
+    let moves = (plies + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// Mate scores are stored relative to the node rather than the root so they stay
+/// valid when the position is reached at a different ply
+fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score - ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// The terms of the static evaluation, each from White's point of view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalBreakdown {
+    pub material: i32,
+    pub mobility: i32,
+    pub pawn_structure: i32,
+    pub imbalance: i32,
+    pub rook_activity: i32,
+    /// Passed pawns and king placement, blended by game phase
+    pub phased: i32,
+    /// Whether the material left is a known draw, which overrides every term
+    pub known_draw: bool,
+    /// The evaluation, as [`Eval::evaluate_absolute`]: the sum of the terms, scaled
+    /// down as the fifty-move clock runs
+    pub total: i32,
+}
+
+/// Win, draw and loss probabilities in permille, adding up to 1000, from the side to
+/// move's point of view as UCI reports them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wdl {
+    pub win: u16,
+    pub draw: u16,
+    pub loss: u16,
+}
+
+impl Wdl {
+    /// The same chances from the other side's point of view
+    pub fn flip(self) -> Self {
+        Self {
+            win: self.loss,
+            draw: self.draw,
+            loss: self.win,
+        }
+    }
+}
 
 pub struct Eval {}
 
 impl Eval {
+    /// The default value of a piece, as used to order captures and judge exchanges
     pub fn match_piece_value(piece: Piece) -> i32 {
+        Self::piece_value(piece, &EvalParams::DEFAULT)
+    }
+
+    fn piece_value(piece: Piece, params: &EvalParams) -> i32 {
         match piece {
-            Piece::Pawn => PAWN_VALUE,
-            Piece::Knight => KNIGHT_VALUE,
-            Piece::Bishop => BISHOP_VALUE,
-            Piece::Rook => ROOK_VALUE,
-            Piece::Queen => QUEEN_VALUE,
-            Piece::King => KING_VALUE,
+            Piece::Pawn => params.pawn_value,
+            Piece::Knight => params.knight_value,
+            Piece::Bishop => params.bishop_value,
+            Piece::Rook => params.rook_value,
+            Piece::Queen => params.queen_value,
+            Piece::King => 0,
         }
     }
 
     pub fn material(board: &Board, color: Color) -> i32 {
-        match color {
-            Color::White => {
-                board.white_pawns.count_ones() as i32 * PAWN_VALUE
-                    + board.white_knights.count_ones() as i32 * KNIGHT_VALUE
-                    + board.white_bishops.count_ones() as i32 * BISHOP_VALUE
-                    + board.white_rooks.count_ones() as i32 * ROOK_VALUE
-                    + board.white_queens.count_ones() as i32 * QUEEN_VALUE
-            }
-            Color::Black => {
-                board.black_pawns.count_ones() as i32 * PAWN_VALUE
-                    + board.black_knights.count_ones() as i32 * KNIGHT_VALUE
-                    + board.black_bishops.count_ones() as i32 * BISHOP_VALUE
-                    + board.black_rooks.count_ones() as i32 * ROOK_VALUE
-                    + board.black_queens.count_ones() as i32 * QUEEN_VALUE
-            }
-        }
+        Self::material_with(board, color, &EvalParams::DEFAULT)
+    }
+
+    fn material_with(board: &Board, color: Color, params: &EvalParams) -> i32 {
+        [
+            Piece::Pawn,
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Rook,
+            Piece::Queen,
+        ]
+        .into_iter()
+        .map(|piece| {
+            board.get_bb(piece, color).count_ones() as i32 * Self::piece_value(piece, params)
+        })
+        .sum()
     }
 
     pub fn material_balance(board: &Board) -> i32 {
-        Self::material(board, Color::White) - Self::material(board, Color::Black)
+        Self::balance(board, &EvalParams::DEFAULT, Self::material_with)
+    }
+
+    /// A term for White less the same term for Black
+    fn balance(
+        board: &Board,
+        params: &EvalParams,
+        term: fn(&Board, Color, &EvalParams) -> i32,
+    ) -> i32 {
+        term(board, Color::White, params) - term(board, Color::Black, params)
     }
 
+    /// Squares each knight, bishop, rook and queen reaches, weighted by piece type.
+    /// Squares held by friendly pieces or covered by enemy pawns are not counted
     pub fn mobility(board: &Board, color: Color) -> i32 {
-        let moves = Moves::generate_all_moves(board, color);
-        moves.len() as i32 * 10
+        Self::mobility_with(board, color, &EvalParams::DEFAULT)
+    }
+
+    fn mobility_with(board: &Board, color: Color, params: &EvalParams) -> i32 {
+        let enemy_pawns = board.get_bb(Piece::Pawn, color.opposite());
+        let pawn_covered = bitboard::pawn_attacks(enemy_pawns, color.opposite());
+        let available = !board.get_all_pieces(color) & !pawn_covered;
+        let occupied = board.get_all_occupied();
+
+        let mut score = 0;
+        for (piece, weight) in [
+            (Piece::Knight, params.knight_mobility),
+            (Piece::Bishop, params.bishop_mobility),
+            (Piece::Rook, params.rook_mobility),
+            (Piece::Queen, params.queen_mobility),
+        ] {
+            let mut pieces = board.get_bb(piece, color);
+            while pieces != 0 {
+                let square = pieces.trailing_zeros() as u8;
+                pieces &= pieces - 1;
+                let reach = match piece {
+                    Piece::Knight => attacks::knight_attacks(square),
+                    Piece::Bishop => attacks::bishop_attacks(square, occupied),
+                    Piece::Rook => attacks::rook_attacks(square, occupied),
+                    _ => attacks::queen_attacks(square, occupied),
+                };
+                score += (reach & available).count_ones() as i32 * weight;
+            }
+        }
+        score
     }
 
     pub fn mobility_balance(board: &Board) -> i32 {
-        Self::mobility(board, Color::White) - Self::mobility(board, Color::Black)
+        Self::balance(board, &EvalParams::DEFAULT, Self::mobility_with)
     }
 
+    /// Penalties for isolated, doubled and backward pawns and for every pawn island
+    /// past the first, bonuses for pawns side by side (phalanx) or defended by a pawn
+    /// (chain). Passed pawns are scored by [`Eval::passed_pawns`]
     pub fn pawn_structure(board: &Board, color: Color) -> i32 {
+        Self::pawn_structure_with(board, color, &EvalParams::DEFAULT)
+    }
+
+    fn pawn_structure_with(board: &Board, color: Color, params: &EvalParams) -> i32 {
+        let pawns = board.get_bb(Piece::Pawn, color);
+        let enemy_pawns = board.get_bb(Piece::Pawn, color.opposite());
+
         let mut score = 0;
-        let (pawns, enemy_pawns) = match color {
-            Color::White => (board.white_pawns, board.black_pawns),
-            Color::Black => (board.black_pawns, board.white_pawns),
-        };
+        let mut remaining = pawns;
+        while remaining != 0 {
+            let square = remaining.trailing_zeros() as u8;
+            remaining &= remaining - 1;
+            let (file, rank) = (square % 8, square / 8);
+            let adjacent_files = bitboard::adjacent_files(file);
 
-        for i in 0..64 {
-            if (pawns & (1 << i)) != 0 {
-                // Isolated pawn
-                let file = i % 8;
-                let mut is_isolated = true;
-                if file > 0 && (pawns & (1 << (i - 1))) != 0 {
-                    is_isolated = false;
-                }
-                if file < 7 && (pawns & (1 << (i + 1))) != 0 {
-                    is_isolated = false;
-                }
-                if is_isolated {
-                    score -= 30;
-                }
+            // Isolated pawn
+            if pawns & adjacent_files == 0 {
+                score -= params.isolated_pawn;
+            }
 
-                // Doubled pawn
-                let rank = i / 8;
-                for r in 0..rank {
-                    if (pawns & (1 << (r * 8 + file))) != 0 {
-                        score -= 20;
-                        break;
-                    }
-                }
+            // Doubled pawn
+            if pawns & bitboard::file_mask(file) & ((1u64 << square) - 1) != 0 {
+                score -= params.doubled_pawn;
+            }
 
-                // Passed pawn
-                let mut is_passed = true;
+            // Phalanx and chain
+            if pawns & adjacent_files & bitboard::rank_mask(rank) != 0 {
+                score += params.pawn_phalanx;
+            }
+            if attacks::pawn_attacks(square, color.opposite()) & pawns != 0 {
+                score += params.pawn_chain;
+            }
 
-                // Check correct direction based on color
-                let ranks_to_check: Vec<usize> = match color {
-                    Color::White => (rank + 1..8).collect(), // White moves up (toward rank 7)
-                    Color::Black => (0..rank).rev().collect(), // Black moves down (toward rank 0)
+            // Backward pawn: its neighbours have all advanced past it, and an enemy pawn
+            // controls the square in front, so it can neither be supported nor advance
+            let level_or_behind = !bitboard::front_span(bitboard::rank_mask(rank), color);
+            let stop = bitboard::forward(1u64 << square, color);
+            if pawns & adjacent_files != 0
+                && pawns & adjacent_files & level_or_behind == 0
+                && bitboard::pawn_attacks(stop, color) & enemy_pawns != 0
+            {
+                score -= params.backward_pawn;
+            }
+        }
+
+        // Pawn islands: runs of adjacent files holding pawns
+        let occupied_files = (0..8).filter(|&file| pawns & bitboard::file_mask(file as u8) != 0);
+        let mut islands = 0;
+        let mut previous = -2;
+        for file in occupied_files {
+            if file != previous + 1 {
+                islands += 1;
+            }
+            previous = file;
+        }
+        if islands > 1 {
+            score -= params.pawn_island * (islands - 1);
+        }
+
+        score
+    }
+
+    pub fn pawn_structure_balance(board: &Board) -> i32 {
+        Self::balance(board, &EvalParams::DEFAULT, Self::pawn_structure_with)
+    }
+
+    /// Material imbalance adjustments: the bishop pair, knights gaining and bishops losing
+    /// value as pawns stay on the board, and overlapping rooks
+    pub fn imbalance(board: &Board, color: Color) -> i32 {
+        Self::imbalance_with(board, color, &EvalParams::DEFAULT)
+    }
+
+    fn imbalance_with(board: &Board, color: Color, params: &EvalParams) -> i32 {
+        let pawns = board.get_bb(Piece::Pawn, color).count_ones() as i32;
+        let knights = board.get_bb(Piece::Knight, color).count_ones() as i32;
+        let bishops = board.get_bb(Piece::Bishop, color).count_ones() as i32;
+        let rooks = board.get_bb(Piece::Rook, color).count_ones() as i32;
+
+        let mut score = 0;
+        if bishops >= 2 {
+            score += params.bishop_pair;
+        }
+        // Adjusted against a middle of five pawns, closed positions favour knights
+        score += knights * (pawns - 5) * params.knight_pawn_bonus;
+        score -= bishops * (pawns - 5) * params.bishop_pawn_penalty;
+        // A second rook adds less than the first
+        if rooks >= 2 {
+            score -= params.redundant_rook * (rooks - 1);
+        }
+        score
+    }
+
+    pub fn imbalance_balance(board: &Board) -> i32 {
+        Self::balance(board, &EvalParams::DEFAULT, Self::imbalance_with)
+    }
+
+    /// Rooks on open and semi-open files, on the seventh rank, and defending each other
+    pub fn rook_activity(board: &Board, color: Color) -> i32 {
+        Self::rook_activity_with(board, color, &EvalParams::DEFAULT)
+    }
+
+    fn rook_activity_with(board: &Board, color: Color, params: &EvalParams) -> i32 {
+        let own_pawns = board.get_bb(Piece::Pawn, color);
+        let enemy_pawns = board.get_bb(Piece::Pawn, color.opposite());
+        let rooks = board.get_bb(Piece::Rook, color);
+        let seventh_rank = match color {
+            Color::White => 6,
+            Color::Black => 1,
+        };
+
+        let mut score = 0;
+        let mut remaining = rooks;
+        while remaining != 0 {
+            let square = remaining.trailing_zeros() as u8;
+            remaining &= remaining - 1;
+
+            let file = bitboard::file_mask(square % 8);
+            if own_pawns & file == 0 {
+                score += if enemy_pawns & file == 0 {
+                    params.rook_open_file
+                } else {
+                    params.rook_semi_open_file
                 };
+            }
+            if square / 8 == seventh_rank {
+                score += params.rook_seventh_rank;
+            }
+            // Each rook of a connected pair gets the full bonus, so a pair scores it twice
+            if attacks::rook_attacks(square, board.get_all_occupied()) & rooks != 0 {
+                score += params.connected_rooks;
+            }
+        }
+        score
+    }
 
-                for r in ranks_to_check {
-                    // Check same file
-                    if (enemy_pawns & (1 << (r * 8 + file))) != 0 {
-                        is_passed = false;
-                        break;
-                    }
-
-                    // Check left diagonal file
-                    if file > 0 && (enemy_pawns & (1 << (r * 8 + file - 1))) != 0 {
-                        is_passed = false;
-                        break;
-                    }
-
-                    // Check right diagonal file
-                    if file < 7 && (enemy_pawns & (1 << (r * 8 + file + 1))) != 0 {
-                        is_passed = false;
-                        break;
-                    }
-                }
+    pub fn rook_activity_balance(board: &Board) -> i32 {
+        Self::balance(board, &EvalParams::DEFAULT, Self::rook_activity_with)
+    }
+
+    /// No enemy pawn ahead of the pawn on its own or an adjacent file
+    fn is_passed(square: u8, color: Color, enemy_pawns: u64) -> bool {
+        let pawn = 1u64 << square;
+        let ahead = bitboard::front_span(pawn, color) | bitboard::pawn_attack_span(pawn, color);
+        enemy_pawns & ahead == 0
+    }
+
+    /// Midgame and endgame bonuses for passed pawns, growing as they advance.
+    /// They matter far more once the pieces that could stop them are gone. Passers
+    /// defended by a pawn or side by side with another passer earn extra, blockaded
+    /// ones less, and in a pawn ending a passer the enemy king cannot catch is close
+    /// to a new queen
+    pub fn passed_pawns(board: &Board, color: Color) -> (i32, i32) {
+        Self::passed_pawns_with(board, color, &EvalParams::DEFAULT)
+    }
 
-                if is_passed {
-                    score += 30;
+    fn passed_pawns_with(board: &Board, color: Color, params: &EvalParams) -> (i32, i32) {
+        let own_pawns = board.get_bb(Piece::Pawn, color);
+        let enemy_pawns = board.get_bb(Piece::Pawn, color.opposite());
+        let enemy_pieces = board.get_all_pieces(color.opposite())
+            & !enemy_pawns
+            & !board.get_bb(Piece::King, color.opposite());
+        let enemy_king = board
+            .get_piece_squares(color.opposite(), Piece::King)
+            .first()
+            .copied();
+
+        let mut passers = 0u64;
+        let mut remaining = own_pawns;
+        while remaining != 0 {
+            let square = remaining.trailing_zeros() as u8;
+            remaining &= remaining - 1;
+            if Self::is_passed(square, color, enemy_pawns) {
+                passers |= 1u64 << square;
+            }
+        }
+
+        let (mut mg, mut eg) = (0, 0);
+        let mut remaining = passers;
+        while remaining != 0 {
+            let square = remaining.trailing_zeros() as u8;
+            remaining &= remaining - 1;
+            let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+            let advance = match color {
+                Color::White => rank - 1,
+                Color::Black => 6 - rank,
+            }
+            .max(0);
+            mg += params.passed_pawn_mg + params.passed_pawn_mg_advance * advance;
+            eg += params.passed_pawn_eg + params.passed_pawn_eg_advance * advance;
+
+            // Defended by a pawn, or with a passer beside it on the same rank
+            let defended = attacks::pawn_attacks(square, color.opposite()) & own_pawns != 0;
+            let neighbours =
+                passers & bitboard::adjacent_files(file as u8) & bitboard::rank_mask(rank as u8)
+                    != 0;
+            if defended || neighbours {
+                mg += params.supported_passer_mg + params.supported_passer_mg_advance * advance;
+                eg += params.supported_passer_eg + params.supported_passer_eg_advance * advance;
+            }
+
+            // Anything on the square in front stops the pawn for now
+            let front = bitboard::forward(1u64 << square, color);
+            if board.get_all_occupied() & front != 0 {
+                mg -= params.blockaded_passer_mg + params.blockaded_passer_mg_advance * advance;
+                eg -= params.blockaded_passer_eg + params.blockaded_passer_eg_advance * advance;
+            }
+
+            // Rule of the square: with no pieces to help, the king must reach the
+            // promotion square before the pawn does. The double step saves a move
+            if enemy_pieces == 0
+                && let Some(king) = enemy_king
+            {
+                let promotion = match color {
+                    Color::White => 56 + square % 8,
+                    Color::Black => square % 8,
+                };
+                let pawn_moves = (6 - advance).min(5);
+                let king_file = (king % 8) as i32;
+                let king_rank = (king / 8) as i32;
+                let promotion_rank = (promotion / 8) as i32;
+                let king_moves = (king_file - file)
+                    .abs()
+                    .max((king_rank - promotion_rank).abs());
+                let tempo = if board.to_move == (color == Color::White) {
+                    0
+                } else {
+                    1
+                };
+                if king_moves - tempo > pawn_moves {
+                    eg += params.unstoppable_passer;
                 }
             }
         }
+        (mg, eg)
+    }
 
-        score
+    /// Midgame and endgame king placement: shelter on the back rank while there is
+    /// material to attack it, then walk to the centre
+    pub fn king_activity(board: &Board, color: Color) -> (i32, i32) {
+        Self::king_activity_with(board, color, &EvalParams::DEFAULT)
     }
 
-    pub fn pawn_structure_balance(board: &Board) -> i32 {
-        Self::pawn_structure(board, Color::White) - Self::pawn_structure(board, Color::Black)
+    fn king_activity_with(board: &Board, color: Color, params: &EvalParams) -> (i32, i32) {
+        let Some(&square) = board.get_piece_squares(color, Piece::King).first() else {
+            return (0, 0);
+        };
+        let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+        let home_distance = match color {
+            Color::White => rank,
+            Color::Black => 7 - rank,
+        };
+        let centre_distance = (3 - file).max(file - 4) + (3 - rank).max(rank - 4);
+        (
+            -params.king_shelter * home_distance,
+            params.king_centre - params.king_centre_distance * centre_distance,
+        )
+    }
+
+    /// Remaining non-pawn material on a 0-24 scale: 24 with every piece on the board,
+    /// 0 with only kings and pawns. Minor pieces count 1, rooks 2 and queens 4
+    pub fn game_phase(board: &Board) -> i32 {
+        let minors =
+            (board.white_knights | board.black_knights | board.white_bishops | board.black_bishops)
+                .count_ones();
+        let rooks = (board.white_rooks | board.black_rooks).count_ones();
+        let queens = (board.white_queens | board.black_queens).count_ones();
+        ((minors + 2 * rooks + 4 * queens) as i32).min(MAX_PHASE)
     }
 
+    /// Blend a midgame and an endgame score by the game phase
+    pub fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+        (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+
+    /// Turn a search score for the side to move of `board` into win, draw and loss
+    /// chances. Each side wins with a logistic of how far the score is past a margin
+    /// and the rest is drawn. The margin and the spread grow with the pieces and pawns
+    /// left, as the same advantage converts less surely in a full middlegame than in
+    /// an ending. Mates are certain and known draws are drawn
+    #[cfg(feature = "std")]
+    pub fn wdl(score: i32, board: &Board) -> Wdl {
+        if is_mate_score(score) {
+            let (win, loss) = if score > 0 { (1000, 0) } else { (0, 1000) };
+            return Wdl { win, draw: 0, loss };
+        }
+        if Self::is_known_draw(board) {
+            return Wdl {
+                win: 0,
+                draw: 1000,
+                loss: 0,
+            };
+        }
+
+        let pawns = (board.white_pawns | board.black_pawns).count_ones() as f64;
+        let phase = Self::game_phase(board) as f64;
+        let margin = 60.0 + 4.0 * phase + 3.0 * pawns;
+        let spread = 40.0 + phase + pawns;
+        let chance = |score: f64| 1.0 / (1.0 + ((margin - score) / spread).exp());
+        let win = (chance(score as f64) * 1000.0).round() as u16;
+        let loss = (chance(-score as f64) * 1000.0).round() as u16;
+        Wdl {
+            win,
+            draw: 1000u16.saturating_sub(win + loss),
+            loss,
+        }
+    }
+
+    /// Material signatures neither side can win with: everything
+    /// [`Board::is_insufficient_material`] covers, two knights against a bare king, and
+    /// opposite-coloured bishops without pawns
+    pub fn is_known_draw(board: &Board) -> bool {
+        if board.is_insufficient_material() {
+            return true;
+        }
+        let heavy_or_pawns = board.white_pawns
+            | board.black_pawns
+            | board.white_rooks
+            | board.black_rooks
+            | board.white_queens
+            | board.black_queens;
+        if heavy_or_pawns != 0 {
+            return false;
+        }
+
+        let white_minors = board.white_knights | board.white_bishops;
+        let black_minors = board.black_knights | board.black_bishops;
+        let two_knights =
+            |knights: u64, minors: u64| knights.count_ones() == 2 && minors == knights;
+        if (two_knights(board.white_knights, white_minors) && black_minors == 0)
+            || (two_knights(board.black_knights, black_minors) && white_minors == 0)
+        {
+            return true;
+        }
+
+        let lone_bishop =
+            |bishops: u64, minors: u64| bishops.count_ones() == 1 && minors == bishops;
+        lone_bishop(board.white_bishops, white_minors)
+            && lone_bishop(board.black_bishops, black_minors)
+            && (board.white_bishops & bitboard::LIGHT_SQUARES == 0)
+                != (board.black_bishops & bitboard::LIGHT_SQUARES == 0)
+    }
+
+    /// Static evaluation relative to the side to move, as the negamax search expects:
+    /// positive when the player on move stands better
     pub fn evaluate(board: &Board) -> i32 {
-        let material = Self::material_balance(board);
-        let mobility = Self::mobility_balance(board);
-        let pawn_structure = Self::pawn_structure_balance(board);
+        Self::evaluate_with(board, &EvalParams::DEFAULT)
+    }
+
+    /// [`Eval::evaluate`] with the weights of `params`
+    pub fn evaluate_with(board: &Board, params: &EvalParams) -> i32 {
+        let score = Self::evaluate_absolute_with(board, params);
+        if board.to_move { score } else { -score }
+    }
+
+    /// Static evaluation from White's point of view, for display and analysis.
+    /// With the `symmetry-check` feature every call also checks that the colour-flipped
+    /// position scores exactly the opposite, and panics otherwise
+    pub fn evaluate_absolute(board: &Board) -> i32 {
+        Self::evaluate_absolute_with(board, &EvalParams::DEFAULT)
+    }
+
+    /// [`Eval::evaluate_absolute`] with the weights of `params`
+    pub fn evaluate_absolute_with(board: &Board, params: &EvalParams) -> i32 {
+        let score = Self::evaluate_terms(board, params);
+        #[cfg(feature = "symmetry-check")]
+        assert_eq!(
+            score,
+            -Self::evaluate_terms(&board.mirror(), params),
+            "asymmetric evaluation of {}",
+            crate::fen::to_fen(board)
+        );
+        score
+    }
+
+    fn evaluate_terms(board: &Board, params: &EvalParams) -> i32 {
+        Self::breakdown_with(board, params).total
+    }
+
+    /// Every term of the static evaluation, from White's point of view
+    pub fn breakdown(board: &Board) -> EvalBreakdown {
+        Self::breakdown_with(board, &EvalParams::DEFAULT)
+    }
+
+    /// [`Eval::breakdown`] with the weights of `params`
+    pub fn breakdown_with(board: &Board, params: &EvalParams) -> EvalBreakdown {
+        let (white_passed_mg, white_passed_eg) =
+            Self::passed_pawns_with(board, Color::White, params);
+        let (black_passed_mg, black_passed_eg) =
+            Self::passed_pawns_with(board, Color::Black, params);
+        let (white_king_mg, white_king_eg) = Self::king_activity_with(board, Color::White, params);
+        let (black_king_mg, black_king_eg) = Self::king_activity_with(board, Color::Black, params);
+        let mg = white_passed_mg - black_passed_mg + white_king_mg - black_king_mg;
+        let eg = white_passed_eg - black_passed_eg + white_king_eg - black_king_eg;
+
+        let mut breakdown = EvalBreakdown {
+            material: Self::balance(board, params, Self::material_with),
+            mobility: Self::balance(board, params, Self::mobility_with),
+            pawn_structure: Self::balance(board, params, Self::pawn_structure_with),
+            imbalance: Self::balance(board, params, Self::imbalance_with),
+            rook_activity: Self::balance(board, params, Self::rook_activity_with),
+            phased: Self::taper(mg, eg, Self::game_phase(board)),
+            known_draw: Self::is_known_draw(board),
+            total: 0,
+        };
+        if !breakdown.known_draw {
+            let total = breakdown.material
+                + breakdown.mobility
+                + breakdown.pawn_structure
+                + breakdown.imbalance
+                + breakdown.rook_activity
+                + breakdown.phased;
+            breakdown.total = Self::fifty_move_scale(total, board.halfmove_clock);
+        }
+        breakdown
+    }
 
-        material + mobility + pawn_structure
+    /// Shrink `score` towards zero as the fifty-move rule approaches, down to half at
+    /// 100 halfmoves, so that a winning side prefers lines that reset the clock
+    pub fn fifty_move_scale(score: i32, halfmove_clock: u16) -> i32 {
+        let clock = halfmove_clock.min(100) as i32;
+        score * (200 - clock) / 200
     }
 
+    /// Alpha-beta search with scores from White's point of view, a wrapper around
+    /// [`Eval::negamax`] whose scores are relative to the side to move.
+    /// `maximizing_player` must match `board.to_move` (true for White)
     pub fn alpha_beta(
         board: &Board,
         depth: u8,
         alpha: i32,
         beta: i32,
         maximizing_player: bool,
+        ctx: &mut SearchContext,
     ) -> i32 {
+        let mut board = *board;
+        if maximizing_player {
+            Self::negamax(&mut board, depth, alpha, beta, Color::White, ctx)
+        } else {
+            -Self::negamax(&mut board, depth, -beta, -alpha, Color::Black, ctx)
+        }
+    }
+
+    /// Principal variation search in negamax form, scored relative to `color`, which must
+    /// be the side to move on `board`.
+    /// The first move is searched with the full window and the rest with a null window,
+    /// re-searching only when a later move turns out to be better. Near the leaves of
+    /// null window nodes, quiet moves that cannot lift the static evaluation above
+    /// alpha are skipped (futility pruning). Results are cached in
+    /// the transposition table and quiet cutoff moves feed the history heuristic.
+    /// Checkmate scores `-(MATE_SCORE - ply)` so shorter mates are preferred, and a
+    /// position already on the search path or in the game history is a draw, as is one
+    /// where the fifty-move rule applies. Endings in the tablebase, when one is set, score
+    /// their exact distance to mate.
+    /// Moves are played and taken back on `board`, which is unchanged on return
+    pub fn negamax(
+        board: &mut Board,
+        depth: u8,
+        alpha: i32,
+        beta: i32,
+        color: Color,
+        ctx: &mut SearchContext,
+    ) -> i32 {
+        debug_assert_eq!(board.to_move, color == Color::White);
+        ctx.nodes += 1;
+        ctx.seldepth = ctx.seldepth.max(ctx.ply);
+        ctx.check_limits();
+
+        if ctx.stopped() {
+            return 0;
+        }
+
+        let key = board.hash();
+        // The root must still find a move, even when it repeats an earlier position
+        if ctx.ply > 0 && ctx.is_repetition(key, board.halfmove_clock) {
+            return 0;
+        }
+        // Fifty moves without a capture or pawn move draw, unless the last one mated
+        if ctx.ply > 0 && board.halfmove_clock >= 100 && !Move::is_checkmate(board, color) {
+            return 0;
+        }
+        if ctx.ply > 0
+            && let Some(dtm) = ctx
+                .tablebase
+                .as_deref()
+                .and_then(|tablebase| tablebase::probe(tablebase, board))
+        {
+            return dtm.to_score(ctx.ply);
+        }
+
         if depth == 0 {
-            return Self::evaluate(board);
+            // A mated or stalemated leaf must not be scored by material
+            if !Move::has_legal_move(board, color) {
+                return if Move::is_in_check(board, color) {
+                    -MATE_SCORE + ctx.ply as i32
+                } else {
+                    0
+                };
+            }
+            #[cfg(feature = "stats")]
+            {
+                ctx.stats.leaf_nodes += 1;
+            }
+            return Self::evaluate_with(board, &ctx.eval_params);
         }
 
-        let color = if maximizing_player {
-            Color::White
-        } else {
-            Color::Black
-        };
-        let moves = Moves::generate_all_moves(board, color);
+        // Mate distance pruning: being mated here or mating next move bounds the score,
+        // so a window outside those bounds cannot improve on a shorter mate
+        let ply = ctx.ply as i32;
+        let mut alpha = alpha.max(-MATE_SCORE + ply);
+        let mut beta = beta.min(MATE_SCORE - ply - 1);
+        if alpha >= beta {
+            #[cfg(feature = "stats")]
+            {
+                ctx.stats.mate_distance_prunes += 1;
+            }
+            return alpha;
+        }
+        let mut tt_move = None;
 
-        if maximizing_player {
-            let mut max_eval = i32::MIN;
-            let mut alpha = alpha;
-
-            for mv in moves {
-                let mut new_board = board.clone();
-                new_board.make_move(&mv);
-                let eval = Self::alpha_beta(&new_board, depth - 1, alpha, beta, false);
-                max_eval = max_eval.max(eval);
-                alpha = alpha.max(eval);
-                if beta <= alpha {
-                    break; // Beta cut-off
+        // Probe the transposition table
+        #[cfg(feature = "stats")]
+        {
+            ctx.stats.tt_probes += 1;
+        }
+        if let Some(entry) = ctx.tt.probe(key) {
+            #[cfg(feature = "stats")]
+            {
+                ctx.stats.tt_hits += 1;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                depth,
+                ply = ctx.ply,
+                entry_depth = entry.depth,
+                bound = ?entry.bound,
+                "tt hit"
+            );
+            tt_move = entry.best_move;
+            if entry.depth >= depth {
+                let score = score_from_tt(entry.score, ctx.ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower => alpha = alpha.max(score),
+                    Bound::Upper => beta = beta.min(score),
+                }
+                if alpha >= beta {
+                    return score;
                 }
             }
+        }
 
-            max_eval
-        } else {
-            let mut min_eval = i32::MAX;
-            let mut beta = beta;
-
-            for mv in moves {
-                let mut new_board = board.clone();
-                new_board.make_move(&mv);
-                let eval = Self::alpha_beta(&new_board, depth - 1, alpha, beta, true);
-                min_eval = min_eval.min(eval);
-                beta = beta.min(eval);
-                if beta <= alpha {
-                    break; // Alpha cut-off
+        let original_alpha = alpha;
+        let enemy_color = color.opposite();
+
+        let mut moves = Move::generate_legal_moves(board, color);
+        if moves.is_empty() {
+            return if Move::is_in_check(board, color) {
+                -MATE_SCORE + ctx.ply as i32
+            } else {
+                0
+            };
+        }
+        order_moves_by(board, &mut moves, tt_move, |mv| ctx.quiet_score(board, mv));
+
+        // Shallow null window nodes out of check may skip hopeless quiet moves
+        let shallow = (depth as usize) < FUTILITY_MARGINS.len()
+            && beta - alpha == 1
+            && !is_mate_score(alpha)
+            && board.checkers() == 0;
+        let futile = shallow
+            && Self::evaluate_with(board, &ctx.eval_params) + FUTILITY_MARGINS[depth as usize]
+                <= alpha;
+
+        let mut best_eval = -INFINITY;
+        let mut best_move = None;
+        let mut pruned = false;
+
+        ctx.path.push(key);
+        for (i, mv) in moves.iter().enumerate() {
+            let current = piece_to(board, mv);
+            let prunable = shallow && i > 0 && is_quiet(mv);
+            let unpromising = prunable
+                && ctx.continuation_score(board, mv) < -HISTORY_PRUNING_MARGIN * depth as i32;
+            let undo = board.make_move(mv);
+            // Checks are kept, they may change the evaluation by more than the margin
+            if prunable && (futile || unpromising) && board.checkers() == 0 {
+                board.unmake_move(&undo);
+                pruned = true;
+                #[cfg(feature = "stats")]
+                if futile {
+                    ctx.stats.futility_prunes += 1;
+                } else {
+                    ctx.stats.history_prunes += 1;
+                }
+                continue;
+            }
+
+            ctx.played.push(current);
+            ctx.ply += 1;
+            let eval = if i == 0 {
+                -Self::negamax(board, depth - 1, -beta, -alpha, enemy_color, ctx)
+            } else {
+                // Null window probe, re-searched with the full window if it beats alpha
+                let probe = -Self::negamax(board, depth - 1, -alpha - 1, -alpha, enemy_color, ctx);
+                if probe > alpha && probe < beta {
+                    -Self::negamax(board, depth - 1, -beta, -alpha, enemy_color, ctx)
+                } else {
+                    probe
+                }
+            };
+            ctx.ply -= 1;
+            ctx.played.pop();
+            board.unmake_move(&undo);
+
+            // A stopped search returns garbage that must not reach the table
+            if ctx.stopped() {
+                ctx.path.pop();
+                return 0;
+            }
+
+            if eval > best_eval {
+                best_eval = eval;
+                best_move = Some(*mv);
+            }
+            alpha = alpha.max(eval);
+            if alpha >= beta {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(
+                    depth,
+                    ply = ctx.ply,
+                    move_index = i,
+                    mv = %mv.to_uci(),
+                    "beta cutoff"
+                );
+                #[cfg(feature = "stats")]
+                {
+                    ctx.stats.cutoffs[i.min(SearchStats::CUTOFF_BUCKETS - 1)] += 1;
+                }
+                if is_quiet(mv) {
+                    // Moves skipped by pruning were never searched and are not penalised
+                    let tried = if pruned { &[][..] } else { &moves[..i] };
+                    ctx.reward_quiet(board, mv, tried, depth);
                 }
+                break; // Beta cut-off
             }
+        }
+        ctx.path.pop();
 
-            min_eval
+        let bound = if best_eval <= original_alpha {
+            Bound::Upper
+        } else if best_eval >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        #[cfg(feature = "stats")]
+        match bound {
+            Bound::Exact => ctx.stats.pv_nodes += 1,
+            Bound::Lower => ctx.stats.cut_nodes += 1,
+            Bound::Upper => ctx.stats.all_nodes += 1,
         }
+        ctx.tt.store(
+            key,
+            depth,
+            score_to_tt(best_eval, ctx.ply),
+            bound,
+            best_move,
+        );
+
+        best_eval
     }
 }
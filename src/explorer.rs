@@ -0,0 +1,48 @@
+use crate::board::Board;
+use crate::book::OpeningBook;
+use crate::database::GameDatabase;
+use crate::moves::Move;
+
+/// A move of the explorer with the number of games that played it and how many of
+/// them White won, drew and Black won
+pub type MoveRow = (Move, u32, u32, u32, u32);
+
+/// Somewhere the explorer can look positions up
+pub trait ExplorerSource {
+    /// The moves known in `board`, most played first
+    fn stats(&self, board: &Board) -> Vec<MoveRow>;
+}
+
+impl ExplorerSource for GameDatabase {
+    fn stats(&self, board: &Board) -> Vec<MoveRow> {
+        self.moves(board)
+            .into_iter()
+            .map(|stats| {
+                let results = stats.results;
+                (
+                    stats.mv,
+                    results.games(),
+                    results.white_wins,
+                    results.draws,
+                    results.black_wins,
+                )
+            })
+            .collect()
+    }
+}
+
+/// A Polyglot book keeps a weight per move but not the results behind it, so its rows
+/// carry the weight as the game count and no wins, draws or losses
+impl ExplorerSource for OpeningBook {
+    fn stats(&self, board: &Board) -> Vec<MoveRow> {
+        self.moves(board)
+            .into_iter()
+            .map(|(mv, weight)| (mv, weight as u32, 0, 0, 0))
+            .collect()
+    }
+}
+
+/// The moves `source` knows in `board` with their game counts and results
+pub fn stats(source: &dyn ExplorerSource, board: &Board) -> Vec<MoveRow> {
+    source.stats(board)
+}
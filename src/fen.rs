@@ -1,94 +1,290 @@
-use crate::board::Board;
+use crate::board::{
+    BLACK_KINGSIDE, BLACK_QUEENSIDE, Board, STANDARD_CASTLING_ROOKS, WHITE_KINGSIDE,
+    WHITE_QUEENSIDE,
+};
 use crate::piece::{Color, Piece};
 use crate::util::*;
+use alloc::{format, string::String, string::ToString, vec::Vec};
+use core::fmt;
 
 pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
-pub fn parse_fen(fen: &str) -> Result<Board, String> {
+/// Why a FEN record was rejected. Offsets are byte positions in the input string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// Not six whitespace-separated fields (seven with three-check counters)
+    FieldCount {
+        found: usize,
+    },
+    /// A character in the piece placement that is neither a piece letter nor a digit 1-8
+    InvalidPiece {
+        char: char,
+        offset: usize,
+    },
+    /// A rank that does not describe exactly eight squares, numbered 1-8
+    InvalidRankLength {
+        rank: u8,
+        offset: usize,
+    },
+    /// A piece placement without exactly eight ranks
+    InvalidRankCount {
+        found: usize,
+        offset: usize,
+    },
+    InvalidActiveColor {
+        offset: usize,
+    },
+    InvalidCastling {
+        char: char,
+        offset: usize,
+    },
+    /// Not a square, or not on the rank a double push by the side that just moved lands behind
+    InvalidEnPassant {
+        offset: usize,
+    },
+    InvalidHalfmoveClock {
+        offset: usize,
+    },
+    InvalidFullmoveNumber {
+        offset: usize,
+    },
+    InvalidCheckCounts {
+        offset: usize,
+    },
+}
+
+impl FenError {
+    /// Byte offset of the offending input, `None` for a wrong field count
+    pub fn offset(&self) -> Option<usize> {
+        match *self {
+            FenError::FieldCount { .. } => None,
+            FenError::InvalidPiece { offset, .. }
+            | FenError::InvalidRankLength { offset, .. }
+            | FenError::InvalidRankCount { offset, .. }
+            | FenError::InvalidActiveColor { offset }
+            | FenError::InvalidCastling { offset, .. }
+            | FenError::InvalidEnPassant { offset }
+            | FenError::InvalidHalfmoveClock { offset }
+            | FenError::InvalidFullmoveNumber { offset }
+            | FenError::InvalidCheckCounts { offset } => Some(offset),
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::FieldCount { found } => {
+                write!(f, "expected 6 fields in FEN, found {}", found)
+            }
+            FenError::InvalidPiece { char, offset } => {
+                write!(f, "invalid piece '{}' at offset {}", char, offset)
+            }
+            FenError::InvalidRankLength { rank, offset } => write!(
+                f,
+                "rank {} does not have 8 squares (offset {})",
+                rank, offset
+            ),
+            FenError::InvalidRankCount { found, offset } => {
+                write!(f, "expected 8 ranks, found {} (offset {})", found, offset)
+            }
+            FenError::InvalidActiveColor { offset } => {
+                write!(f, "invalid active color at offset {}", offset)
+            }
+            FenError::InvalidCastling { char, offset } => {
+                write!(f, "invalid castling right '{}' at offset {}", char, offset)
+            }
+            FenError::InvalidEnPassant { offset } => {
+                write!(f, "invalid en passant square at offset {}", offset)
+            }
+            FenError::InvalidHalfmoveClock { offset } => {
+                write!(f, "invalid halfmove clock at offset {}", offset)
+            }
+            FenError::InvalidFullmoveNumber { offset } => {
+                write!(f, "invalid fullmove number at offset {}", offset)
+            }
+            FenError::InvalidCheckCounts { offset } => {
+                write!(f, "invalid check counts at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl core::error::Error for FenError {}
+
+impl From<FenError> for String {
+    fn from(error: FenError) -> Self {
+        error.to_string()
+    }
+}
+
+pub fn parse_fen(fen: &str) -> Result<Board, FenError> {
     let mut board = Board::new();
 
-    let parts: [&str; 6] = fen
-        .split(' ')
-        .collect::<Vec<_>>()
+    // Each field with its byte offset. Fields are separated by any run of whitespace
+    let mut fields: Vec<(usize, &str)> = fen
+        .split_whitespace()
+        .map(|field| (field.as_ptr() as usize - fen.as_ptr() as usize, field))
+        .collect();
+
+    // Optional seventh field with the checks given so far in three-check, e.g. "+1+0"
+    if fields.len() == 7 {
+        let (offset, checks) = fields.pop().unwrap();
+        let error = FenError::InvalidCheckCounts { offset };
+        let counts: Vec<&str> = checks.split('+').collect();
+        let ["", white, black] = counts[..] else {
+            return Err(error);
+        };
+        board.white_checks = white.parse().map_err(|_| error.clone())?;
+        board.black_checks = black.parse().map_err(|_| error)?;
+    }
+
+    let found = fields.len();
+    let parts: [(usize, &str); 6] = fields
         .try_into()
-        .map_err(|_| "Invalid FEN format")?;
+        .map_err(|_| FenError::FieldCount { found })?;
     let [
-        position,
-        to_move,
-        castling_rights,
-        en_passant,
-        halfmove_clock,
-        fullmove_number,
+        (position_offset, position),
+        (to_move_offset, to_move),
+        (castling_offset, castling_rights),
+        (en_passant_offset, en_passant),
+        (halfmove_offset, halfmove_clock),
+        (fullmove_offset, fullmove_number),
     ] = parts;
 
-    // Position
-    for (rank, row) in position.split('/').rev().enumerate() {
+    // Position, listed from the eighth rank down
+    let ranks: Vec<&str> = position.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::InvalidRankCount {
+            found: ranks.len(),
+            offset: position_offset,
+        });
+    }
+    let mut rank_offset = position_offset;
+    for (i, row) in ranks.into_iter().enumerate() {
+        let rank = 7 - i;
+        let length_error = FenError::InvalidRankLength {
+            rank: rank as u8 + 1,
+            offset: rank_offset,
+        };
         let mut file = 0;
-        for char in row.chars() {
-            let square = (rank * 8 + file) as u8;
-            match char {
-                '1'..='8' => file += char.to_digit(10).unwrap() as usize - 1, // -1 because we add that +1 back to the file for the square
-                'p' => board.set_piece(Piece::Pawn, Color::Black, square),
-                'n' => board.set_piece(Piece::Knight, Color::Black, square),
-                'b' => board.set_piece(Piece::Bishop, Color::Black, square),
-                'r' => board.set_piece(Piece::Rook, Color::Black, square),
-                'q' => board.set_piece(Piece::Queen, Color::Black, square),
-                'k' => board.set_piece(Piece::King, Color::Black, square),
-                'P' => board.set_piece(Piece::Pawn, Color::White, square),
-                'N' => board.set_piece(Piece::Knight, Color::White, square),
-                'B' => board.set_piece(Piece::Bishop, Color::White, square),
-                'R' => board.set_piece(Piece::Rook, Color::White, square),
-                'Q' => board.set_piece(Piece::Queen, Color::White, square),
-                'K' => board.set_piece(Piece::King, Color::White, square),
-                _ => {}
+        for (char_offset, char) in row.char_indices() {
+            if let Some(empty) = char.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                file += empty as usize;
+                if file > 8 {
+                    return Err(length_error);
+                }
+                continue;
             }
+            let Some((piece, color)) = Piece::from_char(char) else {
+                return Err(FenError::InvalidPiece {
+                    char,
+                    offset: rank_offset + char_offset,
+                });
+            };
+            if file == 8 {
+                return Err(length_error);
+            }
+            board.set_piece(piece, color, (rank * 8 + file) as u8);
             file += 1;
         }
+        if file != 8 {
+            return Err(length_error);
+        }
+        rank_offset += row.len() + 1;
     }
 
     // To move
-    if to_move == "w" {
-        board.to_move = true;
-    } else if to_move == "b" {
-        board.to_move = false;
-    } else {
-        return Err("Invalid active color in FEN".to_string());
-    }
+    board.to_move = match to_move {
+        "w" => true,
+        "b" => false,
+        _ => {
+            return Err(FenError::InvalidActiveColor {
+                offset: to_move_offset,
+            });
+        }
+    };
 
-    // Castling rights (e.g. KQk => 0b1110)
+    // Castling rights: KQkq (X-FEN picks the outermost rook) or rook files (Shredder-FEN)
     board.castling_rights = 0;
-    for char in castling_rights.chars() {
-        match char {
-            'K' => board.castling_rights |= 0b1000,
-            'Q' => board.castling_rights |= 0b0100,
-            'k' => board.castling_rights |= 0b0010,
-            'q' => board.castling_rights |= 0b0001,
-            _ => {}
+    board.castling_rooks = STANDARD_CASTLING_ROOKS;
+    if castling_rights != "-" {
+        for (char_offset, char) in castling_rights.char_indices() {
+            let (color, side) = match char {
+                'K' => (Color::White, Some(true)),
+                'Q' => (Color::White, Some(false)),
+                'k' => (Color::Black, Some(true)),
+                'q' => (Color::Black, Some(false)),
+                'A'..='H' => (Color::White, None),
+                'a'..='h' => (Color::Black, None),
+                _ => {
+                    return Err(FenError::InvalidCastling {
+                        char,
+                        offset: castling_offset + char_offset,
+                    });
+                }
+            };
+            let back_rank = back_rank(color);
+            let king_file = king_file(&board, color);
+            let rook = match side {
+                Some(kingside) => outermost_rook(&board, color, kingside)
+                    .unwrap_or(back_rank + if kingside { 7 } else { 0 }),
+                None => back_rank + (char.to_ascii_lowercase() as u8 - b'a'),
+            };
+            let right = castling_right(color, rook % 8 > king_file);
+            board.castling_rights |= right;
+            board.castling_rooks[right.trailing_zeros() as usize] = rook;
         }
     }
 
-    // En passant
-    if en_passant == "-" {
-        board.en_passant = None;
-    } else {
-        board.en_passant = pos_to_u8(en_passant);
-    }
+    // En passant: behind a pawn that just moved two squares, so rank 6 with White to move
+    board.en_passant = match en_passant {
+        "-" => None,
+        _ => {
+            let expected_rank = if board.to_move { 5 } else { 2 };
+            let square = pos_to_u8(en_passant).filter(|&square| square / 8 == expected_rank);
+            if square.is_none() {
+                return Err(FenError::InvalidEnPassant {
+                    offset: en_passant_offset,
+                });
+            }
+            square
+        }
+    };
 
     // Halfmove clock
     board.halfmove_clock = halfmove_clock
         .parse()
-        .map_err(|_| "Invalid halfmove clock")?;
+        .map_err(|_| FenError::InvalidHalfmoveClock {
+            offset: halfmove_offset,
+        })?;
 
     // Fullmove number
-    board.fullmove_number = fullmove_number
-        .parse()
-        .map_err(|_| "Invalid fullmove number")?;
+    board.fullmove_number =
+        fullmove_number
+            .parse()
+            .map_err(|_| FenError::InvalidFullmoveNumber {
+                offset: fullmove_offset,
+            })?;
+
+    // Side to move, castling and en passant were assigned directly
+    board.refresh_hash();
 
-    // Return
     Ok(board)
 }
 
+/// FEN with castling rights as KQkq, falling back to rook files (X-FEN) only when another
+/// rook stands further out than the castling one
 pub fn to_fen(board: &Board) -> String {
+    write_fen(board, false)
+}
+
+/// FEN with castling rights always written as rook files, e.g. `HAha` (Shredder-FEN)
+pub fn to_shredder_fen(board: &Board) -> String {
+    write_fen(board, true)
+}
+
+fn write_fen(board: &Board, shredder: bool) -> String {
     let mut fen = String::new();
 
     // Pieces
@@ -147,17 +343,31 @@ pub fn to_fen(board: &Board) -> String {
 
     // Castling rights
     fen.push(' ');
-    if board.castling_rights & 0b1000 != 0 {
-        fen.push('K');
-    }
-    if board.castling_rights & 0b0100 != 0 {
-        fen.push('Q');
-    }
-    if board.castling_rights & 0b0010 != 0 {
-        fen.push('k');
-    }
-    if board.castling_rights & 0b0001 != 0 {
-        fen.push('q');
+    for (index, (color, kingside)) in [
+        (Color::White, true),
+        (Color::White, false),
+        (Color::Black, true),
+        (Color::Black, false),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if board.castling_rights & castling_right(color, kingside) == 0 {
+            continue;
+        }
+        let rook = board.castling_rooks[index];
+        let letter =
+            if shredder || outermost_rook(board, color, kingside).is_some_and(|sq| sq != rook) {
+                (b'a' + rook % 8) as char
+            } else if kingside {
+                'k'
+            } else {
+                'q'
+            };
+        fen.push(match color {
+            Color::White => letter.to_ascii_uppercase(),
+            Color::Black => letter,
+        });
     }
     if board.castling_rights == 0 {
         fen.push('-');
@@ -178,5 +388,51 @@ pub fn to_fen(board: &Board) -> String {
     fen.push(' ');
     fen.push_str(&board.fullmove_number.to_string());
 
+    // Three-check counters, left out while no check has been counted
+    if board.white_checks > 0 || board.black_checks > 0 {
+        fen.push_str(&format!(" +{}+{}", board.white_checks, board.black_checks));
+    }
+
     fen
 }
+
+fn castling_right(color: Color, kingside: bool) -> u8 {
+    match (color, kingside) {
+        (Color::White, true) => WHITE_KINGSIDE,
+        (Color::White, false) => WHITE_QUEENSIDE,
+        (Color::Black, true) => BLACK_KINGSIDE,
+        (Color::Black, false) => BLACK_QUEENSIDE,
+    }
+}
+
+fn back_rank(color: Color) -> u8 {
+    match color {
+        Color::White => 0,
+        Color::Black => 56,
+    }
+}
+
+/// File of the king on its back rank, assuming the e-file if it is elsewhere
+fn king_file(board: &Board, color: Color) -> u8 {
+    let rank_mask = 0xFFu64 << back_rank(color);
+    match board.get_bb(Piece::King, color) & rank_mask {
+        0 => 4,
+        king => king.trailing_zeros() as u8 % 8,
+    }
+}
+
+/// Back-rank rook furthest from the king on the given side
+fn outermost_rook(board: &Board, color: Color, kingside: bool) -> Option<u8> {
+    let back_rank = back_rank(color);
+    let king_file = king_file(board, color);
+    let rooks = board.get_bb(Piece::Rook, color);
+    let files: Vec<u8> = if kingside {
+        (king_file + 1..8).rev().collect()
+    } else {
+        (0..king_file).collect()
+    };
+    files
+        .into_iter()
+        .map(|file| back_rank + file)
+        .find(|&square| rooks & (1u64 << square) != 0)
+}
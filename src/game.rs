@@ -1,434 +1,835 @@
 use crate::board::Board;
-use crate::fen::START_FEN;
-use crate::moves::{MoveType, Moves};
+use crate::book::OpeningBook;
+use crate::fen::{FenError, START_FEN};
+use crate::movelist::MoveList;
+use crate::moves::{Move, MoveType};
+use crate::notation::{MoveParseError, MoveParser};
+use crate::options::EngineOptions;
 use crate::piece::{Color, Piece};
-use crate::util::{parse_algebraic, pos_to_u8};
-use std::io::{self, Write};
+use crate::search::{Search, SearchLimits};
+use crate::util::pos_to_u8;
+use crate::variant::{Rules, Standard};
+use crate::variation::{NodeId, VariationTree};
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+pub use crate::cli::ChessGame;
+
+/// Why a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationReason {
+    Checkmate,
+    Stalemate,
+    /// The same position occurred three times
+    Repetition,
+    /// Fifty moves by each side without a capture or pawn move
+    FiftyMove,
+    InsufficientMaterial,
+    Resignation,
+    Timeout,
+    /// A king was caught in an atomic explosion
+    Explosion,
+    /// A side delivered its third check in three-check
+    ThreeChecks,
+}
 
-pub struct ChessGame {
-    board: Board,
-    current_player: Color,
-    move_history: Vec<String>,
+impl fmt::Display for TerminationReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            TerminationReason::Checkmate => "checkmate",
+            TerminationReason::Stalemate => "stalemate",
+            TerminationReason::Repetition => "threefold repetition",
+            TerminationReason::FiftyMove => "the fifty-move rule",
+            TerminationReason::InsufficientMaterial => "insufficient material",
+            TerminationReason::Resignation => "resignation",
+            TerminationReason::Timeout => "timeout",
+            TerminationReason::Explosion => "explosion",
+            TerminationReason::ThreeChecks => "three checks",
+        };
+        f.write_str(text)
+    }
 }
 
-impl ChessGame {
-    pub fn new() -> Self {
-        Self {
-            board: Board::from_fen(START_FEN),
-            current_player: Color::White,
-            move_history: Vec::new(),
+/// Outcome of a finished game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins(TerminationReason),
+    BlackWins(TerminationReason),
+    Draw(TerminationReason),
+}
+
+impl GameResult {
+    /// A win for `color`'s opponent
+    pub(crate) fn loss_for(color: Color, reason: TerminationReason) -> Self {
+        match color {
+            Color::White => GameResult::BlackWins(reason),
+            Color::Black => GameResult::WhiteWins(reason),
         }
     }
 
-    pub fn from_fen(fen: &str) -> Self {
-        Self {
-            board: Board::from_fen(fen),
-            current_player: Color::White,
-            move_history: Vec::new(),
+    pub fn reason(&self) -> TerminationReason {
+        match *self {
+            GameResult::WhiteWins(reason)
+            | GameResult::BlackWins(reason)
+            | GameResult::Draw(reason) => reason,
         }
     }
 
-    fn display_board(&self) {
-        println!("\n   a b c d e f g h");
-        println!("  ┌─────────────────┐");
-
-        for rank in (0..8).rev() {
-            print!("{} │ ", rank + 1);
-            for file in 0..8 {
-                let square = rank * 8 + file;
-                match self.board.get_piece_at(square) {
-                    Some((piece, color)) => {
-                        let symbol = match (piece, color) {
-                            (Piece::King, Color::White) => "♔",
-                            (Piece::Queen, Color::White) => "♕",
-                            (Piece::Rook, Color::White) => "♖",
-                            (Piece::Bishop, Color::White) => "♗",
-                            (Piece::Knight, Color::White) => "♘",
-                            (Piece::Pawn, Color::White) => "♙",
-                            (Piece::King, Color::Black) => "♚",
-                            (Piece::Queen, Color::Black) => "♛",
-                            (Piece::Rook, Color::Black) => "♜",
-                            (Piece::Bishop, Color::Black) => "♝",
-                            (Piece::Knight, Color::Black) => "♞",
-                            (Piece::Pawn, Color::Black) => "♟",
-                        };
-                        print!("{} ", symbol);
-                    }
-                    None => print!("· "),
-                }
-            }
-            println!("│ {}", rank + 1);
+    /// The winning side, `None` for a draw
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameResult::WhiteWins(_) => Some(Color::White),
+            GameResult::BlackWins(_) => Some(Color::Black),
+            GameResult::Draw(_) => None,
         }
+    }
 
-        println!("  └─────────────────┘");
-        println!("   a b c d e f g h\n");
+    /// PGN result token ("1-0", "0-1" or "1/2-1/2")
+    pub fn pgn_token(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins(_) => "1-0",
+            GameResult::BlackWins(_) => "0-1",
+            GameResult::Draw(_) => "1/2-1/2",
+        }
     }
+}
 
-    fn display_game_status(&self) {
-        let current_color_name = match self.current_player {
-            Color::White => "White",
-            Color::Black => "Black",
-        };
+/// Where a game stands, see [`Game::status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    InProgress { to_move: Color, in_check: bool },
+    Over(GameResult),
+}
 
-        println!("Current player: {}", current_color_name);
+/// Why [`Game::play`] refused a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayError {
+    /// The game has already ended with this result
+    GameOver(GameResult),
+    /// Not a legal move of the current position
+    IllegalMove(Move),
+}
 
-        if Moves::is_in_check(&self.board, self.current_player) {
-            if Moves::is_checkmate(&self.board, self.current_player) {
-                let winner = match self.current_player {
-                    Color::White => "Black",
-                    Color::Black => "White",
-                };
-                println!("🏁 CHECKMATE! {} wins!", winner);
-            } else {
-                println!("⚠️  {} is in CHECK!", current_color_name);
-            }
-        } else if Moves::is_stalemate(&self.board, self.current_player) {
-            println!("🤝 STALEMATE! The game is a draw.");
+impl fmt::Display for PlayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlayError::GameOver(result) => write!(f, "the game is over by {}", result.reason()),
+            PlayError::IllegalMove(mv) => write!(f, "illegal move '{}'", mv.to_uci()),
         }
+    }
+}
+
+impl std::error::Error for PlayError {}
 
-        let legal_moves = Moves::generate_legal_moves(&self.board, self.current_player);
-        println!("Legal moves available: {}", legal_moves.len());
+impl From<PlayError> for String {
+    fn from(error: PlayError) -> Self {
+        error.to_string()
     }
+}
 
-    fn show_legal_moves(&self) {
-        let legal_moves = Moves::generate_legal_moves(&self.board, self.current_player);
+/// Receives the events of a [`Game`] as they happen, e.g. to play sounds, log moves or
+/// update a GUI without polling the board. Every hook does nothing by default. Moves
+/// replayed by [`Game::load_pgn`] are reported too
+pub trait GameObserver: Send {
+    /// Called first for every move, after it was played
+    fn on_move_played(&mut self, _record: &MoveRecord) {}
 
-        if legal_moves.is_empty() {
-            println!("No legal moves available!");
-            return;
-        }
+    /// The move took `piece`
+    fn on_capture(&mut self, _record: &MoveRecord, _piece: Piece) {}
 
-        println!("\nLegal moves (showing first 20):");
-        for (i, mv) in legal_moves.iter().take(20).enumerate() {
-            let piece_name = if let Some((piece, _)) = self.board.get_piece_at(mv.from) {
-                format!("{:?}", piece)
-            } else {
-                "?".to_string()
-            };
+    /// The move put `color`, now to move, in check
+    fn on_check(&mut self, _color: Color) {}
 
-            let move_desc = match mv.move_type {
-                MoveType::Normal => "",
-                MoveType::Capture => " (capture)",
-                MoveType::Castle => " (castle)",
-                MoveType::EnPassant => " (en passant)",
-                MoveType::Double => " (double pawn)",
-                MoveType::Promotion { piece } => &format!(" (promote to {:?})", piece),
-                MoveType::PromotionCapture { piece } => {
-                    &format!(" (capture + promote to {:?})", piece)
-                }
-            };
+    /// The game just ended, on the board or by resignation or timeout
+    fn on_game_end(&mut self, _result: GameResult) {}
+}
 
-            print!(
-                "{:2}. {} ({}){}",
-                i + 1,
-                mv.to_algebraic(),
-                piece_name,
-                move_desc
-            );
-            if (i + 1) % 2 == 0 {
-                println!();
-            } else {
-                print!("    ");
-            }
-        }
+/// One move of a game, with what is needed to show, export and take it back
+#[derive(Debug, Clone)]
+pub struct MoveRecord {
+    pub mv: Move,
+    pub san: String,
+    /// The position the move was played in
+    pub board_before: Board,
+    /// Piece taken by the move, a pawn for en passant
+    pub captured: Option<Piece>,
+    /// Time the player or engine took over the move, when played through
+    /// [`ChessGame::run`] or recorded with [`Game::set_move_time`]
+    pub time: Option<Duration>,
+}
 
-        if legal_moves.len() % 2 == 1 {
-            println!();
+impl MoveRecord {
+    /// The record of playing `mv` in `board`, before any time is known
+    pub fn new(board: &Board, mv: Move) -> Self {
+        let captured = match mv.move_type {
+            MoveType::EnPassant => Some(Piece::Pawn),
+            _ => board.get_piece_at(mv.to).map(|(piece, _)| piece),
+        };
+        Self {
+            mv,
+            san: mv.to_san(board),
+            board_before: *board,
+            captured,
+            time: None,
         }
+    }
 
-        if legal_moves.len() > 20 {
-            println!("... and {} more moves", legal_moves.len() - 20);
-        }
-        println!();
+    pub fn fen_before(&self) -> String {
+        crate::fen::to_fen(&self.board_before)
     }
+}
 
-    fn looks_like_move_input(&self, input: &str) -> bool {
-        let input = input.trim();
+/// Who played a game, and when and where, for the PGN Seven Tag Roster. Unknown values
+/// are exported as "?"
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameInfo {
+    pub event: Option<String>,
+    pub site: Option<String>,
+    /// As `YYYY.MM.DD`, with `??` for unknown parts
+    pub date: Option<String>,
+    pub round: Option<String>,
+    pub white: Option<String>,
+    pub black: Option<String>,
+}
 
-        // Check if it looks like coordinate notation (e.g., "e2e4", "g1f3")
-        if input.len() == 4 {
-            let chars: Vec<char> = input.chars().collect();
-            if chars[0].is_ascii_lowercase()
-                && chars[1].is_ascii_digit()
-                && chars[2].is_ascii_lowercase()
-                && chars[3].is_ascii_digit()
-            {
-                return true;
-            }
+impl GameInfo {
+    /// The roster values among PGN tag pairs
+    pub fn from_tags(tags: &[(String, String)]) -> Self {
+        let tag = |name: &str| {
+            tags.iter()
+                .find(|(tag, value)| tag == name && !value.is_empty() && !value.starts_with('?'))
+                .map(|(_, value)| value.clone())
+        };
+        Self {
+            event: tag("Event"),
+            site: tag("Site"),
+            date: tag("Date"),
+            round: tag("Round"),
+            white: tag("White"),
+            black: tag("Black"),
         }
+    }
+}
 
-        // Check if it looks like coordinate notation with promotion (e.g., "e7e8=Q")
-        if input.len() == 6 && input.contains('=') {
-            return true;
-        }
+/// Name the engine is given in exported games when its side has no name
+const ENGINE_NAME: &str = "OxM8";
 
-        // Check if it looks like space-separated coordinates
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        if parts.len() == 2 {
-            if parts[0].len() == 2 && parts[1].len() == 2 {
-                let from_chars: Vec<char> = parts[0].chars().collect();
-                let to_chars: Vec<char> = parts[1].chars().collect();
-                if from_chars[0].is_ascii_lowercase()
-                    && from_chars[1].is_ascii_digit()
-                    && to_chars[0].is_ascii_lowercase()
-                    && to_chars[1].is_ascii_digit()
-                {
-                    return true;
-                }
-            }
+/// A game without any terminal I/O: it takes moves, keeps the history and knows when
+/// the game is over. [`ChessGame`] puts a command line around it
+pub struct Game {
+    board: Board,
+    initial_board: Board,
+    current_player: Color,
+    /// The moves from the initial position to the current one
+    history: Vec<MoveRecord>,
+    /// Every move tried, the current line among them
+    tree: VariationTree,
+    /// The last move of `history` in `tree`
+    current: Option<NodeId>,
+    /// Set when the game ends off the board (resignation or timeout)
+    forced_result: Option<GameResult>,
+    rules: Box<dyn Rules>,
+    engine: Option<EngineOpponent>,
+    observers: Vec<Box<dyn GameObserver>>,
+    info: GameInfo,
+}
+
+/// The engine playing one side of the game
+struct EngineOpponent {
+    color: Color,
+    limits: SearchLimits,
+    search: Search,
+    book: Option<OpeningBook>,
+    options: EngineOptions,
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::from_fen(START_FEN).expect("the start position is valid FEN")
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let board = Board::try_from_fen(fen)?;
+        Ok(Self {
+            board,
+            initial_board: board,
+            current_player: if board.to_move {
+                Color::White
+            } else {
+                Color::Black
+            },
+            history: Vec::new(),
+            tree: VariationTree::new(),
+            current: None,
+            forced_result: None,
+            rules: Box::new(Standard),
+            engine: None,
+            observers: Vec::new(),
+            info: GameInfo::default(),
+        })
+    }
+
+    /// Play under a different rule set instead of standard chess
+    pub fn with_rules(mut self, rules: impl Rules + 'static) -> Self {
+        self.rules = Box::new(rules);
+        self
+    }
+
+    pub fn rules(&self) -> &dyn Rules {
+        self.rules.as_ref()
+    }
+
+    /// Name the players and the event for exported games
+    pub fn with_info(mut self, info: GameInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    pub fn info(&self) -> &GameInfo {
+        &self.info
+    }
+
+    pub fn info_mut(&mut self) -> &mut GameInfo {
+        &mut self.info
+    }
+
+    /// Report the game's events to `observer`, after any observers added before it
+    pub fn with_observer(mut self, observer: impl GameObserver + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Let the engine play `color`, thinking within `limits` before each move
+    pub fn with_engine(mut self, color: Color, limits: SearchLimits) -> Self {
+        self.engine = Some(EngineOpponent {
+            color,
+            limits,
+            search: Search::default(),
+            book: None,
+            options: EngineOptions::default(),
+        });
+        self
+    }
+
+    /// Give the engine an opening book to play from before it starts searching.
+    /// Does nothing unless [`Game::with_engine`] was called first
+    pub fn with_book(mut self, book: OpeningBook) -> Self {
+        if let Some(engine) = self.engine.as_mut() {
+            engine.book = Some(book);
         }
+        self
+    }
 
-        false
-    }
-
-    fn parse_move_input(&self, input: &str) -> Option<Moves> {
-        let input = input.trim();
-
-        // Handle special notations
-        match input.to_lowercase().as_str() {
-            "o-o" | "0-0" => {
-                // Kingside castling
-                let king_square = match self.current_player {
-                    Color::White => 4,  // e1
-                    Color::Black => 60, // e8
-                };
-                let target_square = match self.current_player {
-                    Color::White => 6,  // g1
-                    Color::Black => 62, // g8
-                };
-                return Some(Moves::new(king_square, target_square, MoveType::Castle));
-            }
-            "o-o-o" | "0-0-0" => {
-                // Queenside castling
-                let king_square = match self.current_player {
-                    Color::White => 4,  // e1
-                    Color::Black => 60, // e8
-                };
-                let target_square = match self.current_player {
-                    Color::White => 2,  // c1
-                    Color::Black => 58, // c8
-                };
-                return Some(Moves::new(king_square, target_square, MoveType::Castle));
-            }
-            _ => {}
+    /// Configure the engine: its table size and threads, the move overhead taken off
+    /// its thinking time, and whether it plays from its book. Does nothing unless
+    /// [`Game::with_engine`] was called first
+    pub fn with_engine_options(mut self, options: EngineOptions) -> Self {
+        if let Some(engine) = self.engine.as_mut() {
+            engine.search.set_options(options.search_options());
+            engine.options = options;
         }
+        self
+    }
 
-        // Try to parse coordinate algebraic notation (e.g., "e2e4", "e7e8=Q")
-        if let Some((from, to, promotion)) = parse_algebraic(input) {
-            // Find the appropriate move type
-            let legal_moves = Moves::generate_legal_moves(&self.board, self.current_player);
-
-            for mv in legal_moves {
-                if mv.from == from && mv.to == to {
-                    match (promotion, &mv.move_type) {
-                        (Some(piece), MoveType::Promotion { piece: mv_piece })
-                        | (Some(piece), MoveType::PromotionCapture { piece: mv_piece }) => {
-                            if piece == *mv_piece {
-                                return Some(mv);
-                            }
-                        }
-                        (None, _) if !mv.is_promotion() => {
-                            return Some(mv);
-                        }
-                        _ => continue,
-                    }
-                }
-            }
+    /// The engine's opening book, if it has one
+    pub fn book(&self) -> Option<&OpeningBook> {
+        self.engine.as_ref()?.book.as_ref()
+    }
+
+    /// The side the engine plays, if any
+    pub fn engine_color(&self) -> Option<Color> {
+        self.engine.as_ref().map(|engine| engine.color)
+    }
+
+    /// Play the engine's book move, or search the current position when out of book.
+    /// Returns `None` if no engine was set up or there is no legal move. The search
+    /// knows only standard chess, so under other rules a move they forbid is replaced
+    /// by the first legal one
+    pub fn play_engine_move(&mut self) -> Option<Move> {
+        let hashes = self.position_hashes();
+        let engine = self.engine.as_mut()?;
+        engine.search.set_game_history(hashes);
+        let book_move = engine
+            .book
+            .as_ref()
+            .filter(|_| engine.options.own_book)
+            .and_then(|book| book.best_move(&self.board));
+        let limits = engine.options.limits(engine.limits);
+        let best = book_move.or_else(|| engine.search.best_move(&self.board, limits).0);
+        let mv = best
+            .filter(|mv| self.rules.is_legal(&self.board, self.current_player, mv))
+            .or_else(|| self.get_legal_moves().first().copied())?;
+        self.make_move(mv);
+        Some(mv)
+    }
+
+    /// Squares the piece on `square` can legally move to, as a bitboard
+    pub fn targets_of(&self, square: u8) -> u64 {
+        self.get_legal_moves()
+            .iter()
+            .filter(|mv| mv.from == square)
+            .fold(0, |targets, mv| targets | (1 << mv.to))
+    }
+
+    /// The legal move of the current position that `text` stands for, written in SAN,
+    /// long algebraic or UCI notation
+    pub fn parse_move(&self, text: &str) -> Result<Move, MoveParseError> {
+        let legal_moves = self.rules.legal_moves(&self.board, self.current_player);
+        MoveParser::with_moves(&self.board, legal_moves).parse(text)
+    }
+
+    /// Play `mv`, one of the current position's legal moves as given by
+    /// [`Game::get_legal_moves`] or [`Game::parse_move`], and tell where the game stands
+    pub fn play(&mut self, mv: Move) -> Result<GameStatus, PlayError> {
+        if let Some(result) = self.result() {
+            return Err(PlayError::GameOver(result));
         }
+        if !self.make_move(mv) {
+            return Err(PlayError::IllegalMove(mv));
+        }
+        Ok(self.status())
+    }
 
-        // Try simple position parsing (e.g., "e2 e4")
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        if parts.len() == 2 {
-            if let (Some(from), Some(to)) = (pos_to_u8(parts[0]), pos_to_u8(parts[1])) {
-                let legal_moves = Moves::generate_legal_moves(&self.board, self.current_player);
-                for mv in legal_moves {
-                    if mv.from == from && mv.to == to && !mv.is_promotion() {
-                        return Some(mv);
-                    }
-                }
-            }
+    /// Whose turn it is, or how the game ended
+    pub fn status(&self) -> GameStatus {
+        match self.result() {
+            Some(result) => GameStatus::Over(result),
+            None => GameStatus::InProgress {
+                to_move: self.current_player,
+                in_check: self.rules.is_in_check(&self.board, self.current_player),
+            },
         }
+    }
 
-        None
+    /// Record how long the last move took, for clocks kept outside the game
+    pub fn set_move_time(&mut self, time: Duration) {
+        if let (Some(record), Some(id)) = (self.history.last_mut(), self.current) {
+            record.time = Some(time);
+            self.tree.node_mut(id).record.time = Some(time);
+        }
     }
 
-    fn make_move(&mut self, mv: Moves) -> bool {
+    fn make_move(&mut self, mv: Move) -> bool {
         // Verify the move is legal
-        let legal_moves = Moves::generate_legal_moves(&self.board, self.current_player);
-        if !legal_moves.contains(&mv) {
+        if !self.rules.is_legal(&self.board, self.current_player, &mv) {
             return false;
         }
 
         // Record the move
-        self.move_history.push(mv.to_algebraic());
+        let record = MoveRecord::new(&self.board, mv);
+        self.current = Some(self.tree.add(self.current, record.clone()));
+        self.history.push(record);
 
         // Make the move
-        self.board.make_move(&mv);
+        self.rules.make_move(&mut self.board, &mv);
 
         // Switch players
-        self.current_player = match self.current_player {
-            Color::White => Color::Black,
-            Color::Black => Color::White,
-        };
+        self.current_player = self.current_player.opposite();
 
+        self.notify_move();
         true
     }
 
-    pub fn is_game_over(&self) -> bool {
-        Moves::is_checkmate(&self.board, self.current_player)
-            || Moves::is_stalemate(&self.board, self.current_player)
-    }
-
-    fn show_help(&self) {
-        println!("\n=== CHESS GAME HELP ===");
-        println!("Move formats:");
-        println!("  • Coordinate notation: e2e4, g1f3, a7a8=Q");
-        println!("  • Space separated: e2 e4");
-        println!("  • Castling: O-O (kingside), O-O-O (queenside)");
-        println!("\nCommands:");
-        println!("  • 'moves' - Show all legal moves");
-        println!("  • 'help' - Show this help");
-        println!("  • 'quit' - Exit game");
-        println!("  • 'history' - Show move history");
-        println!("  • 'fen' - Show current position in FEN notation");
-        println!("  • 'status' - Show detailed game status");
-        println!();
-    }
-
-    fn show_history(&self) {
-        if self.move_history.is_empty() {
-            println!("No moves played yet.");
+    /// Tell the observers about the move just played and what it led to
+    fn notify_move(&mut self) {
+        let Some(record) = self.history.last().filter(|_| !self.observers.is_empty()) else {
             return;
+        };
+        let in_check = self.rules.is_in_check(&self.board, self.current_player);
+        let result = self.result();
+        for observer in &mut self.observers {
+            observer.on_move_played(record);
+            if let Some(piece) = record.captured {
+                observer.on_capture(record, piece);
+            }
+            if in_check {
+                observer.on_check(self.current_player);
+            }
+            if let Some(result) = result {
+                observer.on_game_end(result);
+            }
         }
+    }
 
-        println!("\nMove History:");
-        for (i, mv) in self.move_history.iter().enumerate() {
-            if i % 2 == 0 {
-                print!("{}. {}", i / 2 + 1, mv);
+    /// End the game off the board with `result`
+    fn force_result(&mut self, result: GameResult) {
+        self.forced_result = Some(result);
+        for observer in &mut self.observers {
+            observer.on_game_end(result);
+        }
+    }
+
+    /// Replace the game with the first game of a PGN document, replaying its moves
+    /// under the current rules and taking the players and event from its tags. Engine
+    /// and display settings are kept
+    pub fn load_pgn(&mut self, pgn: &str) -> Result<(), String> {
+        let game = crate::pgn::parse_games(pgn)?
+            .into_iter()
+            .next()
+            .ok_or("the PGN contains no game")?;
+        // Check every move first so a bad file leaves the current game alone
+        let mut board = game.initial_board;
+        for (i, mv) in game.moves.iter().enumerate() {
+            let color = if board.to_move {
+                Color::White
             } else {
-                println!(" {}", mv);
+                Color::Black
+            };
+            if !self.rules.is_legal(&board, color, mv) {
+                return Err(format!("move {} is illegal", i + 1));
             }
+            self.rules.make_move(&mut board, mv);
+        }
+
+        self.reset(game.initial_board);
+        self.info = GameInfo::from_tags(&game.tags);
+        for &mv in &game.moves {
+            self.make_move(mv);
         }
-        if self.move_history.len() % 2 == 1 {
-            println!();
+        // The side-lines were read under standard rules, so other variants keep only
+        // the mainline
+        if self.rules.name() == Standard.name() {
+            self.current = game.tree.mainline(None).last().copied();
+            self.tree = game.tree;
         }
-        println!();
+        Ok(())
     }
 
-    fn show_detailed_status(&self) {
-        println!("\n=== GAME STATUS ===");
+    /// Start a new game from `fen`, which must parse and describe a position that can
+    /// arise in a game. Engine and display settings are kept
+    pub fn set_position(&mut self, fen: &str) -> Result<(), String> {
+        let board = Board::try_from_fen(fen).map_err(|e| e.to_string())?;
+        board.validate().map_err(|e| e.to_string())?;
+        self.reset(board);
+        Ok(())
+    }
 
-        let current_color_name = match self.current_player {
-            Color::White => "White",
-            Color::Black => "Black",
-        };
+    /// Write the game to `path` as PGN
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, self.to_pgn())
+    }
 
-        println!("Current player: {}", current_color_name);
-        println!("Moves played: {}", self.move_history.len());
-        println!("Castling rights: {:04b} (KQkq)", self.board.castling_rights);
+    /// Replace the game with the one saved in the PGN file at `path`
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let pgn = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        self.load_pgn(&pgn)
+    }
 
-        if let Some(ep) = self.board.en_passant {
-            println!("En passant square: {}", crate::util::u8_to_pos(ep));
+    /// Start over from `board` with an empty history
+    fn reset(&mut self, board: Board) {
+        self.board = board;
+        self.initial_board = board;
+        self.current_player = if board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        self.history.clear();
+        self.tree = VariationTree::new();
+        self.current = None;
+        if let Some(engine) = &self.engine {
+            engine.search.clear_hash();
         }
+        self.forced_result = None;
+    }
 
-        println!("Halfmove clock: {}", self.board.halfmove_clock);
-        println!("Fullmove number: {}", self.board.fullmove_number);
+    /// Take back the last `plies` moves, restoring the position before the earliest of
+    /// them including variant state such as check counters. The moves stay in the
+    /// variation tree, so [`Game::goto`] can replay them. Returns `false` without
+    /// changing anything if fewer moves have been played
+    pub fn undo_moves(&mut self, plies: usize) -> bool {
+        let Some(kept) = self.history.len().checked_sub(plies) else {
+            return false;
+        };
+        let path = self.tree.path(self.current);
+        self.goto_node(kept.checked_sub(1).map(|last| path[last]));
+        true
+    }
+
+    /// Every move tried so far, the current line and its side-lines
+    pub fn tree(&self) -> &VariationTree {
+        &self.tree
+    }
+
+    /// The last move played to reach the current position, `None` at the start
+    pub fn current_node(&self) -> Option<NodeId> {
+        self.current
+    }
 
-        // Check game state
-        if Moves::is_in_check(&self.board, Color::White) {
-            println!("White is in check!");
+    /// The moves tried from the current position, the mainline first
+    pub fn variations(&self) -> &[NodeId] {
+        self.tree.children(self.current)
+    }
+
+    /// The current line: the moves played to reach the current position, then the
+    /// mainline on from it
+    pub fn line(&self) -> Vec<NodeId> {
+        let mut line = self.tree.path(self.current);
+        line.extend(self.tree.mainline(self.current));
+        line
+    }
+
+    /// Go to the position after the first `ply` moves of [`Game::line`], back or
+    /// forward. Returns `false` without moving if the line is shorter
+    pub fn goto(&mut self, ply: usize) -> bool {
+        if ply > 0 && ply > self.line().len() {
+            return false;
         }
-        if Moves::is_in_check(&self.board, Color::Black) {
-            println!("Black is in check!");
+        let node = ply.checked_sub(1).map(|last| self.line()[last]);
+        self.goto_node(node);
+        true
+    }
+
+    /// Go to the position after `node`, or to the initial position for `None`
+    pub fn goto_node(&mut self, node: Option<NodeId>) {
+        self.history = self
+            .tree
+            .path(node)
+            .into_iter()
+            .map(|id| self.tree.node(id).record.clone())
+            .collect();
+        self.board = match self.history.last() {
+            Some(record) => {
+                let mut board = record.board_before;
+                self.rules.make_move(&mut board, &record.mv);
+                board
+            }
+            None => self.initial_board,
+        };
+        self.current_player = if self.board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        self.current = node;
+    }
+
+    /// Make the line through `node` the mainline, see [`VariationTree::promote`]. The
+    /// current position does not change
+    pub fn promote_variation(&mut self, node: NodeId) {
+        self.tree.promote(node);
+    }
+
+    /// Take back the player's last move: against the engine, the engine's reply is
+    /// taken back with it so the player is to move again
+    pub fn takeback(&mut self) -> bool {
+        let plies = if self.engine_color() == Some(self.current_player.opposite()) {
+            2
+        } else {
+            1
+        };
+        self.undo_moves(plies)
+    }
+
+    /// The result of the game, or `None` while it is still in progress
+    /// Checkmate takes precedence over the draw rules
+    pub fn result(&self) -> Option<GameResult> {
+        let earlier = self.history.iter().map(|record| record.board_before.hash());
+        self.forced_result
+            .or_else(|| self.outcome(&self.board, earlier))
+    }
+
+    /// How the game stands in `board`, reached through the positions hashed in
+    /// `earlier`, ignoring results forced off the board
+    fn outcome(&self, board: &Board, earlier: impl Iterator<Item = u64>) -> Option<GameResult> {
+        let color = if board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        if let Some(result) = self.rules.outcome(board, color) {
+            return Some(result);
         }
 
-        let legal_moves = Moves::generate_legal_moves(&self.board, self.current_player);
-        println!(
-            "Legal moves for {}: {}",
-            current_color_name,
-            legal_moves.len()
-        );
-        println!();
+        let current = board.hash();
+        let reason = if 1 + earlier.filter(|&hash| hash == current).count() >= 3 {
+            TerminationReason::Repetition
+        } else if board.halfmove_clock >= 100 {
+            TerminationReason::FiftyMove
+        } else if self.rules.is_insufficient_material(board) {
+            TerminationReason::InsufficientMaterial
+        } else {
+            return None;
+        };
+        Some(GameResult::Draw(reason))
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.result().is_some()
+    }
+
+    /// How many times the current position has occurred
+    pub fn repetition_count(&self) -> usize {
+        let current = self.board.hash();
+        1 + self
+            .history
+            .iter()
+            .filter(|record| record.board_before.hash() == current)
+            .count()
     }
 
-    pub fn run(&mut self) {
-        println!("🏰 Welcome to OxM8 Chess! 🏰");
-        println!("Type 'help' for commands or enter moves like 'e2e4' or 'g1f3'");
+    /// End the game with `color` resigning
+    pub fn resign(&mut self, color: Color) {
+        self.force_result(GameResult::loss_for(color, TerminationReason::Resignation));
+    }
 
-        loop {
-            self.display_board();
-            self.display_game_status();
+    /// End the game with `color` losing on time
+    pub fn lose_on_time(&mut self, color: Color) {
+        self.force_result(GameResult::loss_for(color, TerminationReason::Timeout));
+    }
 
-            if self.is_game_over() {
-                println!("Game Over! Type 'quit' to exit.");
-                break;
+    /// PGN result token at the end of `line` ("1-0", "0-1", "1/2-1/2" or "*"), a line
+    /// of moves from the initial position
+    fn result_token(&self, line: &[NodeId]) -> &'static str {
+        let records: Vec<&MoveRecord> = line.iter().map(|&id| &self.tree.node(id).record).collect();
+        let board = match records.last() {
+            Some(record) => {
+                let mut board = record.board_before;
+                self.rules.make_move(&mut board, &record.mv);
+                board
             }
+            None => self.initial_board,
+        };
+        let earlier = records.iter().map(|record| record.board_before.hash());
+        self.forced_result
+            .or_else(|| self.outcome(&board, earlier))
+            .map_or("*", |result| result.pgn_token())
+    }
 
-            print!("Enter move: ");
-            io::stdout().flush().unwrap();
+    /// The name exported for `color`: the one in [`Game::info`], or the engine's when it
+    /// plays that side
+    fn player_name(&self, color: Color) -> &str {
+        let name = match color {
+            Color::White => &self.info.white,
+            Color::Black => &self.info.black,
+        };
+        match name {
+            Some(name) => name,
+            None if self.engine_color() == Some(color) => ENGINE_NAME,
+            None => "?",
+        }
+    }
 
-            let mut input = String::new();
-            if io::stdin().read_line(&mut input).is_err() {
-                continue;
+    /// Export the game as a PGN document with the Seven Tag Roster and SAN movetext.
+    /// The whole of [`Game::line`] is written, with the moves after the current
+    /// position too
+    pub fn to_pgn(&self) -> String {
+        let line = self.line();
+        let result = self.result_token(&line);
+        let mut pgn = String::new();
+
+        let info = &self.info;
+        for (tag, value) in [
+            ("Event", info.event.as_deref().unwrap_or("?")),
+            ("Site", info.site.as_deref().unwrap_or("?")),
+            ("Date", info.date.as_deref().unwrap_or("????.??.??")),
+            ("Round", info.round.as_deref().unwrap_or("?")),
+            ("White", self.player_name(Color::White)),
+            ("Black", self.player_name(Color::Black)),
+            ("Result", result),
+        ] {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", tag, value));
+        }
+        let fen = crate::fen::to_fen(&self.initial_board);
+        if fen != START_FEN {
+            pgn.push_str("[SetUp \"1\"]\n");
+            pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+        }
+        pgn.push('\n');
+
+        // Movetext tokens, numbered from the initial position. The current line is the
+        // mainline, with the other moves tried along it as side-lines
+        let mut tokens = Vec::new();
+        self.push_movetext(
+            &line,
+            self.initial_board.fullmove_number,
+            self.initial_board.to_move,
+            true,
+            &mut tokens,
+        );
+        tokens.push(result.to_string());
+
+        // Wrap movetext lines at 80 characters
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
             }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
 
-            let input = input.trim().to_lowercase();
+        pgn
+    }
 
-            match input.as_str() {
-                "quit" | "exit" | "q" => {
-                    println!("Thanks for playing! 👋");
-                    break;
-                }
-                "help" | "h" => {
-                    self.show_help();
-                    continue;
-                }
-                "moves" | "m" => {
-                    self.show_legal_moves();
-                    continue;
-                }
-                "history" => {
-                    self.show_history();
-                    continue;
-                }
-                "fen" => {
-                    println!("Current position: {}", crate::fen::to_fen(&self.board));
-                    continue;
-                }
-                "status" => {
-                    self.show_detailed_status();
-                    continue;
-                }
-                "" => continue,
-                _ => {}
+    /// Movetext tokens for `line`, whose first move is played at move `number` by
+    /// White if `white`. Side-lines to the first move are included only with
+    /// `first_siblings`, a side-line's own siblings being written by its parent line
+    fn push_movetext(
+        &self,
+        line: &[NodeId],
+        mut number: u16,
+        mut white: bool,
+        first_siblings: bool,
+        tokens: &mut Vec<String>,
+    ) {
+        let mut numbered = false;
+        for (i, &id) in line.iter().enumerate() {
+            let node = self.tree.node(id);
+            if white {
+                tokens.push(format!("{}.", number));
+            } else if !numbered {
+                tokens.push(format!("{}...", number));
+            }
+            numbered = true;
+            if i == 0
+                && let Some(comment) = &node.starting_comment
+            {
+                // Before the move number, which was just pushed
+                tokens.insert(tokens.len() - 1, format!("{{{}}}", comment));
+            }
+            tokens.push(node.record.san.clone());
+            tokens.extend(node.nags.iter().map(|nag| format!("${}", nag)));
+            if let Some(comment) = &node.comment {
+                tokens.push(format!("{{{}}}", comment));
+                numbered = false;
             }
 
-            match self.parse_move_input(&input) {
-                Some(mv) => {
-                    if self.make_move(mv) {
-                        println!("✅ Move played: {}", self.move_history.last().unwrap());
-                    } else {
-                        println!("❌ Illegal move! Try again.");
+            if i > 0 || first_siblings {
+                for &sibling in self.tree.children(node.parent) {
+                    if sibling == id {
+                        continue;
                     }
-                }
-                None => {
-                    // Check if the input looks like coordinate notation but is illegal
-                    if self.looks_like_move_input(&input) {
-                        println!(
-                            "❌ Illegal move! '{}' is not a legal move in this position.",
-                            input
-                        );
-                    } else {
-                        println!("❌ Invalid move format! Type 'help' for examples.");
+                    let mut variation = vec![sibling];
+                    variation.extend(self.tree.mainline(Some(sibling)));
+                    let start = tokens.len();
+                    self.push_movetext(&variation, number, white, false, tokens);
+                    tokens[start].insert(0, '(');
+                    if let Some(last) = tokens.last_mut() {
+                        last.push(')');
                     }
+                    numbered = false;
                 }
             }
+
+            if !white {
+                number += 1;
+            }
+            white = !white;
         }
     }
 
     // Public API methods for external use
-    pub fn get_legal_moves(&self) -> Vec<Moves> {
-        Moves::generate_legal_moves(&self.board, self.current_player)
+    pub fn get_legal_moves(&self) -> MoveList {
+        self.rules.legal_moves(&self.board, self.current_player)
     }
 
     pub fn get_current_player(&self) -> Color {
@@ -439,11 +840,43 @@ impl ChessGame {
         &self.board
     }
 
+    /// The position the game started from
+    pub fn initial_board(&self) -> &Board {
+        &self.initial_board
+    }
+
+    /// Moves played so far, in order
+    pub fn moves(&self) -> Vec<Move> {
+        self.history.iter().map(|record| record.mv).collect()
+    }
+
+    /// Every move played so far with its SAN, prior position and capture
+    pub fn history(&self) -> &[MoveRecord] {
+        &self.history
+    }
+
+    /// Hashes of the positions the moves were played in, oldest first
+    pub fn position_hashes(&self) -> Vec<u64> {
+        self.history
+            .iter()
+            .map(|record| record.board_before.hash())
+            .collect()
+    }
+
+    /// The position after the first `ply` moves, `None` past the last move
+    pub fn position_at(&self, ply: usize) -> Option<Board> {
+        match self.history.get(ply) {
+            Some(record) => Some(record.board_before),
+            None if ply == self.history.len() => Some(self.board),
+            None => None,
+        }
+    }
+
     pub fn try_move(&mut self, from: &str, to: &str) -> Result<(), String> {
         let from_square = pos_to_u8(from).ok_or("Invalid from square")?;
         let to_square = pos_to_u8(to).ok_or("Invalid to square")?;
 
-        let legal_moves = Moves::generate_legal_moves(&self.board, self.current_player);
+        let legal_moves = self.rules.legal_moves(&self.board, self.current_player);
         for mv in legal_moves {
             if mv.from == from_square && mv.to == to_square && !mv.is_promotion() {
                 self.make_move(mv);
@@ -455,20 +888,16 @@ impl ChessGame {
     }
 
     pub fn try_move_algebraic(&mut self, move_str: &str) -> Result<(), String> {
-        match self.parse_move_input(move_str) {
-            Some(mv) => {
-                if self.make_move(mv) {
-                    Ok(())
-                } else {
-                    Err("Move is not legal".to_string())
-                }
-            }
-            None => Err("Could not parse move".to_string()),
+        let mv = self.parse_move(move_str)?;
+        if self.make_move(mv) {
+            Ok(())
+        } else {
+            Err("Move is not legal".to_string())
         }
     }
 }
 
-impl Default for ChessGame {
+impl Default for Game {
     fn default() -> Self {
         Self::new()
     }
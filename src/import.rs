@@ -0,0 +1,221 @@
+use crate::game::Game;
+use std::fmt;
+use std::process::Command;
+
+/// Why a game could not be imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// Neither a lichess nor a chess.com game URL or ID
+    UnknownSource(String),
+    /// The download failed
+    Fetch(String),
+    /// chess.com knew the game but it was not in its player's archive
+    NotFound,
+    /// The downloaded game could not be read or replayed
+    Pgn(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::UnknownSource(text) => {
+                write!(f, "not a lichess or chess.com game: '{}'", text)
+            }
+            ImportError::Fetch(error) => write!(f, "download failed: {}", error),
+            ImportError::NotFound => f.write_str("the game is not in its player's archive"),
+            ImportError::Pgn(error) => write!(f, "bad game: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<ImportError> for String {
+    fn from(error: ImportError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Downloads a page as text. [`Curl`] does it for the CLI, tests and embedders can
+/// supply their own
+pub trait HttpClient {
+    fn get(&mut self, url: &str) -> Result<String, String>;
+}
+
+/// Fetches through the `curl` command, which has to be installed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Curl;
+
+impl HttpClient for Curl {
+    fn get(&mut self, url: &str) -> Result<String, String> {
+        let output = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location", url])
+            .output()
+            .map_err(|e| format!("cannot run curl: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        String::from_utf8(output.stdout).map_err(|_| "the response is not UTF-8".to_string())
+    }
+}
+
+/// A game on one of the sites the importer knows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameSource {
+    /// A lichess game by its 8-character ID
+    Lichess(String),
+    /// A chess.com game by its number, `daily` for correspondence games
+    ChessCom { id: String, daily: bool },
+}
+
+impl GameSource {
+    /// Read a game URL such as `https://lichess.org/q7ZvsdUF/black`,
+    /// `https://www.chess.com/game/live/123456789` or `chess.com/game/daily/123`, or a
+    /// bare ID: a number for a chess.com live game, 8 letters and digits for lichess
+    pub fn parse(text: &str) -> Result<Self, ImportError> {
+        let unknown = || ImportError::UnknownSource(text.to_string());
+        let trimmed = text.trim();
+        let path = trimmed
+            .strip_prefix("https://")
+            .or_else(|| trimmed.strip_prefix("http://"))
+            .unwrap_or(trimmed);
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let path = path.strip_prefix("www.").unwrap_or(path);
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        match segments.as_slice() {
+            ["lichess.org", id, ..] => lichess_id(id).ok_or_else(unknown),
+            ["chess.com", "game", kind, id, ..]
+            | ["chess.com", "analysis", "game", kind, id, ..]
+            | ["chess.com", kind, "game", id, ..] => {
+                let daily = match *kind {
+                    "live" => false,
+                    "daily" => true,
+                    _ => return Err(unknown()),
+                };
+                chess_com_id(id)
+                    .map(|id| GameSource::ChessCom { id, daily })
+                    .ok_or_else(unknown)
+            }
+            // All digits is a chess.com game, lichess IDs have letters too
+            [id] => chess_com_id(id)
+                .map(|id| GameSource::ChessCom { id, daily: false })
+                .or_else(|| lichess_id(id).filter(|_| id.len() == 8))
+                .ok_or_else(unknown),
+            _ => Err(unknown()),
+        }
+    }
+
+    /// Download the game as PGN
+    pub fn fetch_pgn(&self, client: &mut dyn HttpClient) -> Result<String, ImportError> {
+        match self {
+            GameSource::Lichess(id) => client
+                .get(&format!("https://lichess.org/game/export/{}", id))
+                .map_err(ImportError::Fetch),
+            GameSource::ChessCom { id, daily } => fetch_chess_com(client, id, *daily),
+        }
+    }
+}
+
+/// The game ID of a lichess URL segment. Links for one player's side add 4
+/// characters to the 8 of the game
+fn lichess_id(segment: &str) -> Option<GameSource> {
+    let id = segment.get(..8)?;
+    (matches!(segment.len(), 8 | 12) && segment.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then(|| GameSource::Lichess(id.to_string()))
+}
+
+fn chess_com_id(segment: &str) -> Option<String> {
+    (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+        .then(|| segment.to_string())
+}
+
+/// chess.com has no PGN export for a single game. Its game page data names White and
+/// the date, and the game is then looked up in White's monthly archive, which is
+/// filed by the end date: the month after is tried for games that ran over
+fn fetch_chess_com(
+    client: &mut dyn HttpClient,
+    id: &str,
+    daily: bool,
+) -> Result<String, ImportError> {
+    let kind = if daily { "daily" } else { "live" };
+    let page = client
+        .get(&format!(
+            "https://www.chess.com/callback/{}/game/{}",
+            kind, id
+        ))
+        .map_err(ImportError::Fetch)?;
+    let page: serde_json::Value =
+        serde_json::from_str(&page).map_err(|e| ImportError::Fetch(e.to_string()))?;
+    let headers = &page["game"]["pgnHeaders"];
+    let (Some(white), Some(date)) = (headers["White"].as_str(), headers["Date"].as_str()) else {
+        return Err(ImportError::Fetch(
+            "no players in the game data".to_string(),
+        ));
+    };
+    let mut date = date.split('.').map(|part| part.parse::<u32>());
+    let (Some(Ok(year)), Some(Ok(month))) = (date.next(), date.next()) else {
+        return Err(ImportError::Fetch("no date in the game data".to_string()));
+    };
+
+    let next = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    for (year, month) in [(year, month), next] {
+        let archive = client
+            .get(&format!(
+                "https://api.chess.com/pub/player/{}/games/{}/{:02}/pgn",
+                white.to_lowercase(),
+                year,
+                month
+            ))
+            .map_err(ImportError::Fetch)?;
+        if let Some(pgn) = split_games(&archive).find(|pgn| links_to(pgn, id)) {
+            return Ok(pgn.to_string());
+        }
+    }
+    Err(ImportError::NotFound)
+}
+
+/// The games of a PGN file, each from its first tag to the end of its movetext
+fn split_games(pgn: &str) -> impl Iterator<Item = &str> {
+    let mut starts = Vec::new();
+    let mut in_movetext = true;
+    let mut offset = 0;
+    for line in pgn.split_inclusive('\n') {
+        let is_tag = line.trim_start().starts_with('[');
+        if is_tag && in_movetext {
+            starts.push(offset);
+        }
+        if !line.trim().is_empty() {
+            in_movetext = !is_tag;
+        }
+        offset += line.len();
+    }
+    starts.push(pgn.len());
+    let games: Vec<&str> = starts
+        .windows(2)
+        .map(|bounds| pgn[bounds[0]..bounds[1]].trim())
+        .collect();
+    games.into_iter()
+}
+
+/// Whether the `Link` tag of a chess.com game ends with `id`
+fn links_to(pgn: &str, id: &str) -> bool {
+    pgn.lines().any(|line| {
+        line.strip_prefix("[Link \"")
+            .and_then(|link| link.strip_suffix("\"]"))
+            .is_some_and(|link| link.rsplit('/').next() == Some(id))
+    })
+}
+
+/// Download the game at a lichess or chess.com URL or ID and load it, with its
+/// players, comments and side-lines, into a [`Game`] at the final position
+pub fn import_game(text: &str, client: &mut dyn HttpClient) -> Result<Game, ImportError> {
+    let pgn = GameSource::parse(text)?.fetch_pgn(client)?;
+    let mut game = Game::new();
+    game.load_pgn(&pgn).map_err(ImportError::Pgn)?;
+    Ok(game)
+}
@@ -0,0 +1,183 @@
+use crate::board::Board;
+use crate::eval::{Eval, EvalBreakdown};
+use crate::fen::to_fen;
+use crate::moves::{Move, MoveType};
+use crate::piece::{Color, Piece};
+use crate::search::{Score, Search, SearchInfo, SearchLimits, SearchResult};
+use crate::util::u8_to_pos;
+use serde::{Deserialize, Serialize};
+
+fn piece_name(piece: Piece) -> String {
+    format!("{:?}", piece).to_lowercase()
+}
+
+fn color_name(color: Color) -> String {
+    color.to_string().to_lowercase()
+}
+
+fn side_to_move(board: &Board) -> Color {
+    if board.to_move {
+        Color::White
+    } else {
+        Color::Black
+    }
+}
+
+/// A move as the JSON documents describe it, with squares by name ("e4") and pieces
+/// as lowercase words
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveJson {
+    pub uci: String,
+    pub san: String,
+    pub from: String,
+    pub to: String,
+    pub piece: String,
+    pub capture: bool,
+    pub promotion: Option<String>,
+}
+
+impl MoveJson {
+    /// Describe a move legal in `board`
+    pub fn new(board: &Board, mv: &Move) -> Self {
+        let promotion = match mv.move_type {
+            MoveType::Promotion { piece } | MoveType::PromotionCapture { piece } => {
+                Some(piece_name(piece))
+            }
+            _ => None,
+        };
+        Self {
+            uci: mv.to_uci(),
+            san: mv.to_san(board),
+            from: u8_to_pos(mv.from),
+            to: u8_to_pos(mv.to),
+            piece: board
+                .get_piece_at(mv.from)
+                .map_or_else(String::new, |(piece, _)| piece_name(piece)),
+            capture: mv.is_capture(),
+            promotion,
+        }
+    }
+
+    /// Describe a line of moves played one after another from `board`
+    pub fn line(board: &Board, moves: &[Move]) -> Vec<Self> {
+        let mut board = *board;
+        moves
+            .iter()
+            .map(|mv| {
+                let json = Self::new(&board, mv);
+                board.make_move(mv);
+                json
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceJson {
+    pub square: String,
+    pub piece: String,
+    pub color: String,
+}
+
+/// A position for the WASM bindings and the CLI's `--json` mode. Field names and value
+/// formats are kept stable; the evaluation is from White's point of view
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionJson {
+    pub fen: String,
+    pub side_to_move: String,
+    /// Castling field of the FEN, "-" when neither side may castle
+    pub castling: String,
+    pub en_passant: Option<String>,
+    pub halfmove_clock: u16,
+    pub fullmove_number: u16,
+    pub in_check: bool,
+    /// Occupied squares from a1 to h8
+    pub pieces: Vec<PieceJson>,
+    pub legal_moves: Vec<MoveJson>,
+    pub eval: EvalBreakdown,
+}
+
+impl PositionJson {
+    pub fn new(board: &Board) -> Self {
+        let fen = to_fen(board);
+        let color = side_to_move(board);
+        let pieces = (0..64u8)
+            .filter_map(|square| {
+                let (piece, color) = board.get_piece_at(square)?;
+                Some(PieceJson {
+                    square: u8_to_pos(square),
+                    piece: piece_name(piece),
+                    color: color_name(color),
+                })
+            })
+            .collect();
+        let legal_moves = Move::generate_legal_moves(board, color)
+            .iter()
+            .map(|mv| MoveJson::new(board, mv))
+            .collect();
+        Self {
+            castling: fen.split(' ').nth(2).unwrap_or("-").to_string(),
+            fen,
+            side_to_move: color_name(color),
+            en_passant: board.en_passant.map(u8_to_pos),
+            halfmove_clock: board.halfmove_clock,
+            fullmove_number: board.fullmove_number,
+            in_check: Move::is_in_check(board, color),
+            pieces,
+            legal_moves,
+            eval: Eval::breakdown(board),
+        }
+    }
+}
+
+/// A search score relative to the side to move, `{"cp": 35}` or `{"mate": -2}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreJson {
+    Cp(i32),
+    /// Full moves to mate, negative when the side to move is getting mated
+    Mate(i32),
+}
+
+impl From<Score> for ScoreJson {
+    fn from(score: Score) -> Self {
+        match score {
+            Score::Centipawns(cp) => ScoreJson::Cp(cp),
+            Score::Mate(moves) => ScoreJson::Mate(moves),
+        }
+    }
+}
+
+/// The outcome of searching a position
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalysisJson {
+    pub fen: String,
+    pub depth: u8,
+    pub nodes: u64,
+    pub score: ScoreJson,
+    /// `None` when the side to move has no legal moves
+    pub best_move: Option<MoveJson>,
+    /// Principal variation, starting with the best move
+    pub pv: Vec<MoveJson>,
+}
+
+impl AnalysisJson {
+    pub fn new(board: &Board, result: &SearchResult, pv: &[Move]) -> Self {
+        Self {
+            fen: to_fen(board),
+            depth: result.depth,
+            nodes: result.nodes,
+            score: result.score().into(),
+            best_move: result.best_move.map(|mv| MoveJson::new(board, &mv)),
+            pv: MoveJson::line(board, pv),
+        }
+    }
+
+    /// Search the position within `limits` and describe the result
+    pub fn search(board: &Board, limits: SearchLimits) -> Self {
+        let mut pv = Vec::new();
+        let mut observer = |info: &SearchInfo| pv = info.pv.clone();
+        let result = Search::default().go_with_observer(board, limits, &mut observer);
+        Self::new(board, &result, &pv)
+    }
+}
@@ -1,15 +1,66 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod analysis;
+pub mod attacks;
+#[cfg(feature = "std")]
+pub mod bench;
+pub mod bitboard;
 pub mod board;
+#[cfg(feature = "std")]
+pub mod book;
+#[cfg(feature = "std")]
+pub mod cli;
+#[cfg(feature = "std")]
+pub mod database;
+#[cfg(feature = "std")]
+pub mod engine_match;
 pub mod eval;
+#[cfg(feature = "std")]
+pub mod explorer;
 pub mod fen;
+#[cfg(feature = "std")]
 pub mod game;
+#[cfg(feature = "import")]
+pub mod import;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod movelist;
 pub mod moves;
+pub mod notation;
+pub mod options;
+pub mod params;
+#[cfg(feature = "std")]
+pub mod pgn;
 pub mod piece;
+#[cfg(feature = "std")]
+pub mod puzzle;
+#[cfg(feature = "std")]
+pub mod render;
+pub mod search;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod square;
+pub mod tablebase;
+pub mod tt;
 pub mod util;
+#[cfg(feature = "std")]
+pub mod variant;
+#[cfg(feature = "std")]
+pub mod variation;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod zobrist;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests;
 
 pub use board::Board;
-pub use game::ChessGame;
+#[cfg(feature = "std")]
+pub use game::{ChessGame, Game};
+#[allow(deprecated)]
 pub use moves::Moves;
+pub use moves::{Move, MoveBuilder};
 pub use piece::{Color, Piece};
+pub use square::{File, Rank, Square};
@@ -1,15 +1,26 @@
 use oxm8::board::Board;
-use oxm8::eval::Eval;
-use oxm8::fen::{START_FEN, parse_fen, to_fen};
+use oxm8::eval::{Eval, INFINITY, mate_in};
+use oxm8::fen::{FenError, START_FEN, to_fen};
 use oxm8::game::ChessGame;
-use oxm8::moves::{MoveType, Moves};
+use oxm8::moves::{Move, MoveType};
 use oxm8::piece::Color;
+use oxm8::search::{Score, Search, SearchContext, SearchInfo, SearchLimits};
+use std::time::Duration;
+
+/// Print a FEN error with a marker under the offending part of the input
+fn report_fen_error(fen: &str, error: &FenError) {
+    println!("Error parsing FEN: {}", error);
+    if let Some(offset) = error.offset() {
+        println!("  {}", fen);
+        println!("  {}^", " ".repeat(offset));
+    }
+}
 
 fn test_fen(fen: &str) {
     println!("Original: {}", fen);
-    match parse_fen(fen) {
+    match Board::try_from_fen(fen) {
         Ok(board) => println!("Generated: {}", to_fen(&board)),
-        Err(e) => println!("Error parsing FEN: {}", e),
+        Err(e) => report_fen_error(fen, &e),
     }
 }
 
@@ -19,7 +30,7 @@ fn test_specific_position() {
 
     println!("Testing FEN: {}", fen);
 
-    match parse_fen(fen) {
+    match Board::try_from_fen(fen) {
         Ok(board) => {
             println!("\n--- Position ---");
             board.display();
@@ -28,29 +39,34 @@ fn test_specific_position() {
                 if board.to_move { "White" } else { "Black" }
             );
 
-            let eval_score = Eval::alpha_beta(&board, 1, -10000, 10000, board.to_move);
-            // Convert to White's perspective for display
-            let eval_from_white_pov = if board.to_move {
-                eval_score
-            } else {
-                -eval_score
-            };
+            let mut ctx = SearchContext::default();
+            // alpha_beta already scores from White's point of view
+            let eval_from_white_pov =
+                Eval::alpha_beta(&board, 1, -INFINITY, INFINITY, board.to_move, &mut ctx);
             let material_balance = Eval::material_balance(&board);
             let mobility_balance = Eval::mobility_balance(&board);
-            let static_eval = Eval::evaluate(&board);
+            let static_eval = Eval::evaluate_absolute(&board);
             let pawn_structure = Eval::pawn_structure_balance(&board);
+            let imbalance = Eval::imbalance_balance(&board);
 
             println!("\n--- Evaluation Breakdown ---");
             println!("Material balance: {} centipawns", material_balance);
             println!("Mobility balance: {} centipawns", mobility_balance);
             println!("Pawn structure: {} centipawns", pawn_structure);
+            println!("Material imbalance: {} centipawns", imbalance);
             println!("Static evaluation: {} centipawns", static_eval);
             println!(
                 "\nTotal evaluation (depth 1): {} centipawns",
                 eval_from_white_pov
             );
 
-            if eval_from_white_pov > 0 {
+            if let Some(moves) = mate_in(eval_from_white_pov) {
+                if moves > 0 {
+                    println!("White mates in {}", moves);
+                } else {
+                    println!("Black mates in {}", -moves);
+                }
+            } else if eval_from_white_pov > 0 {
                 println!(
                     "Position favors White by {:.2} pawns",
                     eval_from_white_pov as f32 / 100.0
@@ -64,9 +80,7 @@ fn test_specific_position() {
                 println!("Position is roughly equal");
             }
         }
-        Err(e) => {
-            println!("Error parsing FEN: {}", e);
-        }
+        Err(e) => report_fen_error(fen, &e),
     }
 }
 
@@ -84,8 +98,8 @@ fn demonstrate_engine_capabilities() {
     println!("Starting position:");
     board.display();
 
-    let all_moves = Moves::generate_all_moves(&board, Color::White);
-    let legal_moves = Moves::generate_legal_moves(&board, Color::White);
+    let all_moves = Move::generate_all_moves(&board, Color::White);
+    let legal_moves = Move::generate_legal_moves(&board, Color::White);
 
     println!("Total pseudo-legal moves: {}", all_moves.len());
     println!("Legal moves: {}", legal_moves.len());
@@ -99,7 +113,7 @@ fn demonstrate_engine_capabilities() {
         };
         println!(
             "  {} ({}): {:?}",
-            mv.to_algebraic(),
+            mv.to_san(&board),
             piece_info,
             mv.move_type
         );
@@ -114,8 +128,8 @@ fn demonstrate_engine_capabilities() {
     // Castling
     println!("\nCastling:");
     let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
-    let all_moves = Moves::generate_all_moves(&board, Color::White);
-    let castle_moves: Vec<&Moves> = all_moves
+    let all_moves = Move::generate_all_moves(&board, Color::White);
+    let castle_moves: Vec<&Move> = all_moves
         .iter()
         .filter(|m| matches!(m.move_type, MoveType::Castle))
         .collect();
@@ -125,32 +139,32 @@ fn demonstrate_engine_capabilities() {
         println!(
             "  {} castling available: {}",
             castle_type,
-            mv.to_algebraic()
+            mv.to_san(&board)
         );
     }
 
     // En passant
     println!("\nEn passant:");
     let board = Board::from_fen("8/8/8/pP6/8/8/8/8 w - a6 0 1");
-    let all_moves = Moves::generate_all_moves(&board, Color::White);
-    let ep_moves: Vec<&Moves> = all_moves
+    let all_moves = Move::generate_all_moves(&board, Color::White);
+    let ep_moves: Vec<&Move> = all_moves
         .iter()
         .filter(|m| matches!(m.move_type, MoveType::EnPassant))
         .collect();
 
     for mv in ep_moves {
-        println!("  En passant capture: {}", mv.to_algebraic());
+        println!("  En passant capture: {}", mv.to_san(&board));
     }
 
     // Promotion
     println!("\nPromotion:");
     let board = Board::from_fen("8/P7/8/8/8/8/8/8 w - - 0 1");
-    let all_moves = Moves::generate_all_moves(&board, Color::White);
-    let promo_moves: Vec<&Moves> = all_moves.iter().filter(|m| m.is_promotion()).collect();
+    let all_moves = Move::generate_all_moves(&board, Color::White);
+    let promo_moves: Vec<&Move> = all_moves.iter().filter(|m| m.is_promotion()).collect();
 
     for mv in promo_moves {
         if let MoveType::Promotion { piece } = mv.move_type {
-            println!("  Promote to {:?}: {}", piece, mv.to_algebraic());
+            println!("  Promote to {:?}: {}", piece, mv.to_san(&board));
         }
     }
 
@@ -161,21 +175,21 @@ fn demonstrate_engine_capabilities() {
     let board = Board::from_fen("4k3/8/8/8/4Q3/8/8/4K3 b - - 0 1");
     println!(
         "Black king in check: {}",
-        Moves::is_in_check(&board, Color::Black)
+        Move::is_in_check(&board, Color::Black)
     );
 
     // Checkmate
     let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R6K b - - 0 1");
     println!(
         "Black in checkmate: {}",
-        Moves::is_checkmate(&board, Color::Black)
+        Move::is_checkmate(&board, Color::Black)
     );
 
     // Stalemate
     let board = Board::from_fen("k7/8/1K6/1Q6/8/8/8/8 b - - 0 1");
     println!(
         "Black in stalemate: {}",
-        Moves::is_stalemate(&board, Color::Black)
+        Move::is_stalemate(&board, Color::Black)
     );
 
     println!("\n=== Ready to Play! ===");
@@ -196,6 +210,8 @@ fn interactive_evaluation() {
     println!("  Starting position: rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
     println!("  After 1.e4: rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
 
+    let mut ctx = SearchContext::default();
+
     loop {
         print!("\nEnter FEN: ");
         io::stdout().flush().unwrap();
@@ -208,34 +224,38 @@ fn interactive_evaluation() {
                 break;
             }
 
-            match parse_fen(fen) {
+            match Board::try_from_fen(fen) {
                 Ok(board) => {
                     println!("\n--- Position ---");
                     board.display();
 
-                    let eval_score = Eval::alpha_beta(&board, 1, -10000, 10000, board.to_move);
-                    // Convert to White's perspective for display
-                    let eval_from_white_pov = if board.to_move {
-                        eval_score
-                    } else {
-                        -eval_score
-                    };
+                    // alpha_beta already scores from White's point of view
+                    let eval_from_white_pov =
+                        Eval::alpha_beta(&board, 1, -INFINITY, INFINITY, board.to_move, &mut ctx);
                     let material_balance = Eval::material_balance(&board);
                     let mobility_balance = Eval::mobility_balance(&board);
-                    let static_eval = Eval::evaluate(&board);
+                    let static_eval = Eval::evaluate_absolute(&board);
                     let pawn_structure = Eval::pawn_structure_balance(&board);
+                    let imbalance = Eval::imbalance_balance(&board);
 
                     println!("--- Evaluation Breakdown ---");
                     println!("Material balance: {} centipawns", material_balance);
                     println!("Mobility balance: {} centipawns", mobility_balance);
                     println!("Pawn structure: {} centipawns", pawn_structure);
+                    println!("Material imbalance: {} centipawns", imbalance);
                     println!("Static evaluation: {} centipawns", static_eval);
                     println!(
                         "\nTotal evaluation (in-depth): {} centipawns",
                         eval_from_white_pov
                     );
 
-                    if eval_from_white_pov > 0 {
+                    if let Some(moves) = mate_in(eval_from_white_pov) {
+                        if moves > 0 {
+                            println!("White mates in {}", moves);
+                        } else {
+                            println!("Black mates in {}", -moves);
+                        }
+                    } else if eval_from_white_pov > 0 {
                         println!(
                             "Position favors White by {:.2} pawns",
                             eval_from_white_pov as f32 / 100.0
@@ -250,7 +270,7 @@ fn interactive_evaluation() {
                     }
                 }
                 Err(e) => {
-                    println!("Error parsing FEN: {}", e);
+                    report_fen_error(fen, &e);
                     println!("Please enter a valid FEN string.");
                 }
             }
@@ -258,7 +278,374 @@ fn interactive_evaluation() {
     }
 }
 
+/// Ask which side the engine should play and how long it may think, then start the game
+fn play_against_engine() {
+    use std::io::{self, Write};
+
+    let prompt = |question: &str| {
+        print!("{}", question);
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).ok();
+        input.trim().to_lowercase()
+    };
+
+    let human = match prompt("Play as (w)hite or (b)lack? [w]: ").as_str() {
+        "b" | "black" => Color::Black,
+        _ => Color::White,
+    };
+    let answer = prompt("Engine depth in plies, or think time like '5s' [4]: ");
+    let limits = if let Some(seconds) = answer.strip_suffix('s') {
+        let seconds = seconds.trim().parse().unwrap_or(5);
+        SearchLimits::move_time(Duration::from_secs(seconds))
+    } else {
+        SearchLimits::fixed_depth(answer.parse().unwrap_or(4))
+    };
+
+    println!("\nStarting a game against the engine...\n");
+    let mut game = ChessGame::new().with_engine(human.opposite(), limits);
+    game.run();
+}
+
+/// `--json [FEN] [DEPTH]`: print the position and a search of it as JSON, then exit
+#[cfg(feature = "serde")]
+fn print_json(args: &[String]) {
+    use oxm8::json::{AnalysisJson, PositionJson};
+
+    #[derive(serde::Serialize)]
+    struct Report {
+        position: PositionJson,
+        analysis: AnalysisJson,
+    }
+
+    let fen = args.first().map_or(START_FEN, String::as_str);
+    let depth = args
+        .get(1)
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(4);
+    match Board::try_from_fen(fen) {
+        Ok(board) => {
+            let report = Report {
+                position: PositionJson::new(&board),
+                analysis: AnalysisJson::search(&board, SearchLimits::fixed_depth(depth)),
+            };
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        }
+        Err(e) => {
+            eprintln!("{}", serde_json::json!({ "error": e.to_string() }));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `bench [DEPTH]`: search the bench positions and print the node count and speed
+fn bench(args: &[String]) {
+    let depth = args
+        .first()
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(oxm8::bench::DEFAULT_DEPTH);
+    let report = oxm8::bench::run(depth, |fen, nodes| println!("{:>10}  {}", nodes, fen));
+    println!("\n===========================");
+    println!("Positions     : {}", report.positions);
+    println!("Depth         : {}", depth);
+    println!("Total time(ms): {}", report.elapsed.as_millis());
+    println!("Nodes searched: {}", report.nodes);
+    println!("Nodes/second  : {}", report.nps());
+    #[cfg(feature = "stats")]
+    println!("\n{}", report.stats);
+}
+
+/// A search score from White's point of view, in pawns or as `#N` for a mate
+fn format_score(board: &Board, score: i32) -> String {
+    let score = if board.to_move { score } else { -score };
+    match Score::from(score) {
+        Score::Centipawns(cp) => format!("{:+.2}", cp as f32 / 100.0),
+        Score::Mate(moves) => format!("#{}", moves),
+    }
+}
+
+/// The win, draw and loss chances of a search score from White's point of view, e.g.
+/// `W 21% D 77% L 2%`
+fn format_wdl(board: &Board, score: i32) -> String {
+    let wdl = Eval::wdl(score, board);
+    let wdl = if board.to_move { wdl } else { wdl.flip() };
+    format!(
+        "W {:>3}% D {:>3}% L {:>3}%",
+        (wdl.win + 5) / 10,
+        (wdl.draw + 5) / 10,
+        (wdl.loss + 5) / 10
+    )
+}
+
+/// A line of moves in SAN with move numbers, e.g. `12... Nf6 13. e5`
+fn format_line(board: &Board, moves: &[Move]) -> String {
+    let mut board = *board;
+    let mut line = Vec::new();
+    for (i, mv) in moves.iter().enumerate() {
+        if board.to_move {
+            line.push(format!("{}.", board.fullmove_number));
+        } else if i == 0 {
+            line.push(format!("{}...", board.fullmove_number));
+        }
+        line.push(mv.to_san(&board));
+        board.make_move(mv);
+    }
+    line.join(" ")
+}
+
+/// `analyze [--params FILE] [FEN]`: search until Enter or Ctrl-C, printing every
+/// completed iteration, then report the best line found. `--params` evaluates with the
+/// weights in a TOML or JSON file instead of the built-in ones
+fn analyze(args: &[String]) {
+    let mut search = Search::default();
+    let args = match args {
+        [flag, path, rest @ ..] if flag == "--params" => {
+            match oxm8::params::EvalParams::load(path) {
+                Ok(params) => search.set_eval_params(params),
+                Err(e) => {
+                    eprintln!("Could not read {}: {}", path, e);
+                    std::process::exit(1);
+                }
+            }
+            rest
+        }
+        args => args,
+    };
+    let fen = if args.is_empty() {
+        START_FEN.to_string()
+    } else {
+        args.join(" ")
+    };
+    let board = match Board::try_from_fen(&fen) {
+        Ok(board) => board,
+        Err(e) => {
+            report_fen_error(&fen, &e);
+            std::process::exit(1);
+        }
+    };
+
+    let handle = search.handle();
+    let interrupt = handle.clone();
+    if let Err(e) = ctrlc::set_handler(move || interrupt.stop()) {
+        eprintln!("Ctrl-C will not stop the analysis: {}", e);
+    }
+    // Left blocked on stdin if the search ends on its own; exiting main ends it
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = std::io::stdin().read_line(&mut line);
+        handle.stop();
+    });
+
+    board.display();
+    println!("Analyzing {}", to_fen(&board));
+    println!("Press Enter or Ctrl-C to stop.\n");
+
+    let mut best = None;
+    let mut observer = |info: &SearchInfo| {
+        println!(
+            "depth {:>2}/{:<2}  {:>7}  {}  {:>10} nodes  {:>8} nps  {:>6.1}s  {}",
+            info.depth,
+            info.seldepth,
+            format_score(&board, info.score),
+            format_wdl(&board, info.score),
+            info.nodes,
+            info.nps,
+            info.elapsed.as_secs_f32(),
+            format_line(&board, &info.pv)
+        );
+        best = Some(info.clone());
+    };
+    let result = search.go_with_observer(&board, SearchLimits::infinite(), &mut observer);
+
+    match best {
+        Some(info) => println!(
+            "\nBest line (depth {}, {}, {}): {}",
+            info.depth,
+            format_score(&board, info.score),
+            format_wdl(&board, info.score),
+            format_line(&board, &info.pv)
+        ),
+        None if result.best_move.is_none() => println!("No legal moves in this position."),
+        None => println!("\nStopped before the first iteration completed."),
+    }
+}
+
+/// `puzzles [FILE]`: solve the tactics in an EPD or CSV file, or the built-in set
+fn puzzles(args: &[String]) {
+    use oxm8::puzzle::{BUILTIN_PUZZLES, parse_puzzles, run_puzzles};
+    use oxm8::render::BoardStyle;
+
+    let text = match args.first() {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => BUILTIN_PUZZLES.to_string(),
+    };
+    let puzzles = match parse_puzzles(&text) {
+        Ok(puzzles) => puzzles,
+        Err(e) => {
+            eprintln!("Invalid puzzle file: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Enter your answer in SAN or UCI, 'skip' to see the solution or 'quit'.");
+    let score = run_puzzles(
+        &puzzles,
+        SearchLimits::fixed_depth(4),
+        BoardStyle::detect(),
+        std::io::stdin().lock(),
+        std::io::stdout(),
+    )
+    .expect("terminal I/O failed");
+    println!(
+        "\nFinal score: {}/{} solved, best streak {}",
+        score.solved, score.attempted, score.best_streak
+    );
+}
+
+/// `book PGN OUTPUT [PLIES]`: build a Polyglot book from the first moves of every game
+/// in a PGN file and write it to OUTPUT
+fn build_book(args: &[String]) {
+    use oxm8::book::OpeningBook;
+    use std::fs::File;
+    use std::io::{BufReader, BufWriter};
+
+    let [pgn, output, rest @ ..] = args else {
+        eprintln!("Usage: oxm8 book PGN OUTPUT [PLIES]");
+        std::process::exit(1);
+    };
+    let plies = rest
+        .first()
+        .and_then(|plies| plies.parse().ok())
+        .unwrap_or(20);
+    let book = File::open(pgn)
+        .map_err(|e| e.to_string())
+        .and_then(|file| OpeningBook::from_pgn(BufReader::new(file), plies));
+    let book = match book {
+        Ok(book) => book,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", pgn, e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = File::create(output).and_then(|file| book.write(BufWriter::new(file))) {
+        eprintln!("Could not write {}: {}", output, e);
+        std::process::exit(1);
+    }
+    println!("Wrote {} entries to {}", book.len(), output);
+}
+
+/// `explore PGN`: index the games of a PGN file and play through positions, showing
+/// the moves played in them with `explore`
+fn explore(args: &[String]) {
+    use oxm8::database::GameDatabase;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    let Some(pgn) = args.first() else {
+        eprintln!("Usage: oxm8 explore PGN");
+        std::process::exit(1);
+    };
+    let database = File::open(pgn)
+        .map_err(|e| e.to_string())
+        .and_then(|file| GameDatabase::from_pgn(BufReader::new(file)));
+    let database = match database {
+        Ok(database) => database,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", pgn, e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Indexed {} games. Type 'explore' to see the moves played in a position\n",
+        database.len()
+    );
+    ChessGame::new().with_database(database).run();
+}
+
+/// `import URL|ID`: download a lichess or chess.com game and open it at its final
+/// position, to step through with `goto`
+#[cfg(feature = "import")]
+fn import(args: &[String]) {
+    use oxm8::import::{Curl, import_game};
+
+    let Some(source) = args.first() else {
+        eprintln!("Usage: oxm8 import URL|ID");
+        std::process::exit(1);
+    };
+    let game = match import_game(source, &mut Curl) {
+        Ok(game) => game,
+        Err(e) => {
+            eprintln!("Could not import {}: {}", source, e);
+            std::process::exit(1);
+        }
+    };
+    let info = game.info();
+    println!(
+        "Imported {} vs {}, {} plies\n",
+        info.white.as_deref().unwrap_or("?"),
+        info.black.as_deref().unwrap_or("?"),
+        game.history().len()
+    );
+    ChessGame::from(game).run();
+}
+
+#[cfg(not(feature = "import"))]
+fn import(_args: &[String]) {
+    eprintln!(
+        "Importing games needs the import feature: cargo run --features import -- import URL"
+    );
+    std::process::exit(1);
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json(_args: &[String]) {
+    eprintln!("JSON output needs the serde feature: cargo run --features serde -- --json");
+    std::process::exit(1);
+}
+
 fn main() {
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--json") {
+        print_json(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("analyze") {
+        analyze(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("puzzles") {
+        puzzles(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("bench") {
+        bench(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("book") {
+        build_book(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("explore") {
+        explore(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("import") {
+        import(&args[1..]);
+        return;
+    }
+
     println!("🏰 Welcome to OxM8 Chess Engine! 🏰");
 
     // Test the specific position first
@@ -270,11 +657,12 @@ fn main() {
     // Ask user what they want to do
     println!("\nWhat would you like to do?");
     println!("1. Play interactive chess game");
-    println!("2. Evaluate chess positions (FEN input)");
-    println!("3. Exit");
+    println!("2. Play against the engine");
+    println!("3. Evaluate chess positions (FEN input)");
+    println!("4. Exit");
 
     use std::io::{self, Write};
-    print!("\nEnter choice (1-3): ");
+    print!("\nEnter choice (1-4): ");
     io::stdout().flush().unwrap();
 
     let mut input = String::new();
@@ -286,9 +674,12 @@ fn main() {
                 game.run();
             }
             "2" => {
+                play_against_engine();
+            }
+            "3" => {
                 interactive_evaluation();
             }
-            "3" | "" => {
+            "4" | "" => {
                 println!("Thanks for checking out OxM8 Chess! 👋");
             }
             _ => {
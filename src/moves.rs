@@ -1,6 +1,13 @@
-use crate::board::Board;
+use crate::attacks;
+use crate::bitboard;
+use crate::board::{BLACK_KINGSIDE, BLACK_QUEENSIDE, Board, WHITE_KINGSIDE, WHITE_QUEENSIDE};
+use crate::movelist::{self, MoveList};
 use crate::piece::{Color, Piece};
+use crate::square::Square;
 use crate::util;
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+use core::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MoveType {
@@ -14,126 +21,144 @@ pub enum MoveType {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Moves {
+pub struct Move {
     pub from: u8,
     pub to: u8,
     pub move_type: MoveType,
 }
 
-impl Moves {
-    pub fn new(from: u8, to: u8, move_type: MoveType) -> Self {
-        Moves {
-            from,
-            to,
+/// Coordinate notation, as [`Move::to_algebraic`]
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_algebraic())
+    }
+}
+
+/// Parses coordinate notation ("e2e4", "e7e8=Q" or "e7e8q"). Without a board only the
+/// squares and promotion piece are known, so the move is `Normal` or `Promotion`; match
+/// it against the legal moves of its position to recover the full move type
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to, promotion) =
+            util::parse_algebraic(s).ok_or_else(|| format!("Invalid move '{}'", s))?;
+        let move_type = match promotion {
+            Some(piece) => MoveType::Promotion { piece },
+            None => MoveType::Normal,
+        };
+        Ok(Move::new(from, to, move_type))
+    }
+}
+
+/// Former name of [`Move`]
+#[deprecated(note = "renamed to `Move`")]
+pub type Moves = Move;
+
+impl Move {
+    pub fn new(from: impl Into<Square>, to: impl Into<Square>, move_type: MoveType) -> Self {
+        Move {
+            from: from.into().index(),
+            to: to.into().index(),
             move_type,
         }
     }
 
-    pub fn pawn_moves(board: &Board, square: u8, color: Color) -> Vec<Moves> {
-        let mut moves = Vec::new();
+    /// A quiet move
+    pub fn normal(from: impl Into<Square>, to: impl Into<Square>) -> Self {
+        Self::new(from, to, MoveType::Normal)
+    }
 
-        // Direction variables based on color
-        let (forward_dir, start_rank, promotion_rank, left_capture_dir, right_capture_dir) =
-            match color {
-                Color::White => (8, 1, 6, 7, 9),    // White moves "up" the board
-                Color::Black => (-8, 6, 1, -9, -7), // Black moves "down" the board
-            };
+    pub fn capture(from: impl Into<Square>, to: impl Into<Square>) -> Self {
+        Self::new(from, to, MoveType::Capture)
+    }
 
-        let rank = square / 8;
-        let file = square % 8;
+    /// A pawn push onto the last rank, promoting to `piece`
+    pub fn promotion(from: impl Into<Square>, to: impl Into<Square>, piece: Piece) -> Self {
+        Self::new(from, to, MoveType::Promotion { piece })
+    }
 
-        // Forward moves
-        let one_forward = (square as i8 + forward_dir) as u8;
-        if one_forward < 64 && board.get_piece_at(one_forward).is_none() {
-            if rank == promotion_rank {
-                // Promotion
-                Self::add_promotions(&mut moves, square, one_forward, false);
-            } else {
-                // Normal forward move
-                moves.push(Moves::new(square, one_forward, MoveType::Normal));
-
-                // Double pawn push from starting position
-                if rank == start_rank {
-                    let two_forward = (square as i8 + forward_dir * 2) as u8;
-                    if board.get_piece_at(two_forward).is_none() {
-                        moves.push(Moves::new(square, two_forward, MoveType::Double));
-                    }
-                }
-            }
-        }
+    /// Start building a move from `from` to `to`, a quiet move unless flagged otherwise
+    pub fn builder(from: impl Into<Square>, to: impl Into<Square>) -> MoveBuilder {
+        MoveBuilder::new(from, to)
+    }
 
-        // Left diagonal capture
-        if file > 0 {
-            let capture_left = (square as i8 + left_capture_dir) as u8;
-            if let Some((_, enemy_color)) = board.get_piece_at(capture_left) {
-                if enemy_color != color {
-                    if rank == promotion_rank {
-                        Self::add_promotions(&mut moves, square, capture_left, true);
-                    } else {
-                        moves.push(Moves::new(square, capture_left, MoveType::Capture));
-                    }
-                }
-            }
-        }
+    pub fn from_square(&self) -> Square {
+        Square::from(self.from)
+    }
+
+    pub fn to_square(&self) -> Square {
+        Square::from(self.to)
+    }
 
-        // Right diagonal capture
-        if file < 7 {
-            let capture_right = (square as i8 + right_capture_dir) as u8;
-            if let Some((_, enemy_color)) = board.get_piece_at(capture_right) {
-                if enemy_color != color {
-                    if rank == promotion_rank {
-                        Self::add_promotions(&mut moves, square, capture_right, true);
-                    } else {
-                        moves.push(Moves::new(square, capture_right, MoveType::Capture));
+    pub fn pawn_moves(board: &Board, square: u8, color: Color) -> MoveList {
+        let mut moves = MoveList::new();
+        Self::add_pawn_moves(board, 1u64 << square, color, &mut moves);
+        moves
+    }
+
+    /// Pseudo-legal moves of the pawns of `color` on `pawns`, a whole set at a time:
+    /// each kind of move is one shift of the pawn bitboard, and the offset of that shift
+    /// leads back from every target to its pawn
+    fn add_pawn_moves(board: &Board, pawns: u64, color: Color, moves: &mut MoveList) {
+        let pawns = pawns & board.get_bb(Piece::Pawn, color);
+        let empty = !board.get_all_occupied();
+        let enemies = board.get_all_pieces(color.opposite());
+        let (push, west_capture, east_capture, last_rank, double_rank) = match color {
+            Color::White => (8, 7, 9, bitboard::RANK_8, bitboard::rank_mask(3)),
+            Color::Black => (-8, -9, -7, bitboard::RANK_1, bitboard::rank_mask(4)),
+        };
+        let (west_attacks, east_attacks) = match color {
+            Color::White => (bitboard::north_west(pawns), bitboard::north_east(pawns)),
+            Color::Black => (bitboard::south_west(pawns), bitboard::south_east(pawns)),
+        };
+
+        let mut add = |targets: u64, offset: i8, move_type: MoveType| {
+            let mut targets = targets;
+            while targets != 0 {
+                let to = targets.trailing_zeros() as u8;
+                targets &= targets - 1;
+                let from = (to as i8 - offset) as u8;
+                match move_type {
+                    MoveType::Promotion { .. } => Self::add_promotions(moves, from, to, false),
+                    MoveType::PromotionCapture { .. } => {
+                        Self::add_promotions(moves, from, to, true)
                     }
+                    _ => moves.push(Move::new(from, to, move_type)),
                 }
             }
+        };
+        let promotion = MoveType::Promotion {
+            piece: Piece::Queen,
+        };
+        let promotion_capture = MoveType::PromotionCapture {
+            piece: Piece::Queen,
+        };
+
+        let single = bitboard::forward(pawns, color) & empty;
+        let double = bitboard::forward(single, color) & empty & double_rank;
+        add(single & !last_rank, push, MoveType::Normal);
+        add(single & last_rank, push, promotion);
+        add(double, push * 2, MoveType::Double);
+
+        for (attacks, offset) in [(west_attacks, west_capture), (east_attacks, east_capture)] {
+            let captures = attacks & enemies;
+            add(captures & !last_rank, offset, MoveType::Capture);
+            add(captures & last_rank, offset, promotion_capture);
         }
 
-        // En passant captures
-        if let Some(en_passant_square) = board.en_passant {
-            // Left en passant (capturing to the left)
-            if file > 0 {
-                let left_square = (square as i8 + left_capture_dir) as u8;
-                if left_square == en_passant_square {
-                    // The enemy pawn should be on the same rank as us, one file to the left
-                    let enemy_pawn_square = square - 1;
-                    let enemy_color = if color == Color::White {
-                        Color::Black
-                    } else {
-                        Color::White
-                    };
-                    if let Some((piece, pawn_color)) = board.get_piece_at(enemy_pawn_square) {
-                        if piece == Piece::Pawn && pawn_color == enemy_color {
-                            moves.push(Moves::new(square, en_passant_square, MoveType::EnPassant));
-                        }
-                    }
-                }
-            }
-            // Right en passant (capturing to the right)
-            if file < 7 {
-                let right_square = (square as i8 + right_capture_dir) as u8;
-                if right_square == en_passant_square {
-                    // The enemy pawn should be on the same rank as us, one file to the right
-                    let enemy_pawn_square = square + 1;
-                    let enemy_color = if color == Color::White {
-                        Color::Black
-                    } else {
-                        Color::White
-                    };
-                    if let Some((piece, pawn_color)) = board.get_piece_at(enemy_pawn_square) {
-                        if piece == Piece::Pawn && pawn_color == enemy_color {
-                            moves.push(Moves::new(square, en_passant_square, MoveType::EnPassant));
-                        }
-                    }
-                }
+        // The pawn that just made a double step sits behind the en passant square
+        if let Some(square) = board.en_passant {
+            let target = 1u64 << square;
+            let enemy_pawns = board.get_bb(Piece::Pawn, color.opposite());
+            if bitboard::forward(target, color.opposite()) & enemy_pawns != 0 {
+                add(west_attacks & target, west_capture, MoveType::EnPassant);
+                add(east_attacks & target, east_capture, MoveType::EnPassant);
             }
         }
-
-        moves
     }
 
-    fn add_promotions(moves: &mut Vec<Moves>, from: u8, to: u8, is_capture: bool) {
+    fn add_promotions(moves: &mut MoveList, from: u8, to: u8, is_capture: bool) {
         let promotion_pieces = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
 
         for piece in promotion_pieces {
@@ -143,20 +168,16 @@ impl Moves {
                 MoveType::Promotion { piece }
             };
             // This just adds Move objects to a list - no board changes!
-            moves.push(Moves::new(from, to, move_type));
+            moves.push(Move::new(from, to, move_type));
         }
     }
 
     /// Generate all legal moves for a given color
-    pub fn generate_all_moves(board: &Board, color: Color) -> Vec<Moves> {
-        let mut all_moves = Vec::new();
+    pub fn generate_all_moves(board: &Board, color: Color) -> MoveList {
+        let mut all_moves = MoveList::new();
 
         // Generate pawn moves
-        let pawn_squares = board.get_piece_squares(color, Piece::Pawn);
-        for square in pawn_squares {
-            let pawn_moves = Self::pawn_moves(board, square, color);
-            all_moves.extend(pawn_moves);
-        }
+        Self::add_pawn_moves(board, u64::MAX, color, &mut all_moves);
 
         // Generate knight moves
         let knight_squares = board.get_piece_squares(color, Piece::Knight);
@@ -196,17 +217,179 @@ impl Moves {
         all_moves
     }
 
-    /// Generate only legal moves for a given color (filters out moves that leave king in check)
-    pub fn generate_legal_moves(board: &Board, color: Color) -> Vec<Moves> {
-        let all_moves = Self::generate_all_moves(board, color);
-        all_moves
+    /// Generate only legal moves for a given color
+    /// Pinned pieces, checkers and the squares the king may not step on are computed up
+    /// front, so every pseudo-legal move is accepted or rejected without playing it out.
+    /// Only en passant, which can uncover a check along the rank, is verified on a copy
+    pub fn generate_legal_moves(board: &Board, color: Color) -> MoveList {
+        let legality = Legality::new(board, color);
+        Self::generate_all_moves(board, color)
             .into_iter()
-            .filter(|mv| Self::is_legal_move(board, mv, color))
+            .filter(|mv| legality.allows(mv))
             .collect()
     }
 
+    /// Whether `color` has any legal move, stopping at the first one found. Pseudo-legal
+    /// moves are generated lazily and checked like [`Move::generate_legal_moves`]
+    pub fn has_legal_move(board: &Board, color: Color) -> bool {
+        let legality = Legality::new(board, color);
+        MoveGen::new(board, color).any(|mv| legality.allows(&mv))
+    }
+
+    /// Count the legal moves for a given color
+    pub fn count_legal_moves(board: &Board, color: Color) -> usize {
+        Self::generate_legal_moves(board, color).len()
+    }
+
+    /// Count the leaf nodes of the legal move tree to the given depth, used to validate
+    /// move generation. The last ply is bulk counted instead of played out
+    pub fn perft(board: &Board, depth: u8) -> u64 {
+        let mut board = *board;
+        Self::perft_inner(&mut board, depth)
+    }
+
+    fn perft_inner(board: &mut Board, depth: u8) -> u64 {
+        let color = if board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        match depth {
+            0 => 1,
+            1 => Self::count_legal_moves(board, color) as u64,
+            _ => {
+                let mut nodes = 0;
+                for mv in Self::generate_legal_moves(board, color) {
+                    let undo = board.make_move(&mv);
+                    nodes += Self::perft_inner(board, depth - 1);
+                    board.unmake_move(&undo);
+                }
+                nodes
+            }
+        }
+    }
+
+    /// Perft with the root moves shared out between `threads` worker threads
+    /// A thread count of zero uses the available parallelism
+    #[cfg(feature = "std")]
+    pub fn perft_parallel(board: &Board, depth: u8, threads: usize) -> u64 {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        if depth <= 1 {
+            return Self::perft(board, depth);
+        }
+        let color = if board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let root_moves = Self::generate_legal_moves(board, color);
+        let threads = match threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+        .min(root_moves.len())
+        .max(1);
+
+        // Workers take the next unclaimed root move, so uneven subtrees balance out
+        let next = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut nodes = 0;
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(mv) = root_moves.get(index) else {
+                                break nodes;
+                            };
+                            let mut new_board = *board;
+                            new_board.make_move(mv);
+                            nodes += Self::perft(&new_board, depth - 1);
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("perft worker panicked"))
+                .sum()
+        })
+    }
+
+    /// Generate the legal replies to a check: king moves to squares the enemy does not
+    /// attack and, against a single check, the moves that capture the checking piece or
+    /// block the line between it and the king. Outside of check this is the full legal
+    /// move list
+    pub fn generate_evasions(board: &Board, color: Color) -> MoveList {
+        let legality = Legality::new(board, color);
+        let Some(king_square) = legality.king_square else {
+            return Self::generate_legal_moves(board, color);
+        };
+        if legality.check_mask == u64::MAX {
+            return Self::generate_legal_moves(board, color);
+        }
+
+        // The king may not castle out of check
+        let mut evasions: MoveList = Self::king_moves(board, king_square, color)
+            .into_iter()
+            .filter(|mv| {
+                mv.move_type != MoveType::Castle && legality.king_danger & 1u64 << mv.to == 0
+            })
+            .collect();
+        if legality.check_mask == 0 {
+            return evasions;
+        }
+
+        // An en passant capture takes the checking pawn beside its target square
+        let mut blockers = MoveList::new();
+        Self::add_pawn_moves(board, u64::MAX, color, &mut blockers);
+        for square in board.get_piece_squares(color, Piece::Knight) {
+            blockers.extend(Self::knight_moves(board, square, color));
+        }
+        for square in board.get_piece_squares(color, Piece::Bishop) {
+            blockers.extend(Self::bishop_moves(board, square, color));
+        }
+        for square in board.get_piece_squares(color, Piece::Rook) {
+            blockers.extend(Self::rook_moves(board, square, color));
+        }
+        for square in board.get_piece_squares(color, Piece::Queen) {
+            blockers.extend(Self::queen_moves(board, square, color));
+        }
+        evasions.extend(blockers.into_iter().filter(|mv| {
+            (mv.move_type == MoveType::EnPassant || legality.check_mask & 1u64 << mv.to != 0)
+                && legality.allows(mv)
+        }));
+        evasions
+    }
+
+    /// Squares of the enemy pieces giving check to the king of `color`
+    pub fn checkers(board: &Board, color: Color) -> Vec<u8> {
+        let mut checkers = Self::checkers_bb(board, color);
+        let mut squares = Vec::new();
+        while checkers != 0 {
+            squares.push(checkers.trailing_zeros() as u8);
+            checkers &= checkers - 1;
+        }
+        squares
+    }
+
+    /// Enemy pieces giving check to the king of `color`, cached by the board when
+    /// `color` is to move
+    fn checkers_bb(board: &Board, color: Color) -> u64 {
+        if board.to_move == (color == Color::White) {
+            return board.checkers();
+        }
+        let king = board.get_bb(Piece::King, color);
+        if king == 0 {
+            return 0;
+        }
+        board.attackers_to(king.trailing_zeros() as u8, board.get_all_occupied())
+            & board.get_all_pieces(color.opposite())
+    }
+
     /// Check if a move is legal (doesn't leave own king in check)
-    pub fn is_legal_move(board: &Board, mv: &Moves, color: Color) -> bool {
+    pub fn is_legal_move(board: &Board, mv: &Move, color: Color) -> bool {
         // Make the move on a copy of the board
         let mut test_board = *board;
         test_board.make_move(mv);
@@ -214,162 +397,33 @@ impl Moves {
         // Find our king position after the move
         let king_squares = test_board.get_piece_squares(color, Piece::King);
         if let Some(&king_square) = king_squares.first() {
-            let enemy_color = if color == Color::White {
-                Color::Black
-            } else {
-                Color::White
-            };
+            let enemy_color = color.opposite();
             // Check if our king is attacked after the move
             !Self::is_square_attacked(&test_board, king_square, enemy_color)
         } else {
-            // If we don't have a king, the move is illegal
-            false
+            // Without a king there is nothing to expose to check
+            true
         }
     }
 
     /// Check if the current player is in check
     pub fn is_in_check(board: &Board, color: Color) -> bool {
-        let king_squares = board.get_piece_squares(color, Piece::King);
-        if let Some(&king_square) = king_squares.first() {
-            let enemy_color = if color == Color::White {
-                Color::Black
-            } else {
-                Color::White
-            };
-            Self::is_square_attacked(board, king_square, enemy_color)
-        } else {
-            false
-        }
+        Self::checkers_bb(board, color) != 0
     }
 
     /// Check if the current position is checkmate
     pub fn is_checkmate(board: &Board, color: Color) -> bool {
-        Self::is_in_check(board, color) && Self::generate_legal_moves(board, color).is_empty()
+        Self::is_in_check(board, color) && !Self::has_legal_move(board, color)
     }
 
     /// Check if the current position is stalemate
     pub fn is_stalemate(board: &Board, color: Color) -> bool {
-        !Self::is_in_check(board, color) && Self::generate_legal_moves(board, color).is_empty()
+        !Self::is_in_check(board, color) && !Self::has_legal_move(board, color)
     }
 
     /// Check if a square is under attack by the enemy
     pub fn is_square_attacked(board: &Board, square: u8, by_color: Color) -> bool {
-        let file = square % 8;
-        let rank = square / 8;
-
-        // Check for pawn attacks
-        let pawn_attack_dirs = match by_color {
-            Color::White => [-7, -9], // White pawns attack diagonally "down" from their perspective
-            Color::Black => [7, 9],   // Black pawns attack diagonally "up" from their perspective
-        };
-
-        for &dir in &pawn_attack_dirs {
-            let attack_square = (square as i8 - dir) as u8; // Where the attacking pawn would be
-            if attack_square < 64 {
-                let attack_file = attack_square % 8;
-                let attack_rank = attack_square / 8;
-                // Check if the move is within bounds (no wrapping around board edges)
-                if (attack_file as i8 - file as i8).abs() == 1 {
-                    if let Some((Piece::Pawn, color)) = board.get_piece_at(attack_square) {
-                        if color == by_color {
-                            return true;
-                        }
-                    }
-                }
-            }
-        }
-
-        // Check for knight attacks
-        let knight_moves = [
-            (2, 1),
-            (1, 2),
-            (-1, 2),
-            (-2, 1),
-            (-2, -1),
-            (-1, -2),
-            (1, -2),
-            (2, -1),
-        ];
-        for &(dr, df) in &knight_moves {
-            let new_rank = rank as i8 + dr;
-            let new_file = file as i8 + df;
-            if new_rank >= 0 && new_rank < 8 && new_file >= 0 && new_file < 8 {
-                let target_square = (new_rank * 8 + new_file) as u8;
-                if let Some((Piece::Knight, color)) = board.get_piece_at(target_square) {
-                    if color == by_color {
-                        return true;
-                    }
-                }
-            }
-        }
-
-        // Check for bishop/queen diagonal attacks
-        let diagonal_dirs = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
-        for &(dr, df) in &diagonal_dirs {
-            let mut new_rank = rank as i8;
-            let mut new_file = file as i8;
-            loop {
-                new_rank += dr;
-                new_file += df;
-                if new_rank < 0 || new_rank >= 8 || new_file < 0 || new_file >= 8 {
-                    break;
-                }
-                let target_square = (new_rank * 8 + new_file) as u8;
-                if let Some((piece, color)) = board.get_piece_at(target_square) {
-                    if color == by_color && (piece == Piece::Bishop || piece == Piece::Queen) {
-                        return true;
-                    }
-                    break; // Stop on first piece encountered
-                }
-            }
-        }
-
-        // Check for rook/queen orthogonal attacks
-        let orthogonal_dirs = [(1, 0), (0, 1), (-1, 0), (0, -1)];
-        for &(dr, df) in &orthogonal_dirs {
-            let mut new_rank = rank as i8;
-            let mut new_file = file as i8;
-            loop {
-                new_rank += dr;
-                new_file += df;
-                if new_rank < 0 || new_rank >= 8 || new_file < 0 || new_file >= 8 {
-                    break;
-                }
-                let target_square = (new_rank * 8 + new_file) as u8;
-                if let Some((piece, color)) = board.get_piece_at(target_square) {
-                    if color == by_color && (piece == Piece::Rook || piece == Piece::Queen) {
-                        return true;
-                    }
-                    break; // Stop on first piece encountered
-                }
-            }
-        }
-
-        // Check for king attacks
-        let king_moves = [
-            (1, 0),
-            (1, 1),
-            (0, 1),
-            (-1, 1),
-            (-1, 0),
-            (-1, -1),
-            (0, -1),
-            (1, -1),
-        ];
-        for &(dr, df) in &king_moves {
-            let new_rank = rank as i8 + dr;
-            let new_file = file as i8 + df;
-            if new_rank >= 0 && new_rank < 8 && new_file >= 0 && new_file < 8 {
-                let target_square = (new_rank * 8 + new_file) as u8;
-                if let Some((Piece::King, color)) = board.get_piece_at(target_square) {
-                    if color == by_color {
-                        return true;
-                    }
-                }
-            }
-        }
-
-        false
+        board.attackers_to(square, board.get_all_occupied()) & board.get_all_pieces(by_color) != 0
     }
 
     /// Convert a move to simple algebraic notation
@@ -381,162 +435,134 @@ impl Moves {
         util::move_to_algebraic(self.from, self.to, promotion)
     }
 
+    /// Convert a move to UCI long algebraic notation (e.g., "e2e4", "e7e8q")
+    pub fn to_uci(&self) -> String {
+        let mut uci = format!("{}{}", util::u8_to_pos(self.from), util::u8_to_pos(self.to));
+        if let MoveType::Promotion { piece } | MoveType::PromotionCapture { piece } = self.move_type
+        {
+            uci.push(crate::piece::piece_to_char(piece, Color::Black));
+        }
+        uci
+    }
+
+    /// Convert a legal move to standard algebraic notation (e.g., "Nbd2", "O-O", "Qxf7#")
+    /// The board must be the position before the move is played
+    pub fn to_san(&self, board: &Board) -> String {
+        util::move_to_san(board, self)
+    }
+
     /// Check if a move is a promotion
     pub fn is_promotion(&self) -> bool {
-        matches!(self.move_type, MoveType::Promotion { .. } | MoveType::PromotionCapture { .. })
+        matches!(
+            self.move_type,
+            MoveType::Promotion { .. } | MoveType::PromotionCapture { .. }
+        )
     }
 
     /// Check if a move is a capture
     pub fn is_capture(&self) -> bool {
-        matches!(self.move_type, MoveType::Capture | MoveType::EnPassant | MoveType::PromotionCapture { .. })
-    }
-
-    pub fn knight_moves(board: &Board, square: u8, color: Color) -> Vec<Moves> {
-        let mut moves = Vec::new();
-        let knight_offsets = [
-            (2, 1), (1, 2), (-1, 2), (-2, 1),
-            (-2, -1), (-1, -2), (1, -2), (2, -1),
-        ];
-        let rank = square / 8;
-        let file = square % 8;
-        for (dr, df) in &knight_offsets {
-            let new_rank = rank as i8 + dr;
-            let new_file = file as i8 + df;
-            if new_rank >= 0 && new_rank < 8 && new_file >= 0 && new_file < 8 {
-                let to_square = (new_rank * 8 + new_file) as u8;
-                if let Some((_, piece_color)) = board.get_piece_at(to_square) {
-                    if piece_color != color {
-                        let move_type = if piece_color == color { MoveType::Normal } else { MoveType::Capture };
-                        moves.push(Moves::new(square, to_square, move_type));
-                    }
-                } else {
-                    moves.push(Moves::new(square, to_square, MoveType::Normal));
-                }
-            }
-        }
+        matches!(
+            self.move_type,
+            MoveType::Capture | MoveType::EnPassant | MoveType::PromotionCapture { .. }
+        )
+    }
+
+    pub fn knight_moves(board: &Board, square: u8, color: Color) -> MoveList {
+        let mut moves = MoveList::new();
+        Self::add_piece_moves(
+            board,
+            square,
+            attacks::knight_attacks(square),
+            color,
+            &mut moves,
+        );
         moves
     }
 
-    pub fn king_moves(board: &Board, square: u8, color: Color) -> Vec<Moves> {
-        let mut moves = Vec::new();
-        let king_offsets = [
-            (1, 0), (1, 1), (0, 1), (-1, 1),
-            (-1, 0), (-1, -1), (0, -1), (1, -1),
-        ];
-        let rank = square / 8;
-        let file = square % 8;
-        
-        // basic king moves
-        for (dr, df) in &king_offsets {
-            let new_rank = rank as i8 + dr;
-            let new_file = file as i8 + df;
-            if new_rank >= 0 && new_rank < 8 && new_file >= 0 && new_file < 8 {
-                let to_square = (new_rank * 8 + new_file) as u8;
-                if let Some((_, piece_color)) = board.get_piece_at(to_square) {
-                    if piece_color != color {
-                        moves.push(Moves::new(square, to_square, MoveType::Capture));
-                    }
-                } else {
-                    moves.push(Moves::new(square, to_square, MoveType::Normal));
-                }
-            }
+    /// A move from `square` to each of the `attacks` not taken by a piece of `color`,
+    /// tagged as a capture where an enemy piece stands
+    fn add_piece_moves(
+        board: &Board,
+        square: u8,
+        attacks: u64,
+        color: Color,
+        moves: &mut MoveList,
+    ) {
+        let enemies = board.get_all_pieces(color.opposite());
+        let mut targets = attacks & !board.get_all_pieces(color);
+        while targets != 0 {
+            let to = targets.trailing_zeros() as u8;
+            targets &= targets - 1;
+            let move_type = if enemies & 1u64 << to != 0 {
+                MoveType::Capture
+            } else {
+                MoveType::Normal
+            };
+            moves.push(Move::new(square, to, move_type));
         }
+    }
 
-        // Castling moves
-        if !Self::is_square_attacked(
+    pub fn king_moves(board: &Board, square: u8, color: Color) -> MoveList {
+        let mut moves = MoveList::new();
+        Self::add_piece_moves(
             board,
             square,
-            if color == Color::White {
-                Color::Black
-            } else {
-                Color::White
-            },
-        ) {
+            attacks::king_attacks(square),
+            color,
+            &mut moves,
+        );
+
+        // Castling moves: the king lands on the g- or c-file and the rook beside it,
+        // wherever they started, so Chess960 positions castle too
+        if !Self::is_square_attacked(board, square, color.opposite()) {
             // Only attempt castling if king is not currently in check
-
-            // Kingside castling
-            let kingside_bit = match color {
-                Color::White => 0b0001, // White kingside (K)
-                Color::Black => 0b0100, // Black kingside (k)
+            let (rights, back_rank) = match color {
+                Color::White => ([WHITE_KINGSIDE, WHITE_QUEENSIDE], 0),
+                Color::Black => ([BLACK_KINGSIDE, BLACK_QUEENSIDE], 56),
             };
 
-            if (board.castling_rights & kingside_bit) != 0 {
-                let (rook_square, squares_to_check) = match color {
-                    Color::White => (7, vec![5, 6]),    // f1, g1
-                    Color::Black => (63, vec![61, 62]), // f8, g8
-                };
-
-                // Check that squares between king and rook are empty
-                let squares_empty = squares_to_check
-                    .iter()
-                    .all(|&sq| board.get_piece_at(sq).is_none());
-
-                // Check that king doesn't pass through check
-                let no_check_path = squares_to_check.iter().all(|&sq| {
-                    !Self::is_square_attacked(
-                        board,
-                        sq,
-                        if color == Color::White {
-                            Color::Black
-                        } else {
-                            Color::White
-                        },
-                    )
-                });
+            for (right, king_to, rook_to) in [
+                (rights[0], back_rank + 6, back_rank + 5),
+                (rights[1], back_rank + 2, back_rank + 3),
+            ] {
+                if board.castling_rights & right == 0 {
+                    continue;
+                }
+                let rook_square = board.castling_rooks[right.trailing_zeros() as usize];
+                let between = |a: u8, b: u8| a.min(b)..=a.max(b);
 
                 // Verify rook is still there
                 let rook_present =
                     matches!(board.get_piece_at(rook_square), Some((Piece::Rook, c)) if c == color);
 
-                if squares_empty && no_check_path && rook_present {
-                    let target_square = match color {
-                        Color::White => 6,  // g1
-                        Color::Black => 62, // g8
-                    };
-                    moves.push(Moves::new(square, target_square, MoveType::Castle));
+                // Every square the king or the rook crosses or lands on must be empty,
+                // but for the two of them
+                let squares_empty = between(square, king_to)
+                    .chain(between(rook_square, rook_to))
+                    .all(|sq| {
+                        sq == square || sq == rook_square || board.get_piece_at(sq).is_none()
+                    });
+                if !rook_present || !squares_empty {
+                    continue;
                 }
-            }
 
-            // Queenside castling
-            let queenside_bit = match color {
-                Color::White => 0b0010, // White queenside (Q)
-                Color::Black => 0b1000, // Black queenside (q)
-            };
-
-            if (board.castling_rights & queenside_bit) != 0 {
-                let (rook_square, squares_to_check, squares_must_be_empty) = match color {
-                    Color::White => (0, vec![2, 3], vec![1, 2, 3]), // c1, d1 for check; b1, c1, d1 empty
-                    Color::Black => (56, vec![58, 59], vec![57, 58, 59]), // c8, d8 for check; b8, c8, d8 empty
+                // Check that king doesn't pass through check. A rook off the corner leaves
+                // a square that may have shielded the king's path along the back rank
+                let without_rook;
+                let path_board = if matches!(rook_square % 8, 0 | 7) {
+                    board
+                } else {
+                    let mut lifted = *board;
+                    lifted.remove_piece(Piece::Rook, color, rook_square);
+                    without_rook = lifted;
+                    &without_rook
                 };
-
-                // Check that squares between king and rook are empty
-                let squares_empty = squares_must_be_empty
-                    .iter()
-                    .all(|&sq| board.get_piece_at(sq).is_none());
-
-                // Check that king doesn't pass through check
-                let no_check_path = squares_to_check.iter().all(|&sq| {
-                    !Self::is_square_attacked(
-                        board,
-                        sq,
-                        if color == Color::White {
-                            Color::Black
-                        } else {
-                            Color::White
-                        },
-                    )
+                let no_check_path = between(square, king_to).all(|sq| {
+                    sq == square || !Self::is_square_attacked(path_board, sq, color.opposite())
                 });
 
-                // Verify rook is still there
-                let rook_present =
-                    matches!(board.get_piece_at(rook_square), Some((Piece::Rook, c)) if c == color);
-
-                if squares_empty && no_check_path && rook_present {
-                    let target_square = match color {
-                        Color::White => 2,  // c1
-                        Color::Black => 58, // c8
-                    };
-                    moves.push(Moves::new(square, target_square, MoveType::Castle));
+                if no_check_path {
+                    moves.push(Move::new(square, king_to, MoveType::Castle));
                 }
             }
         }
@@ -544,78 +570,258 @@ impl Moves {
         moves
     }
 
-    pub fn rook_moves(board: &Board, square: u8, color: Color) -> Vec<Moves> {
-        let mut moves = Vec::new();
-        let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
-        let rank = square / 8;
-        let file = square % 8;
+    pub fn rook_moves(board: &Board, square: u8, color: Color) -> MoveList {
+        let mut moves = MoveList::new();
+        let attacks = attacks::rook_attacks(square, board.get_all_occupied());
+        Self::add_piece_moves(board, square, attacks, color, &mut moves);
+        moves
+    }
 
-        for (dr, df) in &directions {
-            let mut new_rank = rank as i8;
-            let mut new_file = file as i8;
+    pub fn bishop_moves(board: &Board, square: u8, color: Color) -> MoveList {
+        let mut moves = MoveList::new();
+        let attacks = attacks::bishop_attacks(square, board.get_all_occupied());
+        Self::add_piece_moves(board, square, attacks, color, &mut moves);
+        moves
+    }
 
-            loop {
-                new_rank += dr;
-                new_file += df;
+    pub fn queen_moves(board: &Board, square: u8, color: Color) -> MoveList {
+        let mut moves = MoveList::new();
+        let attacks = attacks::queen_attacks(square, board.get_all_occupied());
+        Self::add_piece_moves(board, square, attacks, color, &mut moves);
+        moves
+    }
+}
 
-                if new_rank < 0 || new_rank >= 8 || new_file < 0 || new_file >= 8 {
-                    break;
-                }
+/// Lazy pseudo-legal move generator yielding the moves of one piece at a time (all
+/// pawns together, then each knight, bishop, rook, queen and the king), so a caller
+/// that stops early never generates the rest of the list. Yields the same moves, in
+/// the same order, as [`Move::generate_all_moves`]
+pub struct MoveGen<'a> {
+    board: &'a Board,
+    color: Color,
+    pieces: core::slice::Iter<'static, Piece>,
+    current: Option<Piece>,
+    remaining: u64,
+    pending: movelist::IntoIter,
+}
 
-                let to_square = (new_rank * 8 + new_file) as u8;
-                if let Some((_, piece_color)) = board.get_piece_at(to_square) {
-                    if piece_color != color {
-                        moves.push(Moves::new(square, to_square, MoveType::Capture));
-                    }
-                    break; // Stop on first piece encountered
-                } else {
-                    moves.push(Moves::new(square, to_square, MoveType::Normal));
-                }
+impl<'a> MoveGen<'a> {
+    const ORDER: [Piece; 6] = [
+        Piece::Pawn,
+        Piece::Knight,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::Queen,
+        Piece::King,
+    ];
+
+    pub fn new(board: &'a Board, color: Color) -> Self {
+        Self {
+            board,
+            color,
+            pieces: Self::ORDER.iter(),
+            current: None,
+            remaining: 0,
+            pending: MoveList::new().into_iter(),
+        }
+    }
+
+    /// Only the moves that do not leave the king in check
+    pub fn legal(self) -> impl Iterator<Item = Move> + 'a {
+        let legality = Legality::new(self.board, self.color);
+        self.filter(move |mv| legality.allows(mv))
+    }
+}
+
+impl Iterator for MoveGen<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        loop {
+            if let Some(mv) = self.pending.next() {
+                return Some(mv);
+            }
+
+            // Next piece of the current type, or move on to the next type
+            while self.remaining == 0 {
+                let piece = *self.pieces.next()?;
+                self.current = Some(piece);
+                self.remaining = self.board.get_bb(piece, self.color);
+            }
+            if self.current == Some(Piece::Pawn) {
+                let mut moves = MoveList::new();
+                Move::add_pawn_moves(self.board, self.remaining, self.color, &mut moves);
+                self.remaining = 0;
+                self.pending = moves.into_iter();
+                continue;
             }
+            let square = self.remaining.trailing_zeros() as u8;
+            self.remaining &= self.remaining - 1;
+
+            let moves = match self.current? {
+                Piece::Pawn => unreachable!("pawns are generated together"),
+                Piece::Knight => Move::knight_moves(self.board, square, self.color),
+                Piece::Bishop => Move::bishop_moves(self.board, square, self.color),
+                Piece::Rook => Move::rook_moves(self.board, square, self.color),
+                Piece::Queen => Move::queen_moves(self.board, square, self.color),
+                Piece::King => Move::king_moves(self.board, square, self.color),
+            };
+            self.pending = moves.into_iter();
         }
+    }
+}
 
-        moves
+/// What a pseudo-legal move of one side must respect to be legal: the squares its king
+/// may not step on, the check to answer and the pins to keep
+struct Legality<'a> {
+    board: &'a Board,
+    color: Color,
+    /// `None` without a king, when every move is legal
+    king_square: Option<u8>,
+    king_danger: u64,
+    check_mask: u64,
+    pinned: u64,
+}
+
+impl<'a> Legality<'a> {
+    fn new(board: &'a Board, color: Color) -> Self {
+        let mut legality = Self {
+            board,
+            color,
+            king_square: None,
+            king_danger: 0,
+            check_mask: u64::MAX,
+            pinned: 0,
+        };
+        let Some(&king_square) = board.get_piece_squares(color, Piece::King).first() else {
+            return legality;
+        };
+        legality.king_square = Some(king_square);
+
+        // Attacked squares with the king lifted off, so it cannot retreat along a checking ray
+        let occupied = board.get_all_occupied() & !(1u64 << king_square);
+        legality.king_danger = attack_map(board, color.opposite(), occupied);
+
+        // Non-king moves must capture the checker or block it; none escape a double check
+        let checkers = Move::checkers_bb(board, color);
+        legality.check_mask = match checkers.count_ones() {
+            0 => u64::MAX,
+            1 => util::between(checkers.trailing_zeros() as u8, king_square) | checkers,
+            _ => 0,
+        };
+        legality.pinned = board.pinned_pieces(color);
+        legality
+    }
+
+    fn allows(&self, mv: &Move) -> bool {
+        let Some(king_square) = self.king_square else {
+            return true;
+        };
+        let to = 1u64 << mv.to;
+        if mv.from == king_square {
+            // Castling paths are already checked by the king move generator
+            return mv.move_type == MoveType::Castle || self.king_danger & to == 0;
+        }
+        if self.check_mask == 0 {
+            return false;
+        }
+        if mv.move_type == MoveType::EnPassant {
+            return Move::is_legal_move(self.board, mv, self.color);
+        }
+        // A pinned piece stays on the line through its king and the pinner
+        let pin_line = if self.pinned & 1u64 << mv.from != 0 {
+            util::line(king_square, mv.from)
+        } else {
+            u64::MAX
+        };
+        self.check_mask & pin_line & to != 0
     }
+}
 
-    pub fn bishop_moves(board: &Board, square: u8, color: Color) -> Vec<Moves> {
-        let mut moves = Vec::new();
-        let directions = [(1, 1), (1, -1), (-1, 1), (-1, -1)]; // Diagonal directions
-        let rank = square / 8;
-        let file = square % 8;
+/// Every square attacked by `color`, with sliders blocked by `occupied`
+fn attack_map(board: &Board, color: Color, occupied: u64) -> u64 {
+    let mut attacks = 0;
 
-        for (dr, df) in &directions {
-            let mut new_rank = rank as i8;
-            let mut new_file = file as i8;
+    for square in board.get_piece_squares(color, Piece::Pawn) {
+        attacks |= attacks::pawn_attacks(square, color);
+    }
+    for square in board.get_piece_squares(color, Piece::Knight) {
+        attacks |= attacks::knight_attacks(square);
+    }
+    for square in board.get_piece_squares(color, Piece::King) {
+        attacks |= attacks::king_attacks(square);
+    }
+    for square in board.get_piece_squares(color, Piece::Bishop) {
+        attacks |= attacks::bishop_attacks(square, occupied);
+    }
+    for square in board.get_piece_squares(color, Piece::Rook) {
+        attacks |= attacks::rook_attacks(square, occupied);
+    }
+    for square in board.get_piece_squares(color, Piece::Queen) {
+        attacks |= attacks::queen_attacks(square, occupied);
+    }
 
-            loop {
-                new_rank += dr;
-                new_file += df;
+    attacks
+}
 
-                if new_rank < 0 || new_rank >= 8 || new_file < 0 || new_file >= 8 {
-                    break;
-                }
+/// Assembles a [`Move`] from its squares and flags, picking the matching [`MoveType`]
+#[derive(Debug, Clone, Copy)]
+pub struct MoveBuilder {
+    from: Square,
+    to: Square,
+    capture: bool,
+    promotion: Option<Piece>,
+    special: Option<MoveType>,
+}
 
-                let to_square = (new_rank * 8 + new_file) as u8;
-                if let Some((_, piece_color)) = board.get_piece_at(to_square) {
-                    if piece_color != color {
-                        moves.push(Moves::new(square, to_square, MoveType::Capture));
-                    }
-                    break; // Stop on first piece encountered
-                } else {
-                    moves.push(Moves::new(square, to_square, MoveType::Normal));
-                }
-            }
+impl MoveBuilder {
+    pub fn new(from: impl Into<Square>, to: impl Into<Square>) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+            capture: false,
+            promotion: None,
+            special: None,
         }
+    }
 
-        moves
+    pub fn capture(mut self) -> Self {
+        self.capture = true;
+        self
+    }
+
+    pub fn promotion(mut self, piece: Piece) -> Self {
+        self.promotion = Some(piece);
+        self
     }
 
-    pub fn queen_moves(board: &Board, square: u8, color: Color) -> Vec<Moves> {
-        let mut moves = Vec::new();
+    /// A king move that castles
+    pub fn castle(mut self) -> Self {
+        self.special = Some(MoveType::Castle);
+        self
+    }
 
-        moves.extend(Self::rook_moves(board, square, color));
-        moves.extend(Self::bishop_moves(board, square, color));
+    pub fn en_passant(mut self) -> Self {
+        self.special = Some(MoveType::EnPassant);
+        self
+    }
 
-        moves
+    /// A pawn advancing two squares from its starting rank
+    pub fn double_push(mut self) -> Self {
+        self.special = Some(MoveType::Double);
+        self
+    }
+
+    /// Castling, en passant and double pushes take precedence over the capture and
+    /// promotion flags
+    pub fn build(self) -> Move {
+        let move_type = match (self.special, self.promotion, self.capture) {
+            (Some(special), _, _) => special,
+            (None, Some(piece), true) => MoveType::PromotionCapture { piece },
+            (None, Some(piece), false) => MoveType::Promotion { piece },
+            (None, None, true) => MoveType::Capture,
+            (None, None, false) => MoveType::Normal,
+        };
+        Move::new(self.from, self.to, move_type)
     }
 }
@@ -0,0 +1,220 @@
+use crate::board::Board;
+use crate::movelist::MoveList;
+use crate::moves::{Move, MoveType};
+use crate::piece::{Color, Piece};
+use crate::util::get_file;
+use alloc::{string::String, string::ToString, vec::Vec};
+use core::fmt;
+
+/// Why some text could not be read as a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveParseError {
+    /// Not written in any notation the parser knows
+    Unrecognised,
+    /// Well formed, but no legal move matches it
+    Illegal,
+    /// Several legal moves match, e.g. "Nd2" with knights on b1 and f3
+    Ambiguous,
+}
+
+impl fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MoveParseError::Unrecognised => "unrecognised move",
+            MoveParseError::Illegal => "illegal move",
+            MoveParseError::Ambiguous => "ambiguous move",
+        })
+    }
+}
+
+impl core::error::Error for MoveParseError {}
+
+impl From<MoveParseError> for String {
+    fn from(error: MoveParseError) -> Self {
+        error.to_string()
+    }
+}
+
+/// What a move's text tells about it: the destination, and as much of the origin as
+/// was written. SAN gives the file or rank only when needed, LAN and UCI give it all
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pattern {
+    piece: Option<Piece>,
+    from_file: Option<u8>,
+    from_rank: Option<u8>,
+    to: u8,
+    promotion: Option<Piece>,
+}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    match c.to_ascii_uppercase() {
+        'K' => Some(Piece::King),
+        'Q' => Some(Piece::Queen),
+        'R' => Some(Piece::Rook),
+        'B' => Some(Piece::Bishop),
+        'N' => Some(Piece::Knight),
+        _ => None,
+    }
+}
+
+fn file_of(c: char) -> Option<u8> {
+    ('a'..='h').contains(&c).then(|| c as u8 - b'a')
+}
+
+fn rank_of(c: char) -> Option<u8> {
+    ('1'..='8').contains(&c).then(|| c as u8 - b'1')
+}
+
+impl Pattern {
+    /// One grammar for all three notations: an optional piece letter, the origin or
+    /// part of it, an optional `-` or `x`, the destination and an optional promotion,
+    /// as `=Q`, `Q` or UCI's `q`. "Nf3", "exd5", "Ng1-f3", "e2e4" and "e7e8q" all fit
+    fn parse(text: &str) -> Option<Self> {
+        let mut chars: Vec<char> = text
+            .chars()
+            .filter(|c| !matches!(c, '-' | 'x' | ':' | ' '))
+            .collect();
+
+        let promotion = match chars.last() {
+            Some(&c) if c.is_ascii_alphabetic() => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(piece_from_char(c).filter(|&piece| piece != Piece::King)?)
+            }
+            _ => None,
+        };
+
+        let [rest @ .., to_file, to_rank] = chars.as_slice() else {
+            return None;
+        };
+        let to = rank_of(*to_rank)? * 8 + file_of(*to_file)?;
+
+        let (piece, origin) = match rest {
+            [c, origin @ ..] if c.is_ascii_uppercase() => (Some(piece_from_char(*c)?), origin),
+            origin => (None, origin),
+        };
+        let (from_file, from_rank) = match origin {
+            [] => (None, None),
+            [file, rank] => (Some(file_of(*file)?), Some(rank_of(*rank)?)),
+            [c] => match (file_of(*c), rank_of(*c)) {
+                (Some(file), _) => (Some(file), None),
+                (_, Some(rank)) => (None, Some(rank)),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(Self {
+            piece,
+            from_file,
+            from_rank,
+            to,
+            promotion,
+        })
+    }
+
+    fn origin(&self) -> Option<u8> {
+        Some(self.from_rank? * 8 + self.from_file?)
+    }
+
+    fn matches(&self, board: &Board, mv: &Move) -> bool {
+        if mv.to != self.to
+            || self.from_file.is_some_and(|file| file != mv.from % 8)
+            || self.from_rank.is_some_and(|rank| rank != mv.from / 8)
+        {
+            return false;
+        }
+        // Without a piece letter, only a full origin square can name a piece move
+        let piece = match (self.piece, self.origin()) {
+            (Some(piece), _) => Some(piece),
+            (None, Some(_)) => None,
+            (None, None) => Some(Piece::Pawn),
+        };
+        if piece.is_some_and(|piece| board.get_piece_at(mv.from).map(|(p, _)| p) != Some(piece)) {
+            return false;
+        }
+        match mv.move_type {
+            MoveType::Promotion { piece } | MoveType::PromotionCapture { piece } => {
+                self.promotion == Some(piece)
+            }
+            _ => self.promotion.is_none(),
+        }
+    }
+}
+
+/// `text` without an en passant mark after the move, as in "exf6 e.p." or "exf6ep"
+fn strip_en_passant(text: &str) -> &str {
+    ["e.p.", "e.p", "ep"]
+        .iter()
+        .find_map(|mark| text.strip_suffix(mark))
+        .map_or(text, str::trim_end)
+}
+
+/// `text` without check marks and `!`/`?` annotations at its end
+fn strip_marks(text: &str) -> &str {
+    text.trim_end()
+        .trim_end_matches(['!', '?'])
+        .trim_end_matches(['+', '#'])
+}
+
+/// Reads the moves of one position written in SAN ("Nf3", "exd5+", "O-O"), long
+/// algebraic ("Ng1-f3", "e2-e4", "e7e8=Q") or UCI ("e7e8q") and returns the legal move
+/// meant. Check marks, `!`/`?` annotations and en passant marks are ignored
+#[derive(Debug, Clone)]
+pub struct MoveParser<'a> {
+    board: &'a Board,
+    legal_moves: MoveList,
+}
+
+impl<'a> MoveParser<'a> {
+    /// A parser for the side to move of `board` under the standard rules
+    pub fn new(board: &'a Board) -> Self {
+        let color = if board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Self::with_moves(board, Move::generate_legal_moves(board, color))
+    }
+
+    /// A parser choosing among `legal_moves`, for variants with their own rules
+    pub fn with_moves(board: &'a Board, legal_moves: MoveList) -> Self {
+        Self { board, legal_moves }
+    }
+
+    /// The legal move `text` stands for
+    pub fn parse(&self, text: &str) -> Result<Move, MoveParseError> {
+        // The marks may come before or after "e.p."
+        let text = strip_marks(strip_en_passant(strip_marks(text.trim())));
+
+        let castle_file = match text {
+            "O-O" | "0-0" | "o-o" => Some(6),
+            "O-O-O" | "0-0-0" | "o-o-o" => Some(2),
+            _ => None,
+        };
+        if let Some(file) = castle_file {
+            return self.select(|mv| mv.move_type == MoveType::Castle && get_file(mv.to) == file);
+        }
+
+        let pattern = Pattern::parse(text).ok_or(MoveParseError::Unrecognised)?;
+        self.select(|mv| pattern.matches(self.board, mv))
+    }
+
+    fn select(&self, matches: impl Fn(&Move) -> bool) -> Result<Move, MoveParseError> {
+        let mut found = self.legal_moves.iter().filter(|mv| matches(mv));
+        match (found.next(), found.next()) {
+            (Some(&mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(MoveParseError::Ambiguous),
+            (None, _) => Err(MoveParseError::Illegal),
+        }
+    }
+}
+
+/// The squares and promotion piece of a move written with its full origin, as in
+/// "e2e4", "e2-e4", "Ng1f3", "e7e8=Q" or "e7e8q", without checking it against a position
+pub fn parse_coordinate(text: &str) -> Option<(u8, u8, Option<Piece>)> {
+    let pattern = Pattern::parse(text.trim())?;
+    Some((pattern.origin()?, pattern.to, pattern.promotion))
+}
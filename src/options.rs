@@ -0,0 +1,222 @@
+#[cfg(feature = "std")]
+use crate::board::Board;
+#[cfg(feature = "std")]
+use crate::eval::Eval;
+#[cfg(feature = "std")]
+use crate::search::SearchInfo;
+use crate::search::{SearchLimits, SearchOptions};
+use alloc::{format, string::String};
+use core::time::Duration;
+
+/// The type and range of a UCI option, as announced in its `option` line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Check { default: bool },
+    Spin { default: i64, min: i64, max: i64 },
+}
+
+/// One entry of the option registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub kind: OptionKind,
+}
+
+impl OptionSpec {
+    /// The line announcing the option after `uci`, e.g.
+    /// `option name Hash type spin default 16 min 1 max 4096`
+    pub fn uci_line(&self) -> String {
+        match self.kind {
+            OptionKind::Check { default } => {
+                format!("option name {} type check default {}", self.name, default)
+            }
+            OptionKind::Spin { default, min, max } => format!(
+                "option name {} type spin default {} min {} max {}",
+                self.name, default, min, max
+            ),
+        }
+    }
+}
+
+/// Every option the engine understands, in the order they are announced. The defaults
+/// match [`EngineOptions::default`]
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "Hash",
+        kind: OptionKind::Spin {
+            default: 16,
+            min: 1,
+            max: 4096,
+        },
+    },
+    OptionSpec {
+        name: "Threads",
+        kind: OptionKind::Spin {
+            default: 1,
+            min: 1,
+            max: 256,
+        },
+    },
+    OptionSpec {
+        name: "MultiPV",
+        kind: OptionKind::Spin {
+            default: 1,
+            min: 1,
+            max: 256,
+        },
+    },
+    OptionSpec {
+        name: "Move Overhead",
+        kind: OptionKind::Spin {
+            default: 10,
+            min: 0,
+            max: 5000,
+        },
+    },
+    OptionSpec {
+        name: "Skill Level",
+        kind: OptionKind::Spin {
+            default: 20,
+            min: 0,
+            max: 20,
+        },
+    },
+    OptionSpec {
+        name: "OwnBook",
+        kind: OptionKind::Check { default: true },
+    },
+    OptionSpec {
+        name: "UCI_ShowWDL",
+        kind: OptionKind::Check { default: false },
+    },
+];
+
+/// Engine settings a GUI can change with `setoption`. The search and the opening book
+/// read them through [`EngineOptions::search_options`], [`EngineOptions::limits`] and
+/// `own_book`, and `info` lines are written with [`EngineOptions::info_line`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineOptions {
+    /// Transposition table size in megabytes
+    pub hash_mb: usize,
+    pub threads: usize,
+    /// Number of best lines to report after each iteration
+    pub multi_pv: usize,
+    /// Time kept back from every move for communication delays
+    pub move_overhead: Duration,
+    /// Playing strength from 0 to 20, full strength at 20. Level `n` below 20 searches
+    /// at most `n + 1` plies
+    pub skill_level: u8,
+    /// Play from the opening book while it has a move
+    pub own_book: bool,
+    /// Add win, draw and loss chances to `info` lines, see [`Eval::wdl`](crate::eval::Eval::wdl)
+    pub show_wdl: bool,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            hash_mb: 16,
+            threads: 1,
+            multi_pv: 1,
+            move_overhead: Duration::from_millis(10),
+            skill_level: 20,
+            own_book: true,
+            show_wdl: false,
+        }
+    }
+}
+
+impl EngineOptions {
+    /// Set an option by its registry name, compared case-insensitively. Spin values
+    /// outside the option's range are rejected
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let spec = OPTIONS
+            .iter()
+            .find(|spec| spec.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown option '{}'", name))?;
+        let value = value.trim();
+        let number = match spec.kind {
+            OptionKind::Check { .. } => match value.to_lowercase().as_str() {
+                "true" => 1,
+                "false" => 0,
+                _ => {
+                    return Err(format!(
+                        "{} must be true or false, not '{}'",
+                        spec.name, value
+                    ));
+                }
+            },
+            OptionKind::Spin { min, max, .. } => {
+                let number: i64 = value
+                    .parse()
+                    .map_err(|_| format!("{} must be a number, not '{}'", spec.name, value))?;
+                if !(min..=max).contains(&number) {
+                    return Err(format!("{} must be between {} and {}", spec.name, min, max));
+                }
+                number
+            }
+        };
+
+        match spec.name {
+            "Hash" => self.hash_mb = number as usize,
+            "Threads" => self.threads = number as usize,
+            "MultiPV" => self.multi_pv = number as usize,
+            "Move Overhead" => self.move_overhead = Duration::from_millis(number as u64),
+            "Skill Level" => self.skill_level = number as u8,
+            "OwnBook" => self.own_book = number != 0,
+            "UCI_ShowWDL" => self.show_wdl = number != 0,
+            _ => unreachable!("every registered option is handled"),
+        }
+        Ok(())
+    }
+
+    /// Apply a UCI `setoption name <name> [value <value>]` command. Names may contain
+    /// spaces, everything between `name` and `value` is the name
+    pub fn apply_setoption(&mut self, command: &str) -> Result<(), String> {
+        let rest = command
+            .trim()
+            .strip_prefix("setoption")
+            .and_then(|rest| rest.trim_start().strip_prefix("name "))
+            .ok_or_else(|| format!("not a setoption command: '{}'", command))?;
+        let (name, value) = rest.split_once(" value ").unwrap_or((rest, ""));
+        self.set(name.trim(), value)
+    }
+
+    /// The `option` lines announcing every option after `uci`
+    pub fn uci_lines() -> impl Iterator<Item = String> {
+        OPTIONS.iter().map(OptionSpec::uci_line)
+    }
+
+    /// Table size and thread count for a [`Search`](crate::search::Search)
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            hash_mb: self.hash_mb,
+            threads: self.threads.max(1),
+        }
+    }
+
+    /// `limits` with the move overhead taken off the move time, the depth capped by
+    /// the skill level and the number of lines to report
+    pub fn limits(&self, limits: SearchLimits) -> SearchLimits {
+        let depth = match self.skill_level {
+            20.. => limits.depth,
+            level => Some(limits.depth.map_or(level + 1, |depth| depth.min(level + 1))),
+        };
+        SearchLimits {
+            depth,
+            movetime: limits.movetime.map(|time| {
+                time.saturating_sub(self.move_overhead)
+                    .max(Duration::from_millis(1))
+            }),
+            multi_pv: Some(self.multi_pv),
+            ..limits
+        }
+    }
+
+    /// The UCI `info` line for an iteration of a search of `board`, with the win, draw
+    /// and loss chances when `UCI_ShowWDL` is on
+    #[cfg(feature = "std")]
+    pub fn info_line(&self, info: &SearchInfo, board: &Board) -> String {
+        info.uci_line(self.show_wdl.then(|| Eval::wdl(info.score, board)))
+    }
+}
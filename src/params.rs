@@ -0,0 +1,260 @@
+use alloc::{format, string::String};
+use core::fmt::Write;
+
+/// Every weight of the static evaluation, in centipawns, so that evaluations can be
+/// tuned from a file without recompiling. Penalties are stored as positive amounts
+/// taken off the score. [`EvalParams::DEFAULT`] holds the built-in values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct EvalParams {
+    // Piece values
+    pub pawn_value: i32,
+    pub knight_value: i32,
+    pub bishop_value: i32,
+    pub rook_value: i32,
+    pub queen_value: i32,
+    // Mobility, per reachable square
+    pub knight_mobility: i32,
+    pub bishop_mobility: i32,
+    pub rook_mobility: i32,
+    pub queen_mobility: i32,
+    // Pawn structure, penalties counted off
+    pub isolated_pawn: i32,
+    pub doubled_pawn: i32,
+    pub backward_pawn: i32,
+    /// For every island past the first
+    pub pawn_island: i32,
+    pub pawn_phalanx: i32,
+    pub pawn_chain: i32,
+    // Material imbalance
+    pub bishop_pair: i32,
+    /// Per knight and per own pawn above five
+    pub knight_pawn_bonus: i32,
+    /// Per bishop and per own pawn above five
+    pub bishop_pawn_penalty: i32,
+    /// Off for every rook past the first
+    pub redundant_rook: i32,
+    // Rooks
+    pub rook_open_file: i32,
+    pub rook_semi_open_file: i32,
+    pub rook_seventh_rank: i32,
+    /// Counted from both rooks of a pair
+    pub connected_rooks: i32,
+    // Passed pawns: a base plus a step per rank advanced, midgame and endgame
+    pub passed_pawn_mg: i32,
+    pub passed_pawn_mg_advance: i32,
+    pub passed_pawn_eg: i32,
+    pub passed_pawn_eg_advance: i32,
+    pub supported_passer_mg: i32,
+    pub supported_passer_mg_advance: i32,
+    pub supported_passer_eg: i32,
+    pub supported_passer_eg_advance: i32,
+    pub blockaded_passer_mg: i32,
+    pub blockaded_passer_mg_advance: i32,
+    pub blockaded_passer_eg: i32,
+    pub blockaded_passer_eg_advance: i32,
+    /// Endgame bonus for a passer the enemy king can no longer catch
+    pub unstoppable_passer: i32,
+    // King placement
+    /// Midgame penalty per rank away from home
+    pub king_shelter: i32,
+    /// Endgame bonus on a centre square
+    pub king_centre: i32,
+    /// Off the endgame bonus per step from the centre
+    pub king_centre_distance: i32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl EvalParams {
+    pub const DEFAULT: Self = Self {
+        pawn_value: 100,
+        knight_value: 300,
+        bishop_value: 300,
+        rook_value: 500,
+        queen_value: 900,
+        knight_mobility: 4,
+        bishop_mobility: 5,
+        rook_mobility: 3,
+        queen_mobility: 2,
+        isolated_pawn: 30,
+        doubled_pawn: 20,
+        backward_pawn: 15,
+        pawn_island: 10,
+        pawn_phalanx: 8,
+        pawn_chain: 10,
+        bishop_pair: 50,
+        knight_pawn_bonus: 6,
+        bishop_pawn_penalty: 3,
+        redundant_rook: 15,
+        rook_open_file: 25,
+        rook_semi_open_file: 12,
+        rook_seventh_rank: 20,
+        connected_rooks: 8,
+        passed_pawn_mg: 10,
+        passed_pawn_mg_advance: 5,
+        passed_pawn_eg: 20,
+        passed_pawn_eg_advance: 15,
+        supported_passer_mg: 5,
+        supported_passer_mg_advance: 2,
+        supported_passer_eg: 10,
+        supported_passer_eg_advance: 5,
+        blockaded_passer_mg: 5,
+        blockaded_passer_mg_advance: 1,
+        blockaded_passer_eg: 10,
+        blockaded_passer_eg_advance: 5,
+        unstoppable_passer: 400,
+        king_shelter: 10,
+        king_centre: 30,
+        king_centre_distance: 10,
+    };
+
+    /// Each parameter by the name it has in files
+    fn fields_mut(&mut self) -> [(&'static str, &mut i32); 39] {
+        [
+            ("pawn_value", &mut self.pawn_value),
+            ("knight_value", &mut self.knight_value),
+            ("bishop_value", &mut self.bishop_value),
+            ("rook_value", &mut self.rook_value),
+            ("queen_value", &mut self.queen_value),
+            ("knight_mobility", &mut self.knight_mobility),
+            ("bishop_mobility", &mut self.bishop_mobility),
+            ("rook_mobility", &mut self.rook_mobility),
+            ("queen_mobility", &mut self.queen_mobility),
+            ("isolated_pawn", &mut self.isolated_pawn),
+            ("doubled_pawn", &mut self.doubled_pawn),
+            ("backward_pawn", &mut self.backward_pawn),
+            ("pawn_island", &mut self.pawn_island),
+            ("pawn_phalanx", &mut self.pawn_phalanx),
+            ("pawn_chain", &mut self.pawn_chain),
+            ("bishop_pair", &mut self.bishop_pair),
+            ("knight_pawn_bonus", &mut self.knight_pawn_bonus),
+            ("bishop_pawn_penalty", &mut self.bishop_pawn_penalty),
+            ("redundant_rook", &mut self.redundant_rook),
+            ("rook_open_file", &mut self.rook_open_file),
+            ("rook_semi_open_file", &mut self.rook_semi_open_file),
+            ("rook_seventh_rank", &mut self.rook_seventh_rank),
+            ("connected_rooks", &mut self.connected_rooks),
+            ("passed_pawn_mg", &mut self.passed_pawn_mg),
+            ("passed_pawn_mg_advance", &mut self.passed_pawn_mg_advance),
+            ("passed_pawn_eg", &mut self.passed_pawn_eg),
+            ("passed_pawn_eg_advance", &mut self.passed_pawn_eg_advance),
+            ("supported_passer_mg", &mut self.supported_passer_mg),
+            (
+                "supported_passer_mg_advance",
+                &mut self.supported_passer_mg_advance,
+            ),
+            ("supported_passer_eg", &mut self.supported_passer_eg),
+            (
+                "supported_passer_eg_advance",
+                &mut self.supported_passer_eg_advance,
+            ),
+            ("blockaded_passer_mg", &mut self.blockaded_passer_mg),
+            (
+                "blockaded_passer_mg_advance",
+                &mut self.blockaded_passer_mg_advance,
+            ),
+            ("blockaded_passer_eg", &mut self.blockaded_passer_eg),
+            (
+                "blockaded_passer_eg_advance",
+                &mut self.blockaded_passer_eg_advance,
+            ),
+            ("unstoppable_passer", &mut self.unstoppable_passer),
+            ("king_shelter", &mut self.king_shelter),
+            ("king_centre", &mut self.king_centre),
+            ("king_centre_distance", &mut self.king_centre_distance),
+        ]
+    }
+
+    /// One parameter by name, e.g. `get("rook_value")`
+    pub fn get(&self, name: &str) -> Option<i32> {
+        let mut params = *self;
+        params
+            .fields_mut()
+            .into_iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn set(&mut self, name: &str, value: i32) -> Result<(), String> {
+        let (_, field) = self
+            .fields_mut()
+            .into_iter()
+            .find(|(field, _)| *field == name)
+            .ok_or_else(|| format!("unknown evaluation parameter '{}'", name))?;
+        *field = value;
+        Ok(())
+    }
+
+    /// Read `name = value` lines as written by [`EvalParams::to_toml`], a flat TOML
+    /// table. Parameters left out keep their default and `#` starts a comment. Section
+    /// headers are rejected, as their keys would otherwise be read as top-level ones
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let mut params = Self::DEFAULT;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(format!("line {}: sections are not supported", number + 1));
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected 'name = value'", number + 1))?;
+            let value = value.trim().parse().map_err(|_| {
+                format!(
+                    "line {}: '{}' is not a whole number",
+                    number + 1,
+                    value.trim()
+                )
+            })?;
+            params
+                .set(name.trim(), value)
+                .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        Ok(params)
+    }
+
+    /// Every parameter as a `name = value` line, for saving and editing
+    pub fn to_toml(&self) -> String {
+        let mut params = *self;
+        let mut text = String::new();
+        for (name, value) in params.fields_mut() {
+            let _ = writeln!(text, "{} = {}", name, value);
+        }
+        text
+    }
+
+    /// Read a JSON object with the parameters as keys, defaulting those left out
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// Load a parameter file: JSON when the name ends in `.json`, which needs the
+    /// `serde` feature, and TOML otherwise
+    #[cfg(feature = "std")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            #[cfg(feature = "serde")]
+            return Self::from_json(&text);
+            #[cfg(not(feature = "serde"))]
+            return Err("reading JSON parameters needs the serde feature".to_string());
+        }
+        Self::from_toml(&text)
+    }
+}
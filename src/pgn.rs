@@ -0,0 +1,370 @@
+use crate::board::Board;
+use crate::fen::{START_FEN, parse_fen};
+use crate::game::MoveRecord;
+use crate::moves::Move;
+use crate::notation::MoveParser;
+use crate::variation::{NodeId, VariationTree};
+use std::io::BufRead;
+
+/// A single game read from a PGN document
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub initial_board: Board,
+    /// The mainline
+    pub moves: Vec<Move>,
+    /// Mainline comments paired with the number of moves played before them
+    pub comments: Vec<(usize, String)>,
+    /// Every move with its side-lines, comments and NAGs
+    pub tree: VariationTree,
+    pub result: String,
+}
+
+impl Game {
+    /// Look up a tag value by name (e.g. "White", "Result")
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The position reached after replaying every move
+    pub fn final_board(&self) -> Board {
+        let mut board = self.initial_board;
+        for mv in &self.moves {
+            board.make_move(mv);
+        }
+        board
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Move(String),
+    Comment(String),
+    Nag(u8),
+    Result(String),
+    VariationStart,
+    VariationEnd,
+}
+
+fn is_result(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+/// The NAG a move suffix such as "!?" stands for
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Split movetext into tokens, dropping move numbers. Move suffixes such as "!?" become
+/// their NAGs
+fn tokenize(movetext: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = movetext.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '{' => {
+                chars.next();
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            ';' => {
+                chars.next();
+                let comment: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::VariationStart);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::VariationEnd);
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '{' | '}' | '(' | ')' | ';') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                if is_result(&word) {
+                    tokens.push(Token::Result(word));
+                    continue;
+                }
+
+                if let Some(nag) = word.strip_prefix('$') {
+                    if let Ok(nag) = nag.parse() {
+                        tokens.push(Token::Nag(nag));
+                    }
+                    continue;
+                }
+                // E.p. markers carry no move information
+                if word == "e.p." {
+                    continue;
+                }
+
+                let san = strip_move_number(&word);
+                let suffix = san.len() - san.trim_end_matches(['!', '?']).len();
+                let (san, suffix) = san.split_at(san.len() - suffix);
+                if !san.is_empty() {
+                    tokens.push(Token::Move(san.to_string()));
+                }
+                if let Some(nag) = suffix_nag(suffix) {
+                    tokens.push(Token::Nag(nag));
+                }
+            }
+        }
+    }
+
+    tokens
+}
+
+/// `word` without a leading move number ("12." or "12..."), which may be glued to
+/// the move. Digits only count as one when a dot follows, so castling written with
+/// zeros ("0-0", "0-0-0") is left alone. A bare number is dropped
+fn strip_move_number(word: &str) -> &str {
+    let digits = word.len() - word.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &word[digits..];
+    if digits == word.len() {
+        ""
+    } else if digits > 0 && rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else {
+        word
+    }
+}
+
+/// Whether the movetext has reached its terminating result outside any comment or variation
+fn movetext_complete(movetext: &str) -> bool {
+    let mut depth = 0;
+    for token in tokenize(movetext) {
+        match token {
+            Token::VariationStart => depth += 1,
+            Token::VariationEnd => depth -= 1,
+            Token::Result(_) if depth <= 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Parse a tag pair line such as `[White "Kasparov, Garry"]`
+fn parse_tag(line: &str) -> Result<(String, String), String> {
+    let inner = line
+        .trim()
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .ok_or_else(|| format!("Malformed tag pair: {}", line))?;
+    let (name, value) = inner
+        .split_once(' ')
+        .ok_or_else(|| format!("Malformed tag pair: {}", line))?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| format!("Malformed tag value: {}", line))?;
+
+    Ok((
+        name.to_string(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+/// A line of moves being read, the mainline or a side-line
+struct Line {
+    /// The first position of the line
+    start: Option<NodeId>,
+    /// The last move read and the position after it
+    node: Option<NodeId>,
+    board: Board,
+    /// The position before the last move, where a side-line to it starts
+    before: (Option<NodeId>, Board),
+    /// A comment read before the line's first move
+    starting_comment: Option<String>,
+}
+
+impl Line {
+    fn new(node: Option<NodeId>, board: Board) -> Self {
+        Self {
+            start: node,
+            node,
+            board,
+            before: (node, board),
+            starting_comment: None,
+        }
+    }
+
+    /// The last move read in this line, `None` before its first
+    fn last_move(&self) -> Option<NodeId> {
+        self.node.filter(|_| self.node != self.start)
+    }
+}
+
+/// Add `text` to a comment, after any text already there
+fn append_comment(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&text);
+        }
+        None => *comment = Some(text),
+    }
+}
+
+/// Build a game from its tag pairs and movetext, replaying the moves for validation
+fn build_game(tags: Vec<(String, String)>, movetext: &str) -> Result<Game, String> {
+    let fen = tags
+        .iter()
+        .find(|(name, _)| name == "FEN")
+        .map(|(_, value)| value.as_str())
+        .unwrap_or(START_FEN);
+    let initial_board = parse_fen(fen)?;
+
+    let mut moves = Vec::new();
+    let mut comments = Vec::new();
+    let mut tree = VariationTree::new();
+    let mut result = String::from("*");
+    // The line being read, with the lines it branches from under it
+    let mut lines = vec![Line::new(None, initial_board)];
+
+    for token in tokenize(movetext) {
+        let mainline = lines.len() == 1;
+        let line = lines.last_mut().expect("the mainline is never closed");
+        match token {
+            // A side-line replaces the last move of the line it branches from
+            Token::VariationStart => {
+                let (node, board) = line.before;
+                lines.push(Line::new(node, board));
+            }
+            Token::VariationEnd => {
+                if lines.len() > 1 {
+                    lines.pop();
+                }
+            }
+            Token::Comment(text) => {
+                if mainline {
+                    comments.push((moves.len(), text.clone()));
+                }
+                let comment = match line.last_move() {
+                    Some(id) => &mut tree.node_mut(id).comment,
+                    None => &mut line.starting_comment,
+                };
+                append_comment(comment, text);
+            }
+            Token::Nag(nag) => {
+                if let Some(id) = line.last_move() {
+                    tree.node_mut(id).nags.push(nag);
+                }
+            }
+            Token::Result(token) if mainline => {
+                result = token;
+                break;
+            }
+            Token::Result(_) => {}
+            Token::Move(san) => {
+                let mv = MoveParser::new(&line.board).parse(&san).map_err(|e| {
+                    let ply = tree.path(line.node).len() + 1;
+                    format!("{} '{}' at ply {}", e, san, ply)
+                })?;
+                let id = tree.add(line.node, MoveRecord::new(&line.board, mv));
+                if let Some(comment) = line.starting_comment.take() {
+                    tree.node_mut(id).starting_comment = Some(comment);
+                }
+                line.before = (line.node, line.board);
+                line.node = Some(id);
+                line.board.make_move(&mv);
+                if mainline {
+                    moves.push(mv);
+                }
+            }
+        }
+    }
+
+    Ok(Game {
+        tags,
+        initial_board,
+        moves,
+        comments,
+        tree,
+        result,
+    })
+}
+
+/// Streaming PGN reader yielding one game at a time
+pub struct PgnReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut tags = Vec::new();
+        let mut movetext = String::new();
+
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(format!("Error reading PGN: {}", e))),
+            };
+            let trimmed = line.trim();
+
+            // Escape lines are ignored per the PGN standard
+            if trimmed.starts_with('%') {
+                continue;
+            }
+
+            if movetext.trim().is_empty() && trimmed.starts_with('[') {
+                match parse_tag(trimmed) {
+                    Ok(tag) => tags.push(tag),
+                    Err(e) => return Some(Err(e)),
+                }
+                continue;
+            }
+
+            movetext.push_str(&line);
+            movetext.push('\n');
+            if movetext_complete(&movetext) {
+                return Some(build_game(tags, &movetext));
+            }
+        }
+
+        // End of input: flush a trailing game without a result token
+        if tags.is_empty() && movetext.trim().is_empty() {
+            None
+        } else {
+            Some(build_game(tags, &movetext))
+        }
+    }
+}
+
+/// Parse every game in a PGN document
+pub fn parse_games(pgn: &str) -> Result<Vec<Game>, String> {
+    PgnReader::new(pgn.as_bytes()).collect()
+}
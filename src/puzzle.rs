@@ -0,0 +1,237 @@
+use crate::board::Board;
+use crate::moves::Move;
+use crate::notation::MoveParser;
+use crate::piece::Color;
+use crate::render::{BoardStyle, render_board};
+use crate::search::{Search, SearchLimits};
+use std::io::{self, BufRead, Write};
+
+/// A few classic tactics for `oxm8 puzzles` without a file, in EPD
+pub const BUILTIN_PUZZLES: &str = r#"
+r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - bm Qxf7#; id "Scholar's mate";
+6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd8#; id "Back rank";
+r3k3/8/8/3N4/8/8/4P3/4K3 w - - bm Nc7+; id "Knight fork";
+4k3/p7/8/3q4/8/2N5/P7/4K3 w - - bm Nxd5; id "Hanging queen";
+6k1/5ppp/8/8/8/8/q4PPP/1R4K1 w - - bm Rb8#; id "Back rank II";
+"#;
+
+/// Score from the solver's side from which a move counts as winning
+const WINNING_SCORE: i32 = 300;
+
+/// A position with the best moves for the side to move
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub board: Board,
+    pub solutions: Vec<Move>,
+    pub id: Option<String>,
+}
+
+/// How an answer to a puzzle was judged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// One of the listed solutions
+    Solution,
+    /// Not listed, but the confirmation search finds it winning as well
+    AlsoWins,
+    Wrong,
+}
+
+fn side_to_move(board: &Board) -> Color {
+    if board.to_move {
+        Color::White
+    } else {
+        Color::Black
+    }
+}
+
+/// A legal move written in UCI ("e7e8q"), SAN ("e8=Q+") or long algebraic ("e7-e8=Q")
+pub fn parse_move(board: &Board, text: &str) -> Option<Move> {
+    MoveParser::new(board).parse(text).ok()
+}
+
+fn parse_solutions(board: &Board, moves: &str) -> Result<Vec<Move>, String> {
+    let solutions = moves
+        .split_whitespace()
+        .map(|text| parse_move(board, text).ok_or(format!("illegal solution '{}'", text)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if solutions.is_empty() {
+        return Err("no solution given".to_string());
+    }
+    Ok(solutions)
+}
+
+impl Puzzle {
+    /// An EPD record: four FEN fields, then operations such as `bm Qxf7#; id "name";`
+    pub fn from_epd(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.splitn(5, ' ').collect();
+        if fields.len() < 5 {
+            return Err(format!("not an EPD record: '{}'", line));
+        }
+        let (mut moves, mut id) = (None, None);
+        let (mut halfmove, mut fullmove) = ("0", "1");
+        for operation in fields[4].split(';').map(str::trim) {
+            match operation.split_once(' ') {
+                Some(("bm", value)) => moves = Some(value),
+                Some(("id", value)) => id = Some(value.trim_matches('"').to_string()),
+                Some(("hmvc", value)) => halfmove = value,
+                Some(("fmvn", value)) => fullmove = value,
+                _ => {}
+            }
+        }
+        let fen = format!("{} {} {}", fields[..4].join(" "), halfmove, fullmove);
+        let board = Board::try_from_fen(&fen).map_err(|e| e.to_string())?;
+        let moves = moves.ok_or(format!("no 'bm' operation in '{}'", line))?;
+        Ok(Self {
+            solutions: parse_solutions(&board, moves)?,
+            board,
+            id,
+        })
+    }
+
+    /// A CSV row: the FEN, then the best moves separated by spaces, then an optional id
+    pub fn from_csv(line: &str) -> Result<Self, String> {
+        let mut columns = line.split(',').map(str::trim);
+        let fen = columns.next().unwrap_or_default();
+        let board = Board::try_from_fen(fen).map_err(|e| e.to_string())?;
+        let moves = columns.next().ok_or(format!("no solution in '{}'", line))?;
+        Ok(Self {
+            solutions: parse_solutions(&board, moves)?,
+            board,
+            id: columns
+                .next()
+                .filter(|id| !id.is_empty())
+                .map(str::to_string),
+        })
+    }
+
+    /// Judge `answer`, confirming unlisted moves with a search within `limits`. Another
+    /// move is accepted if it wins, or scores as well as the solution when that does not
+    pub fn check(&self, answer: &Move, limits: SearchLimits) -> Verdict {
+        if self.solutions.contains(answer) {
+            return Verdict::Solution;
+        }
+        let mut search = Search::default();
+        let mut score_after = |mv: &Move| {
+            let mut board = self.board;
+            board.make_move(mv);
+            // The opponent's best reply, seen from the solver's side
+            -search.go(&board, limits).score
+        };
+        let best = score_after(&self.solutions[0]);
+        let score = score_after(answer);
+        if score >= best.min(WINNING_SCORE) {
+            Verdict::AlsoWins
+        } else {
+            Verdict::Wrong
+        }
+    }
+}
+
+/// Read puzzles one per line, as CSV when the line has a comma and EPD otherwise.
+/// Blank lines and lines starting with `#` are skipped
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, String> {
+    text.lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            let puzzle = if line.contains(',') {
+                Puzzle::from_csv(line)
+            } else {
+                Puzzle::from_epd(line)
+            };
+            puzzle.map_err(|e| format!("line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Puzzles solved so far and the current run of correct answers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PuzzleScore {
+    pub solved: usize,
+    pub attempted: usize,
+    pub streak: usize,
+    pub best_streak: usize,
+}
+
+impl PuzzleScore {
+    fn record(&mut self, correct: bool) {
+        self.attempted += 1;
+        if correct {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+}
+
+/// Present each puzzle in turn, reading one answer per puzzle from `input`. `skip`
+/// reveals the solution and `quit` stops early
+pub fn run_puzzles(
+    puzzles: &[Puzzle],
+    limits: SearchLimits,
+    style: BoardStyle,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<PuzzleScore> {
+    let out: &mut dyn Write = &mut output;
+    let mut score = PuzzleScore::default();
+
+    'puzzles: for (number, puzzle) in puzzles.iter().enumerate() {
+        let title = puzzle.id.as_deref().unwrap_or("Find the best move");
+        writeln!(
+            out,
+            "\n🧩 Puzzle {}/{}: {}",
+            number + 1,
+            puzzles.len(),
+            title
+        )?;
+        let flipped = !puzzle.board.to_move;
+        writeln!(out, "{}", render_board(&puzzle.board, style, flipped))?;
+        let solution = puzzle.solutions[0].to_san(&puzzle.board);
+
+        loop {
+            write!(out, "{} to move: ", side_to_move(&puzzle.board))?;
+            out.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                break 'puzzles;
+            }
+            let answer = match line.trim() {
+                "" => continue,
+                "quit" | "q" => break 'puzzles,
+                "skip" => {
+                    writeln!(out, "The solution was {}.", solution)?;
+                    score.record(false);
+                    break;
+                }
+                text => match parse_move(&puzzle.board, text) {
+                    Some(mv) => mv,
+                    None => {
+                        writeln!(out, "❌ '{}' is not a legal move here.", text)?;
+                        continue;
+                    }
+                },
+            };
+
+            let verdict = puzzle.check(&answer, limits);
+            match verdict {
+                Verdict::Solution => writeln!(out, "✅ Correct!")?,
+                Verdict::AlsoWins => {
+                    writeln!(out, "✅ That wins too! The intended move was {}.", solution)?
+                }
+                Verdict::Wrong => writeln!(out, "❌ Not quite, the solution was {}.", solution)?,
+            }
+            score.record(verdict != Verdict::Wrong);
+            break;
+        }
+        writeln!(
+            out,
+            "Solved {}/{}, streak {} (best {})",
+            score.solved, score.attempted, score.streak, score.best_streak
+        )?;
+    }
+    Ok(score)
+}
@@ -0,0 +1,311 @@
+use crate::board::Board;
+use crate::piece::{Color, piece_to_char, piece_to_sp_char};
+use std::io::IsTerminal;
+
+/// Square and piece colours of a coloured board, as xterm 256-colour palette indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub light: u8,
+    pub dark: u8,
+    pub white_piece: u8,
+    pub black_piece: u8,
+    /// Background of the last move's from and to squares
+    pub last_move: u8,
+    /// Destination dots and the background of capturable pieces
+    pub target: u8,
+}
+
+/// Built-in themes, the first being the default
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "wood",
+        light: 180,
+        dark: 137,
+        white_piece: 231,
+        black_piece: 16,
+        last_move: 143,
+        target: 167,
+    },
+    Theme {
+        name: "green",
+        light: 187,
+        dark: 65,
+        white_piece: 231,
+        black_piece: 16,
+        last_move: 149,
+        target: 167,
+    },
+    Theme {
+        name: "blue",
+        light: 153,
+        dark: 67,
+        white_piece: 231,
+        black_piece: 16,
+        last_move: 110,
+        target: 167,
+    },
+    Theme {
+        name: "gray",
+        light: 250,
+        dark: 243,
+        white_piece: 231,
+        black_piece: 16,
+        last_move: 144,
+        target: 167,
+    },
+];
+
+impl Theme {
+    pub fn by_name(name: &str) -> Option<&'static Theme> {
+        THEMES
+            .iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// How a board is drawn in the terminal: Unicode or ASCII pieces, with coloured squares
+/// when a theme is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardStyle {
+    pub unicode: bool,
+    pub theme: Option<&'static Theme>,
+}
+
+impl BoardStyle {
+    /// Letters and dots in an ASCII frame, readable on any terminal
+    pub const ASCII: Self = Self {
+        unicode: false,
+        theme: None,
+    };
+
+    /// Piece symbols in a box-drawing frame, without colours
+    pub const UNICODE: Self = Self {
+        unicode: true,
+        theme: None,
+    };
+
+    pub fn themed(theme: &'static Theme) -> Self {
+        Self {
+            unicode: true,
+            theme: Some(theme),
+        }
+    }
+
+    /// The richest style stdout supports: colours only on a terminal that is not
+    /// `TERM=dumb` and without `NO_COLOR`, Unicode only with a UTF-8 locale
+    pub fn detect() -> Self {
+        let var = |key: &str| std::env::var(key).unwrap_or_default();
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .map(var)
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_uppercase();
+        let unicode = cfg!(windows) || locale.contains("UTF-8") || locale.contains("UTF8");
+        let color = std::io::stdout().is_terminal()
+            && std::env::var_os("NO_COLOR").is_none()
+            && var("TERM") != "dumb";
+        Self {
+            unicode,
+            theme: color.then_some(&THEMES[0]),
+        }
+    }
+}
+
+impl Default for BoardStyle {
+    fn default() -> Self {
+        Self::UNICODE
+    }
+}
+
+/// Squares to draw attention to, as bitboards
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Highlights {
+    /// From and to squares of the last move, only shown with a theme
+    pub last_move: u64,
+    /// Legal destinations of a selected piece
+    pub targets: u64,
+}
+
+/// How [`ascii`] draws a board: the piece style, whether rank and file labels frame
+/// it, whose side is at the bottom and which squares stand out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub style: BoardStyle,
+    pub coordinates: bool,
+    /// The side seen from, whose first rank is at the bottom
+    pub perspective: Color,
+    pub highlights: Highlights,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self::new(BoardStyle::default())
+    }
+}
+
+impl RenderOptions {
+    /// `style` with coordinates, from White's side and nothing highlighted
+    pub fn new(style: BoardStyle) -> Self {
+        Self {
+            style,
+            coordinates: true,
+            perspective: Color::White,
+            highlights: Highlights::default(),
+        }
+    }
+
+    pub fn with_coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    pub fn with_perspective(mut self, perspective: Color) -> Self {
+        self.perspective = perspective;
+        self
+    }
+
+    pub fn with_highlights(mut self, highlights: Highlights) -> Self {
+        self.highlights = highlights;
+        self
+    }
+}
+
+/// Draw `board` with rank and file labels, from Black's side when `flipped`
+pub fn render_board(board: &Board, style: BoardStyle, flipped: bool) -> String {
+    render_highlighted(board, style, flipped, Highlights::default())
+}
+
+/// Like [`render_board`], marking the highlighted squares
+pub fn render_highlighted(
+    board: &Board,
+    style: BoardStyle,
+    flipped: bool,
+    highlights: Highlights,
+) -> String {
+    let perspective = if flipped { Color::Black } else { Color::White };
+    ascii(
+        board,
+        &RenderOptions::new(style)
+            .with_perspective(perspective)
+            .with_highlights(highlights),
+    )
+}
+
+/// Draw `board` as text, one line per rank, for printing or embedding in another UI.
+/// Themed styles colour the squares with ANSI escapes. Without a theme, highlighted
+/// targets are dots on empty squares and a dot after a capturable piece
+pub fn ascii(board: &Board, options: &RenderOptions) -> String {
+    let RenderOptions {
+        style,
+        coordinates,
+        highlights,
+        ..
+    } = *options;
+    // White's view has rank 8 at the top and the a-file on the left
+    let ranks: Vec<u8> = match options.perspective {
+        Color::White => (0..8).rev().collect(),
+        Color::Black => (0..8).collect(),
+    };
+    let files: Vec<u8> = ranks.iter().map(|rank| 7 - rank).collect();
+
+    let labels: String = files
+        .iter()
+        .map(|&file| match style.theme {
+            Some(_) => format!("{:^3}", (b'a' + file) as char),
+            None => format!(" {}", (b'a' + file) as char),
+        })
+        .collect();
+    let margin = if coordinates { "  " } else { "" };
+    let (top, bottom, side) = match (style.theme, style.unicode) {
+        (Some(_), _) => (String::new(), String::new(), ""),
+        (None, true) => (
+            format!("{}┌{}┐\n", margin, "─".repeat(17)),
+            format!("{}└{}┘\n", margin, "─".repeat(17)),
+            "│",
+        ),
+        (None, false) => (
+            format!("{}+{}+\n", margin, "-".repeat(17)),
+            format!("{}+{}+\n", margin, "-".repeat(17)),
+            "|",
+        ),
+    };
+
+    let mut out = String::from("\n");
+    if coordinates {
+        out += &format!("  {}\n", labels);
+    }
+    out += &top;
+    for &rank in &ranks {
+        if coordinates {
+            out += &format!("{} ", rank + 1);
+        }
+        if style.theme.is_none() {
+            out += &format!("{} ", side);
+        }
+        for &file in &files {
+            let square = rank * 8 + file;
+            let piece = board.get_piece_at(square);
+            let is_target = highlights.targets & (1 << square) != 0;
+            let dot = if style.unicode { '•' } else { '*' };
+            let Some(theme) = style.theme else {
+                let symbol = match piece {
+                    Some((piece, color)) if style.unicode => piece_to_sp_char(piece, color),
+                    Some((piece, color)) => piece_to_char(piece, color),
+                    None if is_target => dot,
+                    None if style.unicode => '·',
+                    None => '.',
+                };
+                let after = if is_target && piece.is_some() {
+                    dot
+                } else {
+                    ' '
+                };
+                out += &format!("{}{}", symbol, after);
+                continue;
+            };
+
+            // a1 is a dark square
+            let background = if is_target && piece.is_some() {
+                theme.target
+            } else if highlights.last_move & (1 << square) != 0 {
+                theme.last_move
+            } else if (rank + file) % 2 == 0 {
+                theme.dark
+            } else {
+                theme.light
+            };
+            let (symbol, foreground) = match piece {
+                // The filled glyphs read better on coloured squares for both sides
+                Some((piece, color)) => (
+                    if style.unicode {
+                        piece_to_sp_char(piece, Color::Black)
+                    } else {
+                        piece_to_char(piece, color)
+                    },
+                    match color {
+                        Color::White => theme.white_piece,
+                        Color::Black => theme.black_piece,
+                    },
+                ),
+                None if is_target => ('•', theme.target),
+                None => (' ', theme.black_piece),
+            };
+            out += &format!(
+                "\x1b[48;5;{}m\x1b[38;5;{}m {} \x1b[0m",
+                background, foreground, symbol
+            );
+        }
+        out += side;
+        if coordinates {
+            out += &format!(" {}", rank + 1);
+        }
+        out += "\n";
+    }
+    out += &bottom;
+    if coordinates {
+        out += &format!("  {}\n", labels);
+    }
+    out
+}
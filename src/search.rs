@@ -0,0 +1,1431 @@
+use crate::board::Board;
+use crate::eval::{Eval, MATE_SCORE, Wdl, is_mate_score, mate_in};
+use crate::moves::{Move, MoveType};
+use crate::params::EvalParams;
+use crate::piece::{Color, Piece};
+use crate::tablebase::{self, Tablebase};
+use crate::tt::TranspositionTable;
+use alloc::sync::Arc;
+use alloc::{boxed::Box, format, string::String, vec, vec::Vec};
+#[cfg(not(feature = "std"))]
+use clock::Instant;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::thread;
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+use std::time::Instant;
+// std's clock panics on wasm32-unknown-unknown
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+/// Without std there is no clock to read, so time never passes and only depth and node
+/// limits end a search
+#[cfg(not(feature = "std"))]
+mod clock {
+    use core::ops::Add;
+    use core::time::Duration;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Instant(Duration);
+
+    impl Instant {
+        pub fn now() -> Self {
+            Instant(Duration::ZERO)
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::ZERO
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Self;
+
+        fn add(self, time: Duration) -> Self {
+            Instant(self.0 + time)
+        }
+    }
+}
+
+/// Butterfly table scoring quiet moves by how often they caused beta cutoffs
+pub struct HistoryTable {
+    scores: Box<[[i32; 64]; 64]>,
+}
+
+impl HistoryTable {
+    const MAX_SCORE: i32 = 1 << 20;
+
+    pub fn new() -> Self {
+        Self {
+            scores: Box::new([[0; 64]; 64]),
+        }
+    }
+
+    pub fn score(&self, mv: &Move) -> i32 {
+        self.scores[mv.from as usize][mv.to as usize]
+    }
+
+    /// Reward a quiet move that caused a beta cutoff at the given depth
+    pub fn update(&mut self, mv: &Move, depth: u8) {
+        let entry = &mut self.scores[mv.from as usize][mv.to as usize];
+        *entry += depth as i32 * depth as i32;
+
+        // Halve everything once a score grows too large so older results fade
+        if *entry > Self::MAX_SCORE {
+            for row in self.scores.iter_mut() {
+                for score in row.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for row in self.scores.iter_mut() {
+            row.fill(0);
+        }
+    }
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A moved piece and its destination, the index of the continuation tables
+pub type PieceTo = (Piece, u8);
+
+/// The piece `mv` moves on `board` and its destination, before the move is made
+pub fn piece_to(board: &Board, mv: &Move) -> PieceTo {
+    let (piece, _) = board
+        .get_piece_at(mv.from)
+        .expect("No piece at from square");
+    (piece, mv.to)
+}
+
+/// Scores of quiet moves by the move played some plies before them, both indexed by
+/// piece and destination. Whose moves they are follows from the distance between them.
+/// Scores move towards a bonus or a malus and stay within `±MAX_SCORE`
+pub struct ContinuationHistory {
+    scores: Box<[i32]>,
+}
+
+impl ContinuationHistory {
+    pub const MAX_SCORE: i32 = 1 << 14;
+
+    pub fn new() -> Self {
+        Self {
+            scores: vec![0; 384 * 384].into_boxed_slice(),
+        }
+    }
+
+    fn index(previous: PieceTo, current: PieceTo) -> usize {
+        let slot = |(piece, to): PieceTo| piece as usize * 64 + to as usize;
+        slot(previous) * 384 + slot(current)
+    }
+
+    pub fn score(&self, previous: PieceTo, current: PieceTo) -> i32 {
+        self.scores[Self::index(previous, current)]
+    }
+
+    /// Move the score towards `bonus`, a negative bonus being a malus. The closer the
+    /// score already is to the bound, the smaller the step
+    pub fn update(&mut self, previous: PieceTo, current: PieceTo, bonus: i32) {
+        let bonus = bonus.clamp(-Self::MAX_SCORE, Self::MAX_SCORE);
+        let entry = &mut self.scores[Self::index(previous, current)];
+        *entry += bonus - *entry * bonus.abs() / Self::MAX_SCORE;
+    }
+
+    pub fn clear(&mut self) {
+        self.scores.fill(0);
+    }
+}
+
+impl Default for ContinuationHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counters describing how a search went, for engine development. They are only
+/// collected with the `stats` feature and stay zero otherwise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub nodes: u64,
+    /// Nodes whose score fell inside the window
+    pub pv_nodes: u64,
+    /// Nodes that failed high
+    pub cut_nodes: u64,
+    /// Nodes that failed low
+    pub all_nodes: u64,
+    pub tt_probes: u64,
+    /// Probes that found an entry for the position
+    pub tt_hits: u64,
+    /// Beta cutoffs by the index of the move that caused them, the last bucket
+    /// counting every later move
+    pub cutoffs: [u64; SearchStats::CUTOFF_BUCKETS],
+    /// Nodes scored by the static evaluation. There is no quiescence search, so these
+    /// are the search's leaves
+    pub leaf_nodes: u64,
+    pub futility_prunes: u64,
+    pub history_prunes: u64,
+    pub mate_distance_prunes: u64,
+}
+
+impl SearchStats {
+    pub const CUTOFF_BUCKETS: usize = 8;
+
+    /// Share of probes that found an entry, from 0 to 1
+    pub fn tt_hit_rate(&self) -> f64 {
+        self.tt_hits as f64 / self.tt_probes.max(1) as f64
+    }
+
+    /// Share of beta cutoffs caused by the first move searched, from 0 to 1
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        let total: u64 = self.cutoffs.iter().sum();
+        self.cutoffs[0] as f64 / total.max(1) as f64
+    }
+
+    /// Share of the nodes that were leaves, from 0 to 1
+    pub fn leaf_ratio(&self) -> f64 {
+        self.leaf_nodes as f64 / self.nodes.max(1) as f64
+    }
+}
+
+impl core::ops::AddAssign for SearchStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.pv_nodes += other.pv_nodes;
+        self.cut_nodes += other.cut_nodes;
+        self.all_nodes += other.all_nodes;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+        for (total, count) in self.cutoffs.iter_mut().zip(other.cutoffs) {
+            *total += count;
+        }
+        self.leaf_nodes += other.leaf_nodes;
+        self.futility_prunes += other.futility_prunes;
+        self.history_prunes += other.history_prunes;
+        self.mate_distance_prunes += other.mate_distance_prunes;
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Nodes         : {}", self.nodes)?;
+        writeln!(
+            f,
+            "Node types    : {} pv, {} cut, {} all",
+            self.pv_nodes, self.cut_nodes, self.all_nodes
+        )?;
+        writeln!(
+            f,
+            "TT hits       : {}/{} ({:.1}%)",
+            self.tt_hits,
+            self.tt_probes,
+            self.tt_hit_rate() * 100.0
+        )?;
+        writeln!(
+            f,
+            "Cutoff index  : {:?} ({:.1}% on the first move)",
+            self.cutoffs,
+            self.first_move_cutoff_rate() * 100.0
+        )?;
+        writeln!(f, "Leaf nodes    : {:.1}%", self.leaf_ratio() * 100.0)?;
+        write!(
+            f,
+            "Pruned        : {} futility, {} history, {} mate distance",
+            self.futility_prunes, self.history_prunes, self.mate_distance_prunes
+        )
+    }
+}
+
+/// Per-thread search state. The transposition table and stop flag may be shared
+/// with other threads searching the same position
+pub struct SearchContext {
+    pub tt: Arc<TranspositionTable>,
+    pub history: HistoryTable,
+    /// Quiet move scores following the previous move and the one before it
+    pub continuation: [ContinuationHistory; 2],
+    /// Piece and destination of each move on the current search path
+    pub played: Vec<PieceTo>,
+    pub stats: SearchStats,
+    /// Distance to mate tables probed in small endings
+    pub tablebase: Option<Arc<dyn Tablebase>>,
+    /// Weights of the static evaluation at the leaves
+    pub eval_params: EvalParams,
+    pub nodes: u64,
+    /// Distance from the root of the node being searched
+    pub ply: u8,
+    /// Deepest ply reached so far
+    pub seldepth: u8,
+    pub stop: Arc<AtomicBool>,
+    /// Time at which the search must stop
+    pub deadline: Option<Instant>,
+    /// Node budget for this thread
+    pub max_nodes: Option<u64>,
+    /// Root moves given exact scores and reported after each iteration, at least one
+    pub multi_pv: usize,
+    /// Hashes of the game's earlier positions followed by those on the current search
+    /// path, oldest first. The node being searched is not included
+    pub path: Vec<u64>,
+}
+
+impl SearchContext {
+    pub fn new(hash_mb: usize) -> Self {
+        Self::with_shared(
+            Arc::new(TranspositionTable::new(hash_mb)),
+            Arc::new(AtomicBool::new(false)),
+        )
+    }
+
+    pub fn with_shared(tt: Arc<TranspositionTable>, stop: Arc<AtomicBool>) -> Self {
+        Self {
+            tt,
+            history: HistoryTable::new(),
+            continuation: [ContinuationHistory::new(), ContinuationHistory::new()],
+            played: Vec::new(),
+            stats: SearchStats::default(),
+            tablebase: None,
+            eval_params: EvalParams::DEFAULT,
+            nodes: 0,
+            ply: 0,
+            seldepth: 0,
+            stop,
+            deadline: None,
+            max_nodes: None,
+            multi_pv: 1,
+            path: Vec::new(),
+        }
+    }
+
+    /// Whether the position with hash `key` and the given halfmove clock occurred
+    /// before on the path, with the same side to move and no capture or pawn move since
+    pub fn is_repetition(&self, key: u64, halfmove_clock: u16) -> bool {
+        self.path
+            .iter()
+            .rev()
+            .take(halfmove_clock as usize)
+            .skip(1)
+            .step_by(2)
+            .any(|&hash| hash == key)
+    }
+
+    /// Continuation history score of a quiet move on `board` after the moves on the
+    /// search path, the sum over both tables
+    pub fn continuation_score(&self, board: &Board, mv: &Move) -> i32 {
+        let current = piece_to(board, mv);
+        self.continuation
+            .iter()
+            .zip(self.played.iter().rev())
+            .map(|(table, &previous)| table.score(previous, current))
+            .sum()
+    }
+
+    /// Ordering score of a quiet move: its history and continuation history scores
+    pub fn quiet_score(&self, board: &Board, mv: &Move) -> i32 {
+        self.history.score(mv) + self.continuation_score(board, mv)
+    }
+
+    /// Reward `best`, a quiet move that caused a cutoff at `depth` on `board`, and
+    /// penalise the quiet moves in `tried` searched before it without one
+    pub fn reward_quiet(&mut self, board: &Board, best: &Move, tried: &[Move], depth: u8) {
+        self.history.update(best, depth);
+        let bonus = (depth as i32 * depth as i32 * 32).min(ContinuationHistory::MAX_SCORE / 4);
+        for (table, &previous) in self.continuation.iter_mut().zip(self.played.iter().rev()) {
+            table.update(previous, piece_to(board, best), bonus);
+            for mv in tried.iter().filter(|mv| is_quiet(mv)) {
+                table.update(previous, piece_to(board, mv), -bonus);
+            }
+        }
+    }
+
+    /// Whether the search has been asked to stop
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Raise the stop flag once the node or time budget is spent. The clock is only
+    /// read every 1024 nodes to keep the check cheap
+    pub fn check_limits(&self) {
+        let out_of_nodes = self.max_nodes.is_some_and(|max| self.nodes >= max);
+        let out_of_time = self.nodes.is_multiple_of(1024)
+            && self.deadline.is_some_and(|deadline| {
+                let expired = Instant::now() >= deadline;
+                #[cfg(feature = "tracing")]
+                tracing::trace!(nodes = self.nodes, expired, "time check");
+                expired
+            });
+        if out_of_nodes || out_of_time {
+            #[cfg(feature = "tracing")]
+            if !self.stopped() {
+                tracing::debug!(
+                    nodes = self.nodes,
+                    out_of_nodes,
+                    out_of_time,
+                    "search limit reached"
+                );
+            }
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for SearchContext {
+    fn default() -> Self {
+        Self::with_shared(
+            Arc::new(TranspositionTable::default()),
+            Arc::new(AtomicBool::new(false)),
+        )
+    }
+}
+
+/// Whether a move neither captures nor promotes
+pub fn is_quiet(mv: &Move) -> bool {
+    !mv.is_capture() && !mv.is_promotion()
+}
+
+/// Sort moves so the most promising come first: the hash move, then captures and
+/// promotions by most valuable victim / least valuable attacker, then quiet moves
+/// by history score
+pub fn order_moves(
+    board: &Board,
+    moves: &mut [Move],
+    tt_move: Option<Move>,
+    history: &HistoryTable,
+) {
+    order_moves_by(board, moves, tt_move, |mv| history.score(mv));
+}
+
+/// Like [`order_moves`], ranking quiet moves by `quiet_score`, which must stay below
+/// `4 * HistoryTable::MAX_SCORE` to keep them after the captures
+pub fn order_moves_by(
+    board: &Board,
+    moves: &mut [Move],
+    tt_move: Option<Move>,
+    quiet_score: impl Fn(&Move) -> i32,
+) {
+    moves.sort_by_cached_key(|mv| {
+        if Some(*mv) == tt_move {
+            return i32::MIN;
+        }
+        if is_quiet(mv) {
+            return -quiet_score(mv);
+        }
+
+        let victim = match mv.move_type {
+            MoveType::EnPassant => Eval::match_piece_value(crate::piece::Piece::Pawn),
+            _ => board
+                .get_piece_at(mv.to)
+                .map_or(0, |(piece, _)| Eval::match_piece_value(piece)),
+        };
+        let promotion = match mv.move_type {
+            MoveType::Promotion { piece } | MoveType::PromotionCapture { piece } => {
+                Eval::match_piece_value(piece)
+            }
+            _ => 0,
+        };
+        let attacker = board
+            .get_piece_at(mv.from)
+            .map_or(0, |(piece, _)| Eval::match_piece_value(piece));
+
+        -(HistoryTable::MAX_SCORE * 4 + (victim + promotion) * 16 - attacker)
+    });
+}
+
+/// Configuration for a [`Search`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Transposition table size in megabytes
+    pub hash_mb: usize,
+    /// Number of search threads (Lazy SMP when greater than one)
+    pub threads: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            hash_mb: 16,
+            threads: 1,
+        }
+    }
+}
+
+/// When a search should stop. Unset limits are unbounded; with no limits at all the
+/// search runs to [`SearchLimits::MAX_DEPTH`] unless stopped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Maximum depth in plies
+    pub depth: Option<u8>,
+    /// Maximum nodes searched by the main thread
+    pub nodes: Option<u64>,
+    /// Maximum wall-clock time
+    pub movetime: Option<Duration>,
+    /// Look for a mate in this many moves with [`Search::find_mate`] first, falling back
+    /// to a normal search limited to that depth when there is none. The mate search
+    /// gets half of the time and nodes
+    pub mate: Option<u8>,
+    /// Ignore depth limits and search until stopped
+    pub infinite: bool,
+    /// Best root moves given exact scores and reported after each iteration, one when
+    /// unset
+    pub multi_pv: Option<usize>,
+}
+
+impl SearchLimits {
+    pub const MAX_DEPTH: u8 = 64;
+
+    pub fn fixed_depth(depth: u8) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    pub fn node_limit(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Self::default()
+        }
+    }
+
+    pub fn move_time(time: Duration) -> Self {
+        Self {
+            movetime: Some(time),
+            ..Self::default()
+        }
+    }
+
+    pub fn mate_in(moves: u8) -> Self {
+        Self {
+            mate: Some(moves),
+            ..Self::default()
+        }
+    }
+
+    pub fn infinite() -> Self {
+        Self {
+            infinite: true,
+            ..Self::default()
+        }
+    }
+
+    /// Deepest iteration allowed by these limits. A mate in N needs at most 2N - 1 plies
+    pub fn max_depth(&self) -> u8 {
+        if self.infinite {
+            return Self::MAX_DEPTH;
+        }
+        let mate_depth = self
+            .mate
+            .map(|moves| moves.saturating_mul(2).saturating_sub(1).max(1));
+        match (self.depth, mate_depth) {
+            (Some(depth), Some(mate)) => depth.min(mate),
+            (Some(depth), None) => depth,
+            (None, Some(mate)) => mate,
+            (None, None) => Self::MAX_DEPTH,
+        }
+        .clamp(1, Self::MAX_DEPTH)
+    }
+}
+
+/// A search score split into centipawns or a forced mate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    /// Full moves to mate, negative when the side to move is getting mated
+    Mate(i32),
+}
+
+impl From<i32> for Score {
+    fn from(score: i32) -> Self {
+        match mate_in(score) {
+            Some(moves) => Score::Mate(moves),
+            None => Score::Centipawns(score),
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    /// Formats as in UCI `info` lines, e.g. `cp 35` or `mate -2`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Centipawns(cp) => write!(f, "cp {}", cp),
+            Score::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+/// Outcome of a root search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Best move found, `None` when the side to move has no legal moves
+    pub best_move: Option<Move>,
+    /// Score relative to the side to move
+    pub score: i32,
+    /// Deepest fully completed iteration
+    pub depth: u8,
+    /// Nodes searched by all threads
+    pub nodes: u64,
+}
+
+impl SearchResult {
+    /// The score as centipawns or moves to mate
+    pub fn score(&self) -> Score {
+        Score::from(self.score)
+    }
+}
+
+/// A forced mate proven by [`Search::find_mate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mate {
+    /// Mating line against the longest defence, ending in checkmate
+    pub line: Vec<Move>,
+    pub nodes: u64,
+}
+
+impl Mate {
+    /// Full moves until checkmate
+    pub fn moves(&self) -> usize {
+        self.line.len().div_ceil(2)
+    }
+}
+
+/// Progress report sent to a [`SearchObserver`] after each completed iteration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: u8,
+    /// Rank of the line among the root moves, 1 for the best. Only searches with
+    /// [`SearchLimits::multi_pv`] report lines after the first
+    pub multipv: usize,
+    pub seldepth: u8,
+    /// Nodes searched by the main thread so far
+    pub nodes: u64,
+    /// Nodes per second
+    pub nps: u64,
+    /// Transposition table occupancy in permille
+    pub hashfull: u16,
+    /// Score relative to the side to move
+    pub score: i32,
+    /// Principal variation, starting with the best move
+    pub pv: Vec<Move>,
+    pub elapsed: Duration,
+}
+
+impl SearchInfo {
+    /// The score as centipawns or moves to mate
+    pub fn score(&self) -> Score {
+        Score::from(self.score)
+    }
+
+    /// The UCI `info` line for the iteration, naming its `multipv` rank after the
+    /// first line, with the win, draw and loss chances after the score when `wdl` is
+    /// given, as `UCI_ShowWDL` asks for
+    pub fn uci_line(&self, wdl: Option<Wdl>) -> String {
+        let mut line = format!("info depth {} seldepth {}", self.depth, self.seldepth);
+        if self.multipv > 1 {
+            line += &format!(" multipv {}", self.multipv);
+        }
+        line += &format!(" score {}", self.score());
+        if let Some(wdl) = wdl {
+            line += &format!(" wdl {} {} {}", wdl.win, wdl.draw, wdl.loss);
+        }
+        line += &format!(
+            " nodes {} nps {} hashfull {} time {}",
+            self.nodes,
+            self.nps,
+            self.hashfull,
+            self.elapsed.as_millis()
+        );
+        if !self.pv.is_empty() {
+            line += " pv";
+            for mv in &self.pv {
+                line += " ";
+                line += &mv.to_uci();
+            }
+        }
+        line
+    }
+}
+
+/// Receives search progress, e.g. to stream `info` lines in the CLI or over UCI
+pub trait SearchObserver {
+    fn on_iteration(&mut self, info: &SearchInfo);
+}
+
+impl<F: FnMut(&SearchInfo)> SearchObserver for F {
+    fn on_iteration(&mut self, info: &SearchInfo) {
+        self(info)
+    }
+}
+
+/// Observer that ignores all progress
+struct NoObserver;
+
+impl SearchObserver for NoObserver {
+    fn on_iteration(&mut self, _info: &SearchInfo) {}
+}
+
+/// Follow best moves through the transposition table, stopping at the first
+/// missing or illegal entry
+pub fn extract_pv(board: &Board, tt: &TranspositionTable, max_len: usize) -> Vec<Move> {
+    let mut pv = Vec::new();
+    let mut board = *board;
+
+    while pv.len() < max_len {
+        let Some(mv) = tt.probe(board.hash()).and_then(|entry| entry.best_move) else {
+            break;
+        };
+        let color = if board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        if !Move::generate_legal_moves(&board, color).contains(&mv) {
+            break;
+        }
+        board.make_move(&mv);
+        pv.push(mv);
+    }
+
+    pv
+}
+
+/// Cloneable handle for interrupting a running [`Search`] from another thread
+#[derive(Debug, Clone)]
+pub struct SearchHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl SearchHandle {
+    /// Ask the search to stop. It returns the best move of the last completed iteration
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Iterative deepening searcher owning a transposition table shared by its threads
+pub struct Search {
+    options: SearchOptions,
+    tt: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    /// Hashes of the positions played before the one to search
+    game_history: Vec<u64>,
+    /// Statistics of the main thread in the last search
+    stats: SearchStats,
+    tablebase: Option<Arc<dyn Tablebase>>,
+    eval_params: EvalParams,
+}
+
+impl Search {
+    pub fn new(options: SearchOptions) -> Self {
+        Self {
+            options,
+            tt: Arc::new(TranspositionTable::unallocated(options.hash_mb)),
+            stop: Arc::new(AtomicBool::new(false)),
+            game_history: Vec::new(),
+            stats: SearchStats::default(),
+            tablebase: None,
+            eval_params: EvalParams::DEFAULT,
+        }
+    }
+
+    /// Set the hashes of the positions played before the next one searched, oldest
+    /// first, so that moves repeating one of them are scored as draws
+    pub fn set_game_history(&mut self, hashes: Vec<u64>) {
+        self.game_history = hashes;
+    }
+
+    /// Probe `tablebase` for exact distances to mate in the endings it covers, so
+    /// mates through them are found and reported at their true length, and for
+    /// distances to zeroing at the root, so only moves keeping the best result are
+    /// searched
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<dyn Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    /// Evaluate positions with `params` instead of the built-in weights
+    pub fn set_eval_params(&mut self, params: EvalParams) {
+        self.eval_params = params;
+    }
+
+    /// Counters of the main thread in the last search, all zero without the `stats`
+    /// feature
+    pub fn stats(&self) -> SearchStats {
+        self.stats
+    }
+
+    /// Keep the statistics of a finished search
+    fn record_stats(&mut self, ctx: &SearchContext) {
+        self.stats = SearchStats {
+            nodes: ctx.nodes,
+            ..ctx.stats
+        };
+    }
+
+    /// A context for one search thread, sharing the table and stop flag
+    fn context(&self) -> SearchContext {
+        let mut ctx = SearchContext::with_shared(self.tt.clone(), self.stop.clone());
+        ctx.path.clone_from(&self.game_history);
+        ctx.tablebase.clone_from(&self.tablebase);
+        ctx.eval_params = self.eval_params;
+        ctx
+    }
+
+    /// A handle that stops the currently running search.
+    /// A stop requested while no search is running is cleared when the next one starts
+    pub fn handle(&self) -> SearchHandle {
+        SearchHandle {
+            stop: self.stop.clone(),
+        }
+    }
+
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    /// Set the number of search threads (at least one)
+    pub fn set_threads(&mut self, threads: usize) {
+        self.options.threads = threads.max(1);
+    }
+
+    /// Change the table size and thread count between searches. A new table size
+    /// starts with an empty table, the old entries are kept otherwise
+    pub fn set_options(&mut self, options: SearchOptions) {
+        if options.hash_mb != self.options.hash_mb {
+            self.set_hash(options.hash_mb);
+        }
+        self.set_threads(options.threads);
+    }
+
+    /// Resize the transposition table to `hash_mb` megabytes, as for `setoption name
+    /// Hash`. The entries are dropped and the memory is taken when the next search starts
+    pub fn set_hash(&mut self, hash_mb: usize) {
+        self.options.hash_mb = hash_mb;
+        match Arc::get_mut(&mut self.tt) {
+            Some(tt) => tt.resize(hash_mb),
+            None => self.tt = Arc::new(TranspositionTable::unallocated(hash_mb)),
+        }
+    }
+
+    /// Forget every stored result, as for `ucinewgame`
+    pub fn clear_hash(&self) {
+        self.tt.clear();
+    }
+
+    /// Reset the stop flag and allocate a resized table before a search
+    fn prepare(&mut self) {
+        self.stop.store(false, Ordering::Relaxed);
+        if let Some(tt) = Arc::get_mut(&mut self.tt) {
+            tt.allocate();
+        }
+    }
+
+    /// Search the position to a fixed depth
+    pub fn search(&mut self, board: &Board, depth: u8) -> SearchResult {
+        self.go(board, SearchLimits::fixed_depth(depth))
+    }
+
+    /// Like [`Search::search`], reporting progress to `observer` after every iteration
+    pub fn search_with_observer(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchResult {
+        self.go_with_observer(board, SearchLimits::fixed_depth(depth), observer)
+    }
+
+    /// The move to play and its score relative to the side to move. With no legal
+    /// moves there is nothing to play: the score is `-MATE_SCORE` when checkmated and
+    /// 0 when stalemated
+    pub fn best_move(&mut self, board: &Board, limits: SearchLimits) -> (Option<Move>, i32) {
+        let result = self.go(board, limits);
+        (result.best_move, result.score)
+    }
+
+    /// Prove a forced mate for the side to move within `max_plies` plies, or refute one
+    /// by returning `None`. Shorter mates are tried first, checks are searched before
+    /// other moves and a mate-in-one must be a check. `None` is also returned when the
+    /// search is stopped through a [`SearchHandle`] before a mate is found
+    pub fn find_mate(&mut self, board: &Board, max_plies: u8) -> Option<Mate> {
+        self.prepare();
+        let mut ctx = self.context();
+        self.find_mate_with(board, max_plies, &mut ctx)
+    }
+
+    fn find_mate_with(
+        &mut self,
+        board: &Board,
+        max_plies: u8,
+        ctx: &mut SearchContext,
+    ) -> Option<Mate> {
+        use crate::eval::INFINITY;
+
+        let mut board = *board;
+        for depth in (1..=max_plies).step_by(2) {
+            let mut line = Vec::new();
+            let score = mate_search(&mut board, depth, 0, -INFINITY, INFINITY, ctx, &mut line);
+            if ctx.stopped() {
+                return None;
+            }
+            if score > 0 && is_mate_score(score) {
+                return Some(Mate {
+                    line,
+                    nodes: ctx.nodes,
+                });
+            }
+        }
+        None
+    }
+
+    /// Search the position until one of the limits is reached
+    pub fn go(&mut self, board: &Board, limits: SearchLimits) -> SearchResult {
+        self.go_with_observer(board, limits, &mut NoObserver)
+    }
+
+    /// Search the position until one of the limits is reached, reporting progress to
+    /// `observer` after every iteration. With several threads, helper threads run the
+    /// same iterative deepening loop (odd helpers one ply deeper) and share results
+    /// only through the transposition table; the main thread's result is used
+    pub fn go_with_observer(
+        &mut self,
+        board: &Board,
+        limits: SearchLimits,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchResult {
+        self.prepare();
+        let start = Instant::now();
+        let deadline = limits.movetime.map(|time| start + time);
+        let max_depth = limits.max_depth();
+        let mut ctx = self.context();
+        ctx.multi_pv = limits.multi_pv.unwrap_or(1).max(1);
+
+        if limits.mate.is_some() {
+            // The proof gets half the time and nodes, leaving the rest to the normal
+            // search that takes over when it finds nothing
+            ctx.deadline = limits.movetime.map(|time| start + time / 2);
+            ctx.max_nodes = limits.nodes.map(|nodes| nodes / 2);
+            if let Some(mate) = self.find_mate_with(board, max_depth, &mut ctx) {
+                let plies = mate.line.len() as u8;
+                let score = MATE_SCORE - plies as i32;
+                let elapsed = start.elapsed();
+                observer.on_iteration(&SearchInfo {
+                    depth: plies,
+                    multipv: 1,
+                    seldepth: plies,
+                    nodes: mate.nodes,
+                    nps: (mate.nodes as u128 * 1000 / elapsed.as_millis().max(1)) as u64,
+                    hashfull: self.tt.hashfull(),
+                    score,
+                    pv: mate.line.clone(),
+                    elapsed,
+                });
+                self.record_stats(&ctx);
+                return SearchResult {
+                    best_move: mate.line.first().copied(),
+                    score,
+                    depth: plies,
+                    nodes: mate.nodes,
+                };
+            }
+
+            // Stopped by its own share of the limits: re-arm the flag for the normal
+            // search, which goes on counting from the nodes spent here. A stop asked
+            // for through a handle still ends the search
+            let spent = |deadline: Option<Instant>, max_nodes: Option<u64>, nodes: u64| {
+                deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                    || max_nodes.is_some_and(|max| nodes >= max)
+            };
+            if ctx.stopped()
+                && spent(ctx.deadline, ctx.max_nodes, ctx.nodes)
+                && !spent(deadline, limits.nodes, ctx.nodes)
+            {
+                self.stop.store(false, Ordering::Relaxed);
+            }
+        }
+        ctx.deadline = deadline;
+        ctx.max_nodes = limits.nodes;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "search",
+            max_depth,
+            threads = self.options.threads,
+            nodes = limits.nodes,
+            movetime_ms = limits.movetime.map(|time| time.as_millis() as u64),
+        )
+        .entered();
+
+        // Without std there are no threads, so the `threads` option is ignored
+        #[cfg(not(feature = "std"))]
+        {
+            let result =
+                iterative_deepening(board, max_depth, !limits.infinite, &mut ctx, observer);
+            self.record_stats(&ctx);
+            result
+        }
+
+        #[cfg(feature = "std")]
+        thread::scope(|scope| {
+            let helpers = self.options.threads.max(1) - 1;
+            let handles: Vec<_> = (1..=helpers)
+                .map(|id| {
+                    let mut ctx = self.context();
+                    ctx.deadline = deadline;
+                    let board = *board;
+                    let helper_depth = max_depth.saturating_add((id % 2) as u8);
+                    #[cfg(feature = "tracing")]
+                    let span = tracing::debug_span!("helper", id);
+                    scope.spawn(move || {
+                        #[cfg(feature = "tracing")]
+                        let _span = span.entered();
+                        iterative_deepening(&board, helper_depth, true, &mut ctx, &mut NoObserver);
+                        ctx.nodes
+                    })
+                })
+                .collect();
+
+            let mut result =
+                iterative_deepening(board, max_depth, !limits.infinite, &mut ctx, observer);
+
+            self.stop.store(true, Ordering::Relaxed);
+            for handle in handles {
+                result.nodes += handle.join().unwrap_or(0);
+            }
+            self.record_stats(&ctx);
+            result
+        })
+    }
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self::new(SearchOptions::default())
+    }
+}
+
+/// A legal root move and what the iterations so far found out about it
+#[derive(Debug, Clone, Copy)]
+struct RootMove {
+    mv: Move,
+    /// Score in the last iteration that searched the move, exact for the best move,
+    /// or the best few with several lines, and an upper bound for the others
+    score: i32,
+    /// Score in the iteration before
+    previous_score: i32,
+    /// Nodes spent below the move in the last iteration
+    nodes: u64,
+}
+
+/// Iterations the best move must survive unchanged to be played early
+const EASY_MOVE_ITERATIONS: u32 = 3;
+
+/// How far below the best move every other move must score to play it early
+const EASY_MOVE_MARGIN: i32 = 200;
+
+/// Put the best move of the last iteration first and the others by the nodes they
+/// took, since moves that are hard to refute tend to be the next best. With several
+/// lines the others go by score first, so the lines to report lead
+fn sort_root_moves(root_moves: &mut [RootMove], best_move: Move, multi_pv: usize) {
+    use core::cmp::Reverse;
+
+    if multi_pv > 1 {
+        root_moves.sort_by_key(|root| {
+            (
+                root.mv != best_move,
+                Reverse(root.score),
+                Reverse(root.nodes),
+            )
+        });
+    } else {
+        root_moves.sort_by_key(|root| (root.mv != best_move, Reverse(root.nodes)));
+    }
+}
+
+/// Whether every root move but the first fails to come within [`EASY_MOVE_MARGIN`] of
+/// its score in a null window search of half the depth, which makes the first an easy
+/// move such as an obvious recapture
+fn is_easy_move(
+    board: &Board,
+    root_moves: &[RootMove],
+    depth: u8,
+    ctx: &mut SearchContext,
+) -> bool {
+    let [best, others @ ..] = root_moves else {
+        return false;
+    };
+    if is_mate_score(best.score) {
+        return false;
+    }
+    let color = if board.to_move {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let beta = best.score - EASY_MOVE_MARGIN;
+    let mut board = *board;
+    ctx.path.push(board.hash());
+    let easy = others.iter().all(|root| {
+        ctx.played.push(piece_to(&board, &root.mv));
+        let undo = board.make_move(&root.mv);
+        ctx.ply = 1;
+        let score = -Eval::negamax(
+            &mut board,
+            depth / 2,
+            -beta,
+            -beta + 1,
+            color.opposite(),
+            ctx,
+        );
+        ctx.ply = 0;
+        board.unmake_move(&undo);
+        ctx.played.pop();
+        score < beta && !ctx.stopped()
+    });
+    ctx.path.pop();
+    easy
+}
+
+/// Run depth 1, 2, ... up to `max_depth`, keeping the last completed iteration, or a
+/// move that already beat its best move when the search stops mid-iteration.
+/// With `stop_on_mate`, a mate proven within the searched depth ends the search early,
+/// and with a deadline an only move or an easy move (see [`is_easy_move`]) does too
+fn iterative_deepening(
+    board: &Board,
+    max_depth: u8,
+    stop_on_mate: bool,
+    ctx: &mut SearchContext,
+    observer: &mut dyn SearchObserver,
+) -> SearchResult {
+    let start = Instant::now();
+    let color = if board.to_move {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let mut moves = Move::generate_legal_moves(board, color);
+    let tt_move = ctx.tt.probe(board.hash()).and_then(|entry| entry.best_move);
+    order_moves_by(board, &mut moves, tt_move, |mv| ctx.quiet_score(board, mv));
+    // In a tablebase ending, only search the moves that keep the best result the
+    // fifty-move rule allows
+    if let Some(kept) = ctx
+        .tablebase
+        .as_deref()
+        .and_then(|tablebase| tablebase::filter_root_moves(tablebase, board, &moves))
+    {
+        moves.retain(|mv| kept.contains(mv));
+    }
+    let mut root_moves: Vec<RootMove> = moves
+        .iter()
+        .map(|&mv| RootMove {
+            mv,
+            score: -MATE_SCORE,
+            previous_score: -MATE_SCORE,
+            nodes: 0,
+        })
+        .collect();
+
+    let mut result = SearchResult {
+        best_move: moves.first().copied(),
+        score: 0,
+        depth: 0,
+        nodes: 0,
+    };
+
+    if root_moves.is_empty() {
+        result.score = if Move::is_in_check(board, color) {
+            -MATE_SCORE
+        } else {
+            0
+        };
+        return result;
+    }
+
+    let mut stable_iterations = 0;
+    for depth in 1..=max_depth.max(1) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("iteration", depth).entered();
+        let (score, best_move) = search_root(board, &mut root_moves, depth, color, ctx);
+        if ctx.stopped() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(nodes = ctx.nodes, "iteration abandoned");
+            if best_move.is_some() {
+                result.best_move = best_move;
+                result.score = score;
+            }
+            break;
+        }
+        if let Some(mv) = best_move {
+            sort_root_moves(&mut root_moves, mv, ctx.multi_pv);
+        }
+        if best_move == result.best_move {
+            stable_iterations += 1;
+        } else {
+            stable_iterations = 0;
+        }
+        result.best_move = best_move;
+        result.score = score;
+        result.depth = depth;
+
+        let elapsed = start.elapsed();
+        let mut pv = extract_pv(board, &ctx.tt, depth as usize);
+        if pv.first() != best_move.as_ref() {
+            pv = best_move.into_iter().collect();
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            score,
+            nodes = ctx.nodes,
+            seldepth = ctx.seldepth,
+            elapsed_ms = elapsed.as_millis() as u64,
+            best_move = best_move.map(|mv| mv.to_uci()),
+            "iteration complete"
+        );
+        observer.on_iteration(&SearchInfo {
+            depth,
+            multipv: 1,
+            seldepth: ctx.seldepth.max(depth),
+            nodes: ctx.nodes,
+            nps: (ctx.nodes as u128 * 1000 / elapsed.as_millis().max(1)) as u64,
+            hashfull: ctx.tt.hashfull(),
+            score,
+            pv,
+            elapsed,
+        });
+        // The next best lines, each followed from the table after its root move
+        let lines = ctx.multi_pv.min(root_moves.len());
+        for (index, root) in root_moves.iter().enumerate().take(lines).skip(1) {
+            let mut after = *board;
+            after.make_move(&root.mv);
+            let mut pv = vec![root.mv];
+            pv.extend(extract_pv(&after, &ctx.tt, depth as usize - 1));
+            observer.on_iteration(&SearchInfo {
+                depth,
+                multipv: index + 1,
+                seldepth: ctx.seldepth.max(depth),
+                nodes: ctx.nodes,
+                nps: (ctx.nodes as u128 * 1000 / elapsed.as_millis().max(1)) as u64,
+                hashfull: ctx.tt.hashfull(),
+                score: root.score,
+                pv,
+                elapsed,
+            });
+        }
+
+        // Every line up to this depth was searched, so no faster mate exists
+        if stop_on_mate && is_mate_score(score) && MATE_SCORE - score.abs() <= depth as i32 {
+            break;
+        }
+
+        // Out of time: another iteration would not finish anyway
+        if ctx
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(depth, "no time for another iteration");
+            break;
+        }
+
+        // With a clock, an only move is played at once and an easy move once a
+        // sixteenth of the time has passed
+        if let Some(deadline) = ctx.deadline {
+            let time_used = Instant::now() + elapsed * 15 >= deadline;
+            if root_moves.len() == 1
+                || (time_used
+                    && stable_iterations >= EASY_MOVE_ITERATIONS
+                    && is_easy_move(board, &root_moves, depth, ctx))
+            {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(depth, stable_iterations, "easy move");
+                break;
+            }
+        }
+    }
+
+    result.nodes = ctx.nodes;
+    result
+}
+
+/// Whether playing `mv` puts the opponent in check
+fn gives_check(board: &Board, mv: &Move) -> bool {
+    let mut after = *board;
+    after.make_move(mv);
+    let enemy = if after.to_move {
+        Color::White
+    } else {
+        Color::Black
+    };
+    Move::is_in_check(&after, enemy)
+}
+
+/// Negamax that only tells mates apart from everything else: positions without a mate
+/// inside `depth` plies score 0. Bounds are narrowed to the shortest mate still
+/// possible at `ply` (mate-distance pruning), and `line` receives the best line found
+fn mate_search(
+    board: &mut Board,
+    depth: u8,
+    ply: u8,
+    alpha: i32,
+    beta: i32,
+    ctx: &mut SearchContext,
+    line: &mut Vec<Move>,
+) -> i32 {
+    ctx.nodes += 1;
+    ctx.check_limits();
+    if ctx.stopped() {
+        return 0;
+    }
+
+    let alpha = alpha.max(-MATE_SCORE + ply as i32);
+    let beta = beta.min(MATE_SCORE - ply as i32 - 1);
+    if alpha >= beta {
+        return alpha;
+    }
+
+    let color = if board.to_move {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let moves = Move::generate_legal_moves(board, color);
+    if moves.is_empty() {
+        return if Move::is_in_check(board, color) {
+            -MATE_SCORE + ply as i32
+        } else {
+            0
+        };
+    }
+    if depth == 0 {
+        return 0;
+    }
+
+    // Checks first, then captures. With one ply left only a check can mate
+    let mut ordered: Vec<(u8, Move)> = moves
+        .iter()
+        .filter_map(|mv| {
+            let check = gives_check(board, mv);
+            if depth == 1 && !check {
+                return None;
+            }
+            Some((if check { 0 } else { 1 + !mv.is_capture() as u8 }, *mv))
+        })
+        .collect();
+    ordered.sort_by_key(|(rank, _)| *rank);
+
+    // Skipped quiet moves cannot mate, so they score 0
+    let mut best = if ordered.len() < moves.len() {
+        0
+    } else {
+        -MATE_SCORE
+    };
+    let mut alpha = alpha.max(best);
+    let mut child = Vec::new();
+    for (_, mv) in ordered {
+        let undo = board.make_move(&mv);
+        child.clear();
+        let score = -mate_search(board, depth - 1, ply + 1, -beta, -alpha, ctx, &mut child);
+        board.unmake_move(&undo);
+        if ctx.stopped() {
+            return 0;
+        }
+
+        if score > best {
+            best = score;
+            line.clear();
+            line.push(mv);
+            line.extend_from_slice(&child);
+        }
+        if score > alpha {
+            alpha = score;
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+/// Principal variation search over the legal root moves in their current order,
+/// returning the score and best move and recording each move's score and nodes.
+/// With several lines, a move only needs to beat the last of the best scores so far
+/// to be searched exactly. A stopped search only returns a move that beat the fully
+/// searched first one
+fn search_root(
+    board: &Board,
+    root_moves: &mut [RootMove],
+    depth: u8,
+    color: Color,
+    ctx: &mut SearchContext,
+) -> (i32, Option<Move>) {
+    use crate::eval::INFINITY;
+    use crate::tt::Bound;
+
+    ctx.nodes += 1;
+    let enemy_color = color.opposite();
+
+    let mut alpha = -INFINITY;
+    let beta = INFINITY;
+    let mut best_move = None;
+    // The `multi_pv` best scores so far, highest first
+    let multi_pv = ctx.multi_pv.max(1);
+    let mut best_scores: Vec<i32> = Vec::with_capacity(multi_pv);
+
+    let mut board = *board;
+    ctx.path.push(board.hash());
+    for root in root_moves.iter_mut() {
+        let mv = &root.mv;
+        let nodes_before = ctx.nodes;
+        ctx.played.push(piece_to(&board, mv));
+        let undo = board.make_move(mv);
+
+        ctx.ply = 1;
+        let score = match best_scores.get(multi_pv - 1) {
+            None => -Eval::negamax(&mut board, depth - 1, -beta, INFINITY, enemy_color, ctx),
+            Some(&floor) => {
+                let probe =
+                    -Eval::negamax(&mut board, depth - 1, -floor - 1, -floor, enemy_color, ctx);
+                if probe > floor {
+                    -Eval::negamax(&mut board, depth - 1, -beta, -floor, enemy_color, ctx)
+                } else {
+                    probe
+                }
+            }
+        };
+        ctx.ply = 0;
+        board.unmake_move(&undo);
+        ctx.played.pop();
+
+        if ctx.stopped() {
+            break;
+        }
+        root.previous_score = root.score;
+        root.score = score;
+        root.nodes = ctx.nodes - nodes_before;
+        let rank = best_scores.partition_point(|&best| best >= score);
+        if rank < multi_pv {
+            best_scores.insert(rank, score);
+            best_scores.truncate(multi_pv);
+        }
+
+        if score > alpha || best_move.is_none() {
+            alpha = alpha.max(score);
+            best_move = Some(*mv);
+        }
+    }
+    ctx.path.pop();
+
+    if ctx.stopped() {
+        let first = root_moves.first().map(|root| root.mv);
+        return match best_move {
+            Some(mv) if Some(mv) != first => (alpha, best_move),
+            _ => (0, None),
+        };
+    }
+    ctx.tt
+        .store(board.hash(), depth, alpha, Bound::Exact, best_move);
+
+    (alpha, best_move)
+}
@@ -0,0 +1,85 @@
+use crate::board::Board;
+use crate::fen::to_fen;
+use crate::game::{ChessGame, Game};
+use crate::moves::Move;
+use crate::variant::{Atomic, ThreeCheck};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_fen(self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        Board::try_from_fen(&fen).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_uci())
+    }
+}
+
+/// Parsed like [`Move::from_str`](std::str::FromStr), so only `Normal` and `Promotion`
+/// moves come back
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let uci = String::deserialize(deserializer)?;
+        uci.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A game as its variant, starting position and the moves played from it
+#[derive(Serialize, Deserialize)]
+struct GameRecord {
+    variant: String,
+    fen: String,
+    moves: Vec<String>,
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GameRecord {
+            variant: self.rules().name().to_string(),
+            fen: to_fen(self.initial_board()),
+            moves: self.moves().iter().map(Move::to_uci).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Replays the recorded moves, so a game with an illegal move fails to deserialize
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let record = GameRecord::deserialize(deserializer)?;
+        let game = Game::from_fen(&record.fen).map_err(de::Error::custom)?;
+        let mut game = match record.variant.as_str() {
+            "Standard" => game,
+            "Atomic" => game.with_rules(Atomic),
+            "Three-check" => game.with_rules(ThreeCheck),
+            other => return Err(de::Error::custom(format!("unknown variant '{}'", other))),
+        };
+        for uci in &record.moves {
+            game.try_move_algebraic(uci)
+                .map_err(|e| de::Error::custom(format!("{} '{}'", e, uci)))?;
+        }
+        Ok(game)
+    }
+}
+
+impl Serialize for ChessGame {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Game::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChessGame {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Game::deserialize(deserializer).map(ChessGame::from)
+    }
+}
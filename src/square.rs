@@ -0,0 +1,210 @@
+use alloc::{format, string::String};
+use core::fmt;
+use core::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+const FILES: [File; 8] = [
+    File::A,
+    File::B,
+    File::C,
+    File::D,
+    File::E,
+    File::F,
+    File::G,
+    File::H,
+];
+const RANKS: [Rank; 8] = [
+    Rank::One,
+    Rank::Two,
+    Rank::Three,
+    Rank::Four,
+    Rank::Five,
+    Rank::Six,
+    Rank::Seven,
+    Rank::Eight,
+];
+
+impl File {
+    /// File from its index, 0 for the a-file
+    pub fn from_index(index: u8) -> Option<File> {
+        FILES.get(index as usize).copied()
+    }
+
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+impl Rank {
+    /// Rank from its index, 0 for the first rank
+    pub fn from_index(index: u8) -> Option<Rank> {
+        RANKS.get(index as usize).copied()
+    }
+
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+/// A square on the board, indexed like the bitboards: a1 = 0, b1 = 1, ..., h8 = 63
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Square(u8);
+
+impl Square {
+    pub const A1: Square = Square(0);
+    pub const B1: Square = Square(1);
+    pub const C1: Square = Square(2);
+    pub const D1: Square = Square(3);
+    pub const E1: Square = Square(4);
+    pub const F1: Square = Square(5);
+    pub const G1: Square = Square(6);
+    pub const H1: Square = Square(7);
+    pub const A2: Square = Square(8);
+    pub const B2: Square = Square(9);
+    pub const C2: Square = Square(10);
+    pub const D2: Square = Square(11);
+    pub const E2: Square = Square(12);
+    pub const F2: Square = Square(13);
+    pub const G2: Square = Square(14);
+    pub const H2: Square = Square(15);
+    pub const A3: Square = Square(16);
+    pub const B3: Square = Square(17);
+    pub const C3: Square = Square(18);
+    pub const D3: Square = Square(19);
+    pub const E3: Square = Square(20);
+    pub const F3: Square = Square(21);
+    pub const G3: Square = Square(22);
+    pub const H3: Square = Square(23);
+    pub const A4: Square = Square(24);
+    pub const B4: Square = Square(25);
+    pub const C4: Square = Square(26);
+    pub const D4: Square = Square(27);
+    pub const E4: Square = Square(28);
+    pub const F4: Square = Square(29);
+    pub const G4: Square = Square(30);
+    pub const H4: Square = Square(31);
+    pub const A5: Square = Square(32);
+    pub const B5: Square = Square(33);
+    pub const C5: Square = Square(34);
+    pub const D5: Square = Square(35);
+    pub const E5: Square = Square(36);
+    pub const F5: Square = Square(37);
+    pub const G5: Square = Square(38);
+    pub const H5: Square = Square(39);
+    pub const A6: Square = Square(40);
+    pub const B6: Square = Square(41);
+    pub const C6: Square = Square(42);
+    pub const D6: Square = Square(43);
+    pub const E6: Square = Square(44);
+    pub const F6: Square = Square(45);
+    pub const G6: Square = Square(46);
+    pub const H6: Square = Square(47);
+    pub const A7: Square = Square(48);
+    pub const B7: Square = Square(49);
+    pub const C7: Square = Square(50);
+    pub const D7: Square = Square(51);
+    pub const E7: Square = Square(52);
+    pub const F7: Square = Square(53);
+    pub const G7: Square = Square(54);
+    pub const H7: Square = Square(55);
+    pub const A8: Square = Square(56);
+    pub const B8: Square = Square(57);
+    pub const C8: Square = Square(58);
+    pub const D8: Square = Square(59);
+    pub const E8: Square = Square(60);
+    pub const F8: Square = Square(61);
+    pub const G8: Square = Square(62);
+    pub const H8: Square = Square(63);
+
+    pub fn new(file: File, rank: Rank) -> Self {
+        Square(rank.index() * 8 + file.index())
+    }
+
+    /// Square from its bitboard index, or `None` for 64 and above
+    pub fn from_index(index: u8) -> Option<Square> {
+        (index < 64).then_some(Square(index))
+    }
+
+    pub fn index(self) -> u8 {
+        self.0
+    }
+
+    pub fn file(self) -> File {
+        FILES[(self.0 % 8) as usize]
+    }
+
+    pub fn rank(self) -> Rank {
+        RANKS[(self.0 / 8) as usize]
+    }
+
+    /// Single-bit bitboard of this square
+    pub fn bitboard(self) -> u64 {
+        1u64 << self.0
+    }
+}
+
+/// Panics if the index is off the board
+impl From<u8> for Square {
+    fn from(index: u8) -> Self {
+        Square::from_index(index).expect("Square index out of range")
+    }
+}
+
+impl From<Square> for u8 {
+    fn from(square: Square) -> Self {
+        square.0
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file().index()) as char,
+            self.rank().index() + 1
+        )
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+
+    /// Parse a square name such as "e4"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        let [file, rank] = bytes else {
+            return Err(format!("Invalid square '{}'", s));
+        };
+        match (
+            File::from_index(file.wrapping_sub(b'a')),
+            Rank::from_index(rank.wrapping_sub(b'1')),
+        ) {
+            (Some(file), Some(rank)) => Ok(Square::new(file, rank)),
+            _ => Err(format!("Invalid square '{}'", s)),
+        }
+    }
+}
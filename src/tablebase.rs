@@ -0,0 +1,115 @@
+use crate::board::Board;
+use crate::eval::MATE_SCORE;
+use crate::moves::Move;
+use alloc::vec::Vec;
+
+/// Distance to mate from a tablebase, from the side to move's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtm {
+    Draw,
+    /// The side to move mates in this many plies
+    Win(u16),
+    /// The side to move is mated in this many plies
+    Loss(u16),
+}
+
+impl Dtm {
+    /// The search score of a position `ply` plies from the root, on the same scale as
+    /// mates found by the search
+    pub fn to_score(self, ply: u8) -> i32 {
+        match self {
+            Dtm::Draw => 0,
+            Dtm::Win(plies) => MATE_SCORE - ply as i32 - plies as i32,
+            Dtm::Loss(plies) => -MATE_SCORE + ply as i32 + plies as i32,
+        }
+    }
+}
+
+/// Distance to zeroing from a tablebase: plies until the next capture or pawn move
+/// under best play, from the side to move's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtz {
+    Draw,
+    /// The side to move wins, zeroing in this many plies
+    Win(u16),
+    /// The side to move loses, the opponent zeroing in this many plies
+    Loss(u16),
+}
+
+/// A probing hook for endgame tables. The crate reads no table files itself: a
+/// Gaviota (distance to mate) or Syzygy (distance to zeroing) prober, for instance a
+/// binding to its C library, implements this trait and is installed with
+/// [`Search::set_tablebase`](crate::search::Search::set_tablebase). The search probes
+/// it in positions with at most [`Tablebase::max_pieces`] pieces and no castling rights
+pub trait Tablebase: Send + Sync {
+    /// Most pieces, kings included, of the positions the tables cover
+    fn max_pieces(&self) -> u32;
+
+    /// The distance to mate of `board`, or `None` when the tables do not have it
+    fn probe_dtm(&self, _board: &Board) -> Option<Dtm> {
+        None
+    }
+
+    /// The distance to zeroing of `board`, or `None` when the tables do not have it.
+    /// Nothing in the crate answers it, a Syzygy prober has to supply it
+    fn probe_dtz(&self, _board: &Board) -> Option<Dtz> {
+        None
+    }
+}
+
+/// Whether `board` is small enough for `tablebase` and has no castling rights
+pub fn covers(tablebase: &dyn Tablebase, board: &Board) -> bool {
+    board.castling_rights == 0 && board.get_all_occupied().count_ones() <= tablebase.max_pieces()
+}
+
+/// Probe `tablebase` if it can cover `board`
+pub fn probe(tablebase: &dyn Tablebase, board: &Board) -> Option<Dtm> {
+    covers(tablebase, board)
+        .then(|| tablebase.probe_dtm(board))
+        .flatten()
+}
+
+/// Ranks a root move by the distance to zeroing of the position it leads to, higher
+/// keys being better: wins the fifty-move rule cannot spoil, zeroing or nearest first,
+/// then draws, including wins and losses the rule turns into draws, then losses,
+/// longest first
+fn dtz_rank(board: &Board, mv: &Move, tablebase: &dyn Tablebase) -> Option<(u8, i32)> {
+    let mut after = *board;
+    after.make_move(mv);
+    if !covers(tablebase, &after) {
+        return None;
+    }
+    // The opponent's distance, counted from the clock our move leaves
+    let clock = after.halfmove_clock as u32;
+    let rank = match tablebase.probe_dtz(&after)? {
+        // A zeroing move resets the clock and is as fast as a win gets
+        Dtz::Loss(_) if clock == 0 => (2, 0),
+        Dtz::Loss(plies) if clock + plies as u32 <= 100 => (2, -(plies as i32)),
+        Dtz::Win(plies) if clock + plies as u32 <= 100 => (0, plies as i32),
+        _ => (1, 0),
+    };
+    Some(rank)
+}
+
+/// The moves among `moves` that keep the best result the fifty-move rule allows, as
+/// told by the distance to zeroing tables. A winning side only keeps its fastest way
+/// to the next capture or pawn move. `None` when a move cannot be probed
+pub fn filter_root_moves(
+    tablebase: &dyn Tablebase,
+    board: &Board,
+    moves: &[Move],
+) -> Option<Vec<Move>> {
+    let ranks = moves
+        .iter()
+        .map(|mv| dtz_rank(board, mv, tablebase))
+        .collect::<Option<Vec<_>>>()?;
+    let best = ranks.iter().max()?;
+    Some(
+        moves
+            .iter()
+            .zip(&ranks)
+            .filter(|&(_, rank)| rank == best)
+            .map(|(&mv, _)| mv)
+            .collect(),
+    )
+}
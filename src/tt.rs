@@ -0,0 +1,245 @@
+use crate::moves::{Move, MoveType};
+use crate::piece::Piece;
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// How a stored score relates to the true value of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The true score is at least the stored score (fail high)
+    Lower,
+    /// The true score is at most the stored score (fail low)
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TTEntry {
+    pub key: u64,
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+/// A table slot. The key is stored XORed with the data so a torn write from a
+/// concurrent thread fails verification instead of returning a corrupt entry
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+fn encode_move_type(move_type: MoveType) -> u64 {
+    let promotion_index = |piece| match piece {
+        Piece::Knight => 0,
+        Piece::Bishop => 1,
+        Piece::Rook => 2,
+        _ => 3,
+    };
+    match move_type {
+        MoveType::Normal => 0,
+        MoveType::Capture => 1,
+        MoveType::EnPassant => 2,
+        MoveType::Castle => 3,
+        MoveType::Double => 4,
+        MoveType::Promotion { piece } => 5 + promotion_index(piece),
+        MoveType::PromotionCapture { piece } => 9 + promotion_index(piece),
+    }
+}
+
+fn decode_move_type(bits: u64) -> MoveType {
+    let promotion_piece = |index| match index {
+        0 => Piece::Knight,
+        1 => Piece::Bishop,
+        2 => Piece::Rook,
+        _ => Piece::Queen,
+    };
+    match bits {
+        0 => MoveType::Normal,
+        1 => MoveType::Capture,
+        2 => MoveType::EnPassant,
+        3 => MoveType::Castle,
+        4 => MoveType::Double,
+        5..=8 => MoveType::Promotion {
+            piece: promotion_piece(bits - 5),
+        },
+        _ => MoveType::PromotionCapture {
+            piece: promotion_piece(bits - 9),
+        },
+    }
+}
+
+/// Pack an entry into 64 bits:
+/// score (32) | depth (8) | bound (2) | has move (1) | from (6) | to (6) | move type (4)
+fn encode(depth: u8, score: i32, bound: Bound, best_move: Option<Move>) -> u64 {
+    let bound_bits = match bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let move_bits = match best_move {
+        Some(mv) => {
+            1 << 16 | (mv.from as u64) << 10 | (mv.to as u64) << 4 | encode_move_type(mv.move_type)
+        }
+        None => 0,
+    };
+    (score as u32 as u64) << 32 | (depth as u64) << 24 | bound_bits << 17 | move_bits
+}
+
+fn decode(key: u64, data: u64) -> TTEntry {
+    let bound = match (data >> 17) & 0b11 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let best_move = if data & (1 << 16) != 0 {
+        Some(Move::new(
+            ((data >> 10) & 0x3F) as u8,
+            ((data >> 4) & 0x3F) as u8,
+            decode_move_type(data & 0xF),
+        ))
+    } else {
+        None
+    };
+    TTEntry {
+        key,
+        depth: (data >> 24) as u8,
+        score: (data >> 32) as u32 as i32,
+        bound,
+        best_move,
+    }
+}
+
+/// The largest power of two number of slots that fits in `size_mb` megabytes, at least one
+fn slot_count(size_mb: usize) -> usize {
+    let count = (size_mb * 1024 * 1024 / core::mem::size_of::<Slot>()).max(1);
+    1 << count.ilog2()
+}
+
+/// Hash table of search results keyed on Zobrist hashes, holding a power of two
+/// number of entries. Lookups and stores take `&self`, so one table can be shared
+/// between search threads; resizing needs the table to itself
+pub struct TranspositionTable {
+    slots: Box<[Slot]>,
+    /// Entries to allocate when [`TranspositionTable::allocate`] is next called
+    capacity: usize,
+}
+
+impl TranspositionTable {
+    /// Create a table using at most `size_mb` megabytes (at least one entry)
+    pub fn new(size_mb: usize) -> Self {
+        let mut tt = Self::unallocated(size_mb);
+        tt.allocate();
+        tt
+    }
+
+    /// A table of `size_mb` megabytes whose memory is only taken by
+    /// [`TranspositionTable::allocate`]. Until then it stores nothing
+    pub fn unallocated(size_mb: usize) -> Self {
+        Self {
+            slots: Box::default(),
+            capacity: slot_count(size_mb),
+        }
+    }
+
+    /// Change the size to `size_mb` megabytes, dropping every entry. The new table is
+    /// allocated by the next [`TranspositionTable::allocate`]
+    pub fn resize(&mut self, size_mb: usize) {
+        self.slots = Box::default();
+        self.capacity = slot_count(size_mb);
+    }
+
+    /// Take the memory for the table if it was created unallocated or resized
+    pub fn allocate(&mut self) {
+        if self.slots.len() != self.capacity {
+            self.slots = (0..self.capacity).map(|_| Slot::default()).collect();
+        }
+    }
+
+    pub fn is_allocated(&self) -> bool {
+        !self.slots.is_empty()
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.slots.len() - 1)
+    }
+
+    fn load(&self, index: usize) -> Option<TTEntry> {
+        let slot = &self.slots[index];
+        let data = slot.data.load(Ordering::Relaxed);
+        // Stored entries always have a depth of at least one, so zero marks an empty slot
+        if data == 0 {
+            return None;
+        }
+        let key = slot.key.load(Ordering::Relaxed) ^ data;
+        Some(decode(key, data))
+    }
+
+    /// Look up the entry for a position, if one is stored
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        if !self.is_allocated() {
+            return None;
+        }
+        self.load(self.index(key)).filter(|entry| entry.key == key)
+    }
+
+    /// Store a search result, replacing shallower entries for other positions
+    pub fn store(&self, key: u64, depth: u8, score: i32, bound: Bound, best_move: Option<Move>) {
+        if !self.is_allocated() {
+            return;
+        }
+        let index = self.index(key);
+        let existing = self.load(index);
+        if let Some(existing) = existing
+            && existing.key != key
+            && existing.depth > depth
+        {
+            return;
+        }
+
+        // Keep the previous best move when the new result has none
+        let best_move = best_move.or_else(|| {
+            existing
+                .filter(|entry| entry.key == key)
+                .and_then(|entry| entry.best_move)
+        });
+
+        let data = encode(depth, score, bound, best_move);
+        let slot = &self.slots[index];
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Occupancy in permille, sampled from the first thousand slots
+    pub fn hashfull(&self) -> u16 {
+        if !self.is_allocated() {
+            return 0;
+        }
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .filter(|slot| slot.data.load(Ordering::Relaxed) != 0)
+            .count();
+        (used * 1000 / sample) as u16
+    }
+
+    /// Number of entries the table holds once allocated
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
@@ -1,28 +1,24 @@
 use crate::piece::Piece;
+use crate::square::Square;
+use alloc::{format, string::String, string::ToString, vec::Vec};
 
 /// Convert chess position (e.g. "a1", "h8") to a single byte index (0-63)
 /// file 'a'-'h' becomes 0-7, rank 1-8 becomes 0-7
 /// Formula: (rank - 1) * 8 + file_index
 /// Example: "a1" -> (1-1) * 8 + 0 = 0, "h8" -> (8-1) * 8 + 7 = 63
 pub fn pos_to_u8(pos: &str) -> Option<u8> {
-    let (file, rank) = pos.split_at(1);
-    let file = file.chars().next().unwrap();
-    let rank = rank.parse::<u8>().ok()?;
-
-    Some((rank - 1) * 8 + (file as u8 - b'a'))
+    pos.parse::<Square>().ok().map(u8::from)
 }
 
 pub fn u8_to_pos(square: u8) -> String {
-    let file = (square % 8) as u8 + b'a';
-    let rank = (square / 8) as u8 + 1;
-    format!("{}{}", char::from(file), rank)
+    Square::from(square).to_string()
 }
 
 /// Convert a move to coordinate algebraic notation (e.g., "e2e4", "a7a8=Q")
 pub fn move_to_algebraic(from: u8, to: u8, promotion: Option<Piece>) -> String {
-    let from_file = (from % 8) as u8 + b'a';
+    let from_file = (from % 8) + b'a';
     let from_rank = (from / 8) + 1;
-    let to_file = (to % 8) as u8 + b'a';
+    let to_file = (to % 8) + b'a';
     let to_rank = (to / 8) + 1;
 
     let promotion_suffix = match promotion {
@@ -40,27 +36,10 @@ pub fn move_to_algebraic(from: u8, to: u8, promotion: Option<Piece>) -> String {
 }
 
 /// Parse coordinate algebraic notation into components (e.g., "e2e4" -> (12, 28, None))
+/// Promotions may be written "e7e8=Q" or in UCI form "e7e8q", see
+/// [`notation::parse_coordinate`](crate::notation::parse_coordinate)
 pub fn parse_algebraic(algebraic: &str) -> Option<(u8, u8, Option<Piece>)> {
-    if algebraic.len() < 4 {
-        return None;
-    }
-
-    let from_square = pos_to_u8(&algebraic[0..2])?;
-    let to_square = pos_to_u8(&algebraic[2..4])?;
-
-    let promotion = if algebraic.len() >= 6 && &algebraic[4..5] == "=" {
-        match algebraic.chars().nth(5)? {
-            'Q' => Some(Piece::Queen),
-            'R' => Some(Piece::Rook),
-            'B' => Some(Piece::Bishop),
-            'N' => Some(Piece::Knight),
-            _ => None,
-        }
-    } else {
-        None
-    };
-
-    Some((from_square, to_square, promotion))
+    crate::notation::parse_coordinate(algebraic)
 }
 
 /// Get the file (column) of a square (0-7, where 0 is 'a' file)
@@ -90,195 +69,189 @@ pub fn same_diagonal(square1: u8, square2: u8) -> bool {
     file_diff == rank_diff && file_diff != 0
 }
 
+const DIRECTIONS: [(i8, i8); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+/// Squares from `square` to the edge in one direction, not counting `square` itself
+const fn ray(square: u8, (dr, df): (i8, i8)) -> u64 {
+    let mut ray = 0;
+    let mut rank = (square / 8) as i8 + dr;
+    let mut file = (square % 8) as i8 + df;
+    while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+        ray |= 1u64 << (rank * 8 + file);
+        rank += dr;
+        file += df;
+    }
+    ray
+}
+
+const fn build_line_tables() -> ([[u64; 64]; 64], [[u64; 64]; 64]) {
+    let mut between = [[0; 64]; 64];
+    let mut line = [[0; 64]; 64];
+    let mut from = 0;
+    while from < 64 {
+        let mut d = 0;
+        while d < DIRECTIONS.len() {
+            let (dr, df) = DIRECTIONS[d];
+            let full_line = ray(from, (dr, df)) | ray(from, (-dr, -df)) | 1u64 << from;
+            let mut squares = 0;
+            let mut rank = (from / 8) as i8 + dr;
+            let mut file = (from % 8) as i8 + df;
+            while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+                let to = (rank * 8 + file) as usize;
+                between[from as usize][to] = squares;
+                line[from as usize][to] = full_line;
+                squares |= 1u64 << to;
+                rank += dr;
+                file += df;
+            }
+            d += 1;
+        }
+        from += 1;
+    }
+    (between, line)
+}
+
+const LINE_TABLES: ([[u64; 64]; 64], [[u64; 64]; 64]) = build_line_tables();
+
+/// Squares strictly between two squares on a shared rank, file or diagonal, indexed
+/// `[from][to]`. Empty when they are not aligned or are neighbours
+pub static BETWEEN: [[u64; 64]; 64] = LINE_TABLES.0;
+
+/// The whole rank, file or diagonal through two squares, edge to edge and including
+/// both, indexed `[from][to]`. Empty when they are not aligned
+pub static LINE: [[u64; 64]; 64] = LINE_TABLES.1;
+
+/// Squares strictly between `from` and `to`, see [`BETWEEN`]
+pub fn between(from: u8, to: u8) -> u64 {
+    BETWEEN[from as usize][to as usize]
+}
+
+/// The line through `a` and `b`, see [`LINE`]
+pub fn line(a: u8, b: u8) -> u64 {
+    LINE[a as usize][b as usize]
+}
+
 /// Get the distance between two squares (Manhattan distance)
 pub fn manhattan_distance(square1: u8, square2: u8) -> u8 {
-    let file_diff = (get_file(square1) as i8 - get_file(square2) as i8).abs() as u8;
-    let rank_diff = (get_rank(square1) as i8 - get_rank(square2) as i8).abs() as u8;
+    let file_diff = (get_file(square1) as i8 - get_file(square2) as i8).unsigned_abs();
+    let rank_diff = (get_rank(square1) as i8 - get_rank(square2) as i8).unsigned_abs();
     file_diff + rank_diff
 }
 
 /// Get the maximum distance between two squares (Chebyshev distance)
 pub fn king_distance(square1: u8, square2: u8) -> u8 {
-    let file_diff = (get_file(square1) as i8 - get_file(square2) as i8).abs() as u8;
-    let rank_diff = (get_rank(square1) as i8 - get_rank(square2) as i8).abs() as u8;
+    let file_diff = (get_file(square1) as i8 - get_file(square2) as i8).unsigned_abs();
+    let rank_diff = (get_rank(square1) as i8 - get_rank(square2) as i8).unsigned_abs();
     file_diff.max(rank_diff)
 }
 
-/// Convert standard algebraic notation to coordinate notation
-/// Examples: "Nf3" -> "g1f3", "Ke2" -> "e1e2", "Qxd5" -> "d1d5"
+/// Convert standard algebraic notation to coordinate notation, `None` unless it names
+/// a legal move. Castling gives the king's move, wherever the king and rook started
+/// Examples: "Nf3" -> "g1f3", "Ke2" -> "e1e2", "Qxd5" -> "d1d5", "O-O" -> "e1g1"
 pub fn algebraic_to_coordinate(
     algebraic: &str,
     board: &crate::board::Board,
     color: crate::piece::Color,
 ) -> Option<String> {
-    use crate::moves::Moves;
-    use crate::piece::Piece;
-
-    let algebraic = algebraic.trim();
-
-    // Handle castling
-    match algebraic.to_lowercase().as_str() {
-        "o-o" | "0-0" => {
-            return match color {
-                crate::piece::Color::White => Some("e1g1".to_string()),
-                crate::piece::Color::Black => Some("e8g8".to_string()),
-            };
-        }
-        "o-o-o" | "0-0-0" => {
-            return match color {
-                crate::piece::Color::White => Some("e1c1".to_string()),
-                crate::piece::Color::Black => Some("e8c8".to_string()),
-            };
-        }
-        _ => {}
-    }
+    let mv = san_to_move(algebraic, board, color)?;
+    Some(format!("{}{}", u8_to_pos(mv.from), u8_to_pos(mv.to)))
+}
 
-    // Parse the move
-    let mut chars: Vec<char> = algebraic.chars().collect();
-    if chars.is_empty() {
-        return None;
-    }
+/// Resolve standard algebraic notation to the matching legal move of `color`
+/// Examples: "Nf3", "exd5+", "e8=Q", "O-O-O", "Rad1!?"
+pub fn san_to_move(
+    algebraic: &str,
+    board: &crate::board::Board,
+    color: crate::piece::Color,
+) -> Option<crate::moves::Move> {
+    let legal_moves = crate::moves::Move::generate_legal_moves(board, color);
+    crate::notation::MoveParser::with_moves(board, legal_moves)
+        .parse(algebraic)
+        .ok()
+}
 
-    // Remove check/checkmate symbols
-    if chars.last() == Some(&'+') || chars.last() == Some(&'#') {
-        chars.pop();
-    }
+/// Convert a legal move to standard algebraic notation (e.g., "Nbd2", "exd5", "O-O", "Qxf7#")
+/// The board must be the position before the move is played
+pub fn move_to_san(board: &crate::board::Board, mv: &crate::moves::Move) -> String {
+    use crate::moves::{Move, MoveType};
+    use crate::piece::Color;
 
-    // Parse promotion (e.g., "e8=Q")
-    let promotion = if chars.len() >= 3 && chars[chars.len() - 2] == '=' {
-        let promo_char = chars[chars.len() - 1];
-        chars.drain(chars.len() - 2..);
-        match promo_char {
-            'Q' => Some(Piece::Queen),
-            'R' => Some(Piece::Rook),
-            'B' => Some(Piece::Bishop),
-            'N' => Some(Piece::Knight),
-            _ => None,
-        }
-    } else {
-        None
+    let (piece, color) = match board.get_piece_at(mv.from) {
+        Some(found) => found,
+        None => return mv.to_algebraic(),
     };
 
-    // Determine piece type and target square
-    let (piece_type, target_square, disambiguation) = if chars.len() >= 2
-        && chars[chars.len() - 2].is_ascii_lowercase()
-        && chars[chars.len() - 1].is_ascii_digit()
-    {
-        // Extract target square (last 2 characters)
-        let target_file = chars[chars.len() - 2];
-        let target_rank = chars[chars.len() - 1];
-        let target_str = format!("{}{}", target_file, target_rank);
-        let target_square = pos_to_u8(&target_str)?;
-
-        // Remove target square from consideration
-        chars.drain(chars.len() - 2..);
-
-        // Remove capture symbol if present
-        if chars.last() == Some(&'x') {
-            chars.pop();
-        }
+    let mut san = String::new();
 
-        // Determine piece and disambiguation
-        if chars.is_empty() {
-            // Pawn move (no piece symbol)
-            (Piece::Pawn, target_square, None)
+    if mv.move_type == MoveType::Castle {
+        // The king lands on the g-file castling short, even from g1 in Chess960
+        if get_file(mv.to) == 6 {
+            san.push_str("O-O");
         } else {
-            // First character is piece type
-            let piece_char = chars[0];
-            let piece = match piece_char {
-                'K' => Piece::King,
-                'Q' => Piece::Queen,
-                'R' => Piece::Rook,
-                'B' => Piece::Bishop,
-                'N' => Piece::Knight,
-                _ => return None, // Invalid piece
-            };
-
-            // Rest is disambiguation (file or rank)
-            let disambiguation = if chars.len() > 1 {
-                Some(chars[1..].iter().collect::<String>())
-            } else {
-                None
-            };
-
-            (piece, target_square, disambiguation)
+            san.push_str("O-O-O");
         }
     } else {
-        return None;
-    };
-
-    // Find all legal moves that match the criteria
-    let legal_moves = Moves::generate_legal_moves(board, color);
-    let mut matching_moves = Vec::new();
-
-    for mv in legal_moves {
-        // Check if this move goes to the target square
-        if mv.to != target_square {
-            continue;
-        }
-
-        // Check if the piece type matches
-        if let Some((piece, _)) = board.get_piece_at(mv.from) {
-            if piece != piece_type {
-                continue;
+        if piece == Piece::Pawn {
+            if mv.is_capture() {
+                san.push((get_file(mv.from) + b'a') as char);
             }
         } else {
-            continue;
-        }
-
-        // Check promotion
-        match (promotion, &mv.move_type) {
-            (Some(promo_piece), crate::moves::MoveType::Promotion { piece })
-            | (Some(promo_piece), crate::moves::MoveType::PromotionCapture { piece }) => {
-                if promo_piece != *piece {
-                    continue;
+            san.push(crate::piece::piece_to_char(piece, Color::White));
+
+            // Other pieces of the same type that can reach the same square
+            let rivals: Vec<Move> = Move::generate_legal_moves(board, color)
+                .into_iter()
+                .filter(|other| {
+                    other.to == mv.to
+                        && other.from != mv.from
+                        && board.get_piece_at(other.from) == Some((piece, color))
+                })
+                .collect();
+
+            if !rivals.is_empty() {
+                let file_unique = rivals.iter().all(|o| !same_file(o.from, mv.from));
+                let rank_unique = rivals.iter().all(|o| !same_rank(o.from, mv.from));
+                if file_unique {
+                    san.push((get_file(mv.from) + b'a') as char);
+                } else if rank_unique {
+                    san.push((get_rank(mv.from) + b'1') as char);
+                } else {
+                    san.push_str(&u8_to_pos(mv.from));
                 }
             }
-            (None, crate::moves::MoveType::Promotion { .. })
-            | (None, crate::moves::MoveType::PromotionCapture { .. }) => {
-                continue; // Promotion expected but not specified
-            }
-            (Some(_), _) => continue, // Promotion specified but move is not promotion
-            (None, _) => {}           // No promotion, any non-promotion move is fine
         }
 
-        matching_moves.push(mv);
-    }
+        if mv.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&u8_to_pos(mv.to));
 
-    // Handle disambiguation
-    if let Some(ref disambig) = disambiguation {
-        matching_moves.retain(|mv| {
-            let from_file = (mv.from % 8) as u8 + b'a';
-            let from_rank = (mv.from / 8) + 1;
-
-            if disambig.len() == 1 {
-                let disambig_char = disambig.chars().next().unwrap();
-                if disambig_char.is_ascii_lowercase() {
-                    // File disambiguation
-                    from_file == disambig_char as u8
-                } else if disambig_char.is_ascii_digit() {
-                    // Rank disambiguation
-                    from_rank == disambig_char.to_digit(10).unwrap() as u8
-                } else {
-                    false
-                }
-            } else if disambig.len() == 2 {
-                // Full square disambiguation
-                let from_square_str = format!("{}{}", from_file as char, from_rank);
-                from_square_str == *disambig
-            } else {
-                false
-            }
-        });
+        if let MoveType::Promotion { piece: promoted }
+        | MoveType::PromotionCapture { piece: promoted } = mv.move_type
+        {
+            san.push('=');
+            san.push(crate::piece::piece_to_char(promoted, Color::White));
+        }
     }
 
-    // Should have exactly one matching move
-    if matching_moves.len() == 1 {
-        let mv = matching_moves[0];
-        let from_str = u8_to_pos(mv.from);
-        let to_str = u8_to_pos(mv.to);
-        Some(format!("{}{}", from_str, to_str))
-    } else {
-        None // Ambiguous or no legal move found
+    // Check and checkmate suffixes
+    let mut after = *board;
+    after.make_move(mv);
+    let enemy_color = color.opposite();
+    if Move::is_checkmate(&after, enemy_color) {
+        san.push('#');
+    } else if Move::is_in_check(&after, enemy_color) {
+        san.push('+');
     }
+
+    san
 }
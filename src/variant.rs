@@ -0,0 +1,209 @@
+use crate::attacks::king_attacks;
+use crate::board::Board;
+use crate::game::{GameResult, TerminationReason};
+use crate::movelist::MoveList;
+use crate::moves::Move;
+use crate::piece::{Color, Piece};
+
+/// A rule set that move generation, legality and game termination dispatch through.
+/// Every hook defaults to standard chess, so a variant only overrides what it changes
+pub trait Rules: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Legal moves for `color`
+    fn legal_moves(&self, board: &Board, color: Color) -> MoveList {
+        Move::generate_legal_moves(board, color)
+    }
+
+    fn is_legal(&self, board: &Board, color: Color, mv: &Move) -> bool {
+        self.legal_moves(board, color).contains(mv)
+    }
+
+    /// Whether `color` has any legal move, which variants can answer without listing them
+    fn has_legal_move(&self, board: &Board, color: Color) -> bool {
+        !self.legal_moves(board, color).is_empty()
+    }
+
+    fn is_in_check(&self, board: &Board, color: Color) -> bool {
+        Move::is_in_check(board, color)
+    }
+
+    /// Play a move already known to be legal
+    fn make_move(&self, board: &mut Board, mv: &Move) {
+        board.make_move(mv);
+    }
+
+    /// Decisive result or stalemate with `color` to move, judged from the position alone.
+    /// Draws that depend on the game history or move counters are left to the caller
+    fn outcome(&self, board: &Board, color: Color) -> Option<GameResult> {
+        if self.has_legal_move(board, color) {
+            return None;
+        }
+        Some(if self.is_in_check(board, color) {
+            GameResult::loss_for(color, TerminationReason::Checkmate)
+        } else {
+            GameResult::Draw(TerminationReason::Stalemate)
+        })
+    }
+
+    fn is_insufficient_material(&self, board: &Board) -> bool {
+        board.is_insufficient_material()
+    }
+}
+
+/// Standard chess (FIDE rules)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Standard;
+
+impl Rules for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+
+    fn is_legal(&self, board: &Board, color: Color, mv: &Move) -> bool {
+        board.to_move == (color == Color::White) && board.is_legal(mv)
+    }
+
+    fn has_legal_move(&self, board: &Board, color: Color) -> bool {
+        Move::has_legal_move(board, color)
+    }
+}
+
+/// Atomic chess: a capture explodes the capturing piece, the captured piece and every
+/// non-pawn piece next to the capture square. Exploding the enemy king wins, so kings
+/// may not capture and touching kings cannot give check
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Atomic;
+
+impl Atomic {
+    fn has_king(board: &Board, color: Color) -> bool {
+        board.get_bb(Piece::King, color) != 0
+    }
+}
+
+impl Rules for Atomic {
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn legal_moves(&self, board: &Board, color: Color) -> MoveList {
+        let enemy = color.opposite();
+        let mut moves = Move::generate_all_moves(board, color);
+        moves.retain(|mv| {
+            if mv.is_capture() && matches!(board.get_piece_at(mv.from), Some((Piece::King, _))) {
+                return false;
+            }
+            let mut after = *board;
+            self.make_move(&mut after, mv);
+            Self::has_king(&after, color)
+                && (!Self::has_king(&after, enemy) || !self.is_in_check(&after, color))
+        });
+        moves
+    }
+
+    fn is_in_check(&self, board: &Board, color: Color) -> bool {
+        let own_king = board.get_bb(Piece::King, color);
+        let enemy_king = board.get_bb(Piece::King, color.opposite());
+        if own_king == 0 || enemy_king == 0 {
+            return false;
+        }
+        let kings_touch = king_attacks(own_king.trailing_zeros() as u8) & enemy_king != 0;
+        !kings_touch && Move::is_in_check(board, color)
+    }
+
+    fn make_move(&self, board: &mut Board, mv: &Move) {
+        board.make_move(mv);
+        if !mv.is_capture() {
+            return;
+        }
+
+        // The capturing piece goes up with everything but the pawns around it
+        if let Some((piece, color)) = board.get_piece_at(mv.to) {
+            board.remove_piece(piece, color, mv.to);
+        }
+        let mut blast = king_attacks(mv.to);
+        while blast != 0 {
+            let square = blast.trailing_zeros() as u8;
+            blast &= blast - 1;
+            if let Some((piece, color)) = board.get_piece_at(square)
+                && piece != Piece::Pawn
+            {
+                let kept = board.castling_rights_kept(square);
+                board.remove_piece(piece, color, square);
+                board.castling_rights &= kept;
+            }
+        }
+        board.castling_rights &= board.castling_rights_kept(mv.to);
+        board.refresh_hash();
+    }
+
+    fn outcome(&self, board: &Board, color: Color) -> Option<GameResult> {
+        if !Self::has_king(board, color) {
+            return Some(GameResult::loss_for(color, TerminationReason::Explosion));
+        }
+        if !Self::has_king(board, color.opposite()) {
+            return Some(GameResult::loss_for(
+                color.opposite(),
+                TerminationReason::Explosion,
+            ));
+        }
+        if self.has_legal_move(board, color) {
+            return None;
+        }
+        Some(if self.is_in_check(board, color) {
+            GameResult::loss_for(color, TerminationReason::Checkmate)
+        } else {
+            GameResult::Draw(TerminationReason::Stalemate)
+        })
+    }
+
+    /// A king cannot capture, so only bare kings or a lone minor piece cannot force a win
+    fn is_insufficient_material(&self, board: &Board) -> bool {
+        let kings = board.white_king | board.black_king;
+        let others = board.get_all_occupied() & !kings;
+        let minors =
+            board.white_knights | board.black_knights | board.white_bishops | board.black_bishops;
+        others.count_ones() == 0 || (others.count_ones() == 1 && others & minors != 0)
+    }
+}
+
+/// Three-check: standard chess, except that delivering a third check also wins
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreeCheck;
+
+impl Rules for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "Three-check"
+    }
+
+    fn make_move(&self, board: &mut Board, mv: &Move) {
+        let color = if board.to_move {
+            Color::White
+        } else {
+            Color::Black
+        };
+        board.make_move(mv);
+        if Move::is_in_check(board, color.opposite()) {
+            match color {
+                Color::White => board.white_checks += 1,
+                Color::Black => board.black_checks += 1,
+            }
+        }
+    }
+
+    fn outcome(&self, board: &Board, color: Color) -> Option<GameResult> {
+        if board.white_checks >= 3 {
+            return Some(GameResult::WhiteWins(TerminationReason::ThreeChecks));
+        }
+        if board.black_checks >= 3 {
+            return Some(GameResult::BlackWins(TerminationReason::ThreeChecks));
+        }
+        Standard.outcome(board, color)
+    }
+
+    /// Even a lone minor piece can keep giving checks, so only bare kings are a dead draw
+    fn is_insufficient_material(&self, board: &Board) -> bool {
+        let kings = board.white_king | board.black_king;
+        board.get_all_occupied() & !kings == 0
+    }
+}
@@ -0,0 +1,114 @@
+use crate::game::MoveRecord;
+
+/// Index of a move in a [`VariationTree`]
+pub type NodeId = usize;
+
+/// A move of the tree with the moves tried after it
+#[derive(Debug, Clone)]
+pub struct VariationNode {
+    pub record: MoveRecord,
+    /// The move before, `None` for a first move
+    pub parent: Option<NodeId>,
+    /// Replies, the mainline first and side-lines after it
+    pub children: Vec<NodeId>,
+    /// PGN comment written before the move, kept for the first move of a game or of a
+    /// side-line
+    pub starting_comment: Option<String>,
+    /// PGN comment written after the move
+    pub comment: Option<String>,
+    /// Numeric annotation glyphs, e.g. 1 for "!" or 4 for "??"
+    pub nags: Vec<u8>,
+}
+
+/// Every move tried in a game, as a tree of variations growing from the initial
+/// position. At each position the first move continues the mainline
+#[derive(Debug, Clone, Default)]
+pub struct VariationTree {
+    nodes: Vec<VariationNode>,
+    /// The first moves, which have no parent
+    roots: Vec<NodeId>,
+}
+
+impl VariationTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, id: NodeId) -> &VariationNode {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut VariationNode {
+        &mut self.nodes[id]
+    }
+
+    /// The moves tried after `parent`, or the first moves for `None`
+    pub fn children(&self, parent: Option<NodeId>) -> &[NodeId] {
+        match parent {
+            Some(id) => &self.nodes[id].children,
+            None => &self.roots,
+        }
+    }
+
+    fn children_mut(&mut self, parent: Option<NodeId>) -> &mut Vec<NodeId> {
+        match parent {
+            Some(id) => &mut self.nodes[id].children,
+            None => &mut self.roots,
+        }
+    }
+
+    /// Add `record` after `parent`, as the mainline if it is the first move tried there
+    /// and as a new side-line otherwise. A move already in the tree is reused
+    pub fn add(&mut self, parent: Option<NodeId>, record: MoveRecord) -> NodeId {
+        if let Some(&id) = self
+            .children(parent)
+            .iter()
+            .find(|&&id| self.nodes[id].record.mv == record.mv)
+        {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(VariationNode {
+            record,
+            parent,
+            children: Vec::new(),
+            starting_comment: None,
+            comment: None,
+            nags: Vec::new(),
+        });
+        self.children_mut(parent).push(id);
+        id
+    }
+
+    /// The moves from the initial position up to and including `id`
+    pub fn path(&self, id: Option<NodeId>) -> Vec<NodeId> {
+        let mut path: Vec<NodeId> =
+            core::iter::successors(id, |&id| self.nodes[id].parent).collect();
+        path.reverse();
+        path
+    }
+
+    /// The mainline continuation after `id`, following first moves to the end
+    pub fn mainline(&self, id: Option<NodeId>) -> Vec<NodeId> {
+        core::iter::successors(self.children(id).first().copied(), |&id| {
+            self.nodes[id].children.first().copied()
+        })
+        .collect()
+    }
+
+    /// Make the line through `id` the mainline: at every position on the way there
+    /// its move is moved in front of the others, which keep their order
+    pub fn promote(&mut self, id: NodeId) {
+        for id in self.path(Some(id)) {
+            let parent = self.nodes[id].parent;
+            let siblings = self.children_mut(parent);
+            if let Some(index) = siblings.iter().position(|&sibling| sibling == id) {
+                siblings[..=index].rotate_right(1);
+            }
+        }
+    }
+}
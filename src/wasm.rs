@@ -0,0 +1,114 @@
+use crate::fen::to_fen;
+use crate::game::Game;
+use crate::json::{AnalysisJson, PositionJson};
+use crate::moves::Move;
+use crate::search::{Search, SearchInfo, SearchLimits};
+use wasm_bindgen::prelude::*;
+
+/// A game exposed to JavaScript. Moves go in and out as UCI strings ("e2e4", "e7e8q")
+#[wasm_bindgen]
+pub struct WasmGame {
+    game: Game,
+}
+
+#[wasm_bindgen]
+impl WasmGame {
+    /// A game from the standard starting position
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmGame {
+        WasmGame { game: Game::new() }
+    }
+
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn from_fen(fen: &str) -> Result<WasmGame, JsError> {
+        let game = Game::from_fen(fen)?;
+        Ok(WasmGame { game })
+    }
+
+    /// The current position
+    pub fn fen(&self) -> String {
+        to_fen(self.game.get_board())
+    }
+
+    /// "white" or "black"
+    #[wasm_bindgen(js_name = sideToMove)]
+    pub fn side_to_move(&self) -> String {
+        self.game.get_current_player().to_string().to_lowercase()
+    }
+
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        self.game
+            .get_legal_moves()
+            .iter()
+            .map(Move::to_uci)
+            .collect()
+    }
+
+    /// Play a move given in UCI notation, failing if it is not legal
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, uci: &str) -> Result<(), JsError> {
+        self.game
+            .try_move_algebraic(uci)
+            .map_err(|e| JsError::new(&format!("{} '{}'", e, uci)))
+    }
+
+    /// How the game ended, e.g. "White wins by checkmate", or `undefined` while it is
+    /// still in progress
+    pub fn result(&self) -> Option<String> {
+        let result = self.game.result()?;
+        Some(match result.winner() {
+            Some(color) => format!("{} wins by {}", color, result.reason()),
+            None => format!("Draw by {}", result.reason()),
+        })
+    }
+
+    /// The current position as a [`PositionJson`] document
+    #[wasm_bindgen(js_name = positionJson)]
+    pub fn position_json(&self) -> String {
+        serde_json::to_string(&PositionJson::new(self.game.get_board())).unwrap_or_default()
+    }
+
+    /// Search to `depth` plies and return an [`AnalysisJson`] document
+    #[wasm_bindgen(js_name = analysisJson)]
+    pub fn analysis_json(&self, depth: u8) -> String {
+        let analysis =
+            AnalysisJson::search(self.game.get_board(), SearchLimits::fixed_depth(depth));
+        serde_json::to_string(&analysis).unwrap_or_default()
+    }
+
+    pub fn pgn(&self) -> String {
+        self.game.to_pgn()
+    }
+
+    /// Search the current position to `depth` plies and return the best move, or
+    /// `undefined` when there is none. After every iteration `on_info`, if given, is
+    /// called with the depth, the score (e.g. "cp 35" or "mate 2") and the principal
+    /// variation as a space-separated string
+    pub fn search(&self, depth: u8, on_info: Option<js_sys::Function>) -> Option<String> {
+        let mut search = Search::default();
+        let mut report = |info: &SearchInfo| {
+            if let Some(callback) = &on_info {
+                let pv: Vec<String> = info.pv.iter().map(Move::to_uci).collect();
+                let _ = callback.call3(
+                    &JsValue::NULL,
+                    &JsValue::from(info.depth),
+                    &JsValue::from(info.score().to_string()),
+                    &JsValue::from(pv.join(" ")),
+                );
+            }
+        };
+        let result = search.go_with_observer(
+            self.game.get_board(),
+            SearchLimits::fixed_depth(depth),
+            &mut report,
+        );
+        result.best_move.map(|mv| mv.to_uci())
+    }
+}
+
+impl Default for WasmGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
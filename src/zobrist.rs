@@ -0,0 +1,135 @@
+use crate::board::Board;
+use crate::piece::{Color, Piece};
+
+/// SplitMix64 step, usable at compile time to generate the key tables
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+struct Keys {
+    pieces: [[[u64; 64]; 6]; 2],
+    castling: [u64; 16],
+    en_passant: [u64; 8],
+    side: u64,
+}
+
+const fn generate_keys() -> Keys {
+    let mut keys = Keys {
+        pieces: [[[0; 64]; 6]; 2],
+        castling: [0; 16],
+        en_passant: [0; 8],
+        side: 0,
+    };
+    let mut state = 0x0A5A_5A5A_0123_4567;
+
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < 6 {
+            let mut square = 0;
+            while square < 64 {
+                let (next, key) = splitmix64(state);
+                state = next;
+                keys.pieces[color][piece][square] = key;
+                square += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+
+    let mut i = 0;
+    while i < 16 {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys.castling[i] = key;
+        i += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys.en_passant[file] = key;
+        file += 1;
+    }
+
+    keys.side = splitmix64(state).1;
+    keys
+}
+
+static KEYS: Keys = generate_keys();
+
+fn piece_index(piece: Piece) -> usize {
+    match piece {
+        Piece::Pawn => 0,
+        Piece::Knight => 1,
+        Piece::Bishop => 2,
+        Piece::Rook => 3,
+        Piece::Queen => 4,
+        Piece::King => 5,
+    }
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+/// Key for a piece of the given color standing on a square
+pub fn piece_key(piece: Piece, color: Color, square: u8) -> u64 {
+    KEYS.pieces[color_index(color)][piece_index(piece)][square as usize]
+}
+
+/// Key for a castling rights mask (4 bits)
+pub fn castling_key(castling_rights: u8) -> u64 {
+    KEYS.castling[(castling_rights & 0b1111) as usize]
+}
+
+/// Key for an en passant target on the given file
+pub fn en_passant_key(file: u8) -> u64 {
+    KEYS.en_passant[(file & 7) as usize]
+}
+
+/// Key toggled when black is to move
+pub fn side_key() -> u64 {
+    KEYS.side
+}
+
+/// Compute the Zobrist hash of a position from scratch
+pub fn hash(board: &Board) -> u64 {
+    let mut hash = 0;
+
+    for color in [Color::White, Color::Black] {
+        for piece in [
+            Piece::Pawn,
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Rook,
+            Piece::Queen,
+            Piece::King,
+        ] {
+            for square in board.get_piece_squares(color, piece) {
+                hash ^= piece_key(piece, color, square);
+            }
+        }
+    }
+
+    hash ^= castling_key(board.castling_rights);
+
+    if let Some(square) = board.en_passant {
+        hash ^= en_passant_key(square % 8);
+    }
+
+    if !board.to_move {
+        hash ^= side_key();
+    }
+
+    hash
+}